@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS project_processes (
+    id TEXT PRIMARY KEY NOT NULL,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    command TEXT NOT NULL,
+    cwd TEXT, -- Relative to the project path, NULL means project root
+    env TEXT NOT NULL DEFAULT '{}', -- JSON object of KEY -> value
+    autostart BOOLEAN NOT NULL DEFAULT 0,
+    restart_policy TEXT NOT NULL DEFAULT 'never', -- 'never', 'on-failure', 'always'
+    max_restarts INTEGER, -- NULL means unlimited
+    backoff_ms INTEGER NOT NULL DEFAULT 1000,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER IF NOT EXISTS update_project_processes_timestamp
+AFTER UPDATE ON project_processes
+BEGIN
+    UPDATE project_processes SET updated_at = CURRENT_TIMESTAMP WHERE id = old.id;
+END;
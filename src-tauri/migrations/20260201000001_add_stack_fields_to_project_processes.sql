@@ -0,0 +1,4 @@
+-- Multi-service stacks: start ordering and readiness checks
+ALTER TABLE project_processes ADD COLUMN depends_on TEXT NOT NULL DEFAULT '[]'; -- JSON array of definition names
+ALTER TABLE project_processes ADD COLUMN ready_port INTEGER; -- Ready once this TCP port accepts connections
+ALTER TABLE project_processes ADD COLUMN ready_pattern TEXT; -- Ready once a log line matches this regex
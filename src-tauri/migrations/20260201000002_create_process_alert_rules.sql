@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS process_alert_rules (
+    id TEXT PRIMARY KEY NOT NULL,
+    project_id TEXT NOT NULL,
+    label TEXT,
+    pattern TEXT NOT NULL, -- Regex evaluated against each output line, ANSI colors stripped
+    severity TEXT NOT NULL DEFAULT 'error', -- 'info', 'success', 'warning', 'error'
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS terminal_profiles (
+    id TEXT PRIMARY KEY NOT NULL,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    program TEXT NOT NULL,
+    args TEXT NOT NULL DEFAULT '[]', -- JSON array
+    env TEXT NOT NULL DEFAULT '{}', -- JSON object of KEY -> value, applied over the project env
+    startup_script TEXT, -- Typed into the shell once it starts
+    working_subdir TEXT, -- Relative to the project path, NULL means project root
+    is_default BOOLEAN NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER IF NOT EXISTS update_terminal_profiles_timestamp
+AFTER UPDATE ON terminal_profiles
+BEGIN
+    UPDATE terminal_profiles SET updated_at = CURRENT_TIMESTAMP WHERE id = old.id;
+END;
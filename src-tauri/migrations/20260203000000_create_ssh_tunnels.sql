@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS ssh_tunnels (
+    id TEXT PRIMARY KEY NOT NULL,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    host TEXT NOT NULL, -- Alias from ~/.ssh/config
+    kind TEXT NOT NULL DEFAULT 'local', -- 'local' (-L), 'remote' (-R), 'dynamic' (-D, SOCKS)
+    bind_address TEXT, -- NULL lets ssh pick its default (loopback for -L/-D)
+    bind_port INTEGER NOT NULL,
+    target_host TEXT, -- Unused for dynamic forwards
+    target_port INTEGER,
+    connection_id TEXT, -- ProjectConnection that needs this tunnel up before connecting
+    auto_reconnect BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+    FOREIGN KEY (connection_id) REFERENCES project_connections(id) ON DELETE SET NULL
+);
+
+CREATE TRIGGER IF NOT EXISTS update_ssh_tunnels_timestamp
+AFTER UPDATE ON ssh_tunnels
+BEGIN
+    UPDATE ssh_tunnels SET updated_at = CURRENT_TIMESTAMP WHERE id = old.id;
+END;
@@ -0,0 +1,2 @@
+-- Passwords live in the system keychain; details keeps everything else
+ALTER TABLE project_connections ADD COLUMN password_ref TEXT; -- Vault key of the password, NULL when there is none
@@ -3,8 +3,7 @@ pub mod modules;
 pub mod shared;
 
 use tauri::Manager;
-use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::sync::Arc;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -28,13 +27,26 @@ pub fn run() {
       // Note: app_data_dir() returns a Result, need to unwrap or handle
       let app_data_dir = app_handle.path().app_data_dir().expect("failed to get app data dir");
       
-      // Initialize Terminal Sessions State
-      let terminal_sessions: modules::terminal::models::TerminalSessions = Arc::new(Mutex::new(HashMap::new()));
-      app_handle.manage(terminal_sessions);
-
       // Initialize Process State
-      let process_state: modules::processes::models::ProcessState = Arc::new(Mutex::new(HashMap::new()));
-      app_handle.manage(process_state);
+      // Spilled history from a previous run belongs to processes that no longer exist
+      let process_log_dir = app_data_dir.join("process-logs");
+      let _ = std::fs::remove_dir_all(&process_log_dir);
+      let process_state = modules::processes::models::ProcessState::new(Some(process_log_dir));
+      app_handle.manage(process_state.clone());
+
+      // ~/.ssh/config, with backups kept outside ~/.ssh
+      let ssh = modules::ssh::service::SshService::new(app_data_dir.join("ssh-config-backups"));
+      app_handle.manage(ssh.clone());
+
+      // Interactive SSH sessions
+      app_handle.manage(modules::ssh::session::SshSessionService::new(ssh.clone()));
+      app_handle.manage(modules::ssh::sftp::SftpService::new(ssh));
+
+      // Running database panel queries, cancellable by id
+      app_handle.manage(modules::databases::query::QueryRunner::new());
+
+      // Asciicast recordings of terminals and processes
+      app_handle.manage(modules::terminal::recording::RecordingService::new(app_data_dir.join("recordings")));
 
       tauri::async_runtime::block_on(async {
           let pool = database::init_pool(&app_data_dir).await.expect("failed to init database");
@@ -45,6 +57,44 @@ pub fn run() {
               .await
               .expect("failed to run migrations");
 
+          // Older versions kept database passwords in plain text. Retried on every start
+          // until the keychain accepts them; in the background, as the keychain may prompt.
+          let databases = modules::databases::service::DatabaseService::new(pool.clone());
+          tauri::async_runtime::spawn(async move {
+              if let Err(e) = databases.migrate_plaintext_passwords().await {
+                  println!("ERROR: Failed to move database passwords to the keychain: {}", e);
+              }
+          });
+
+          // Process Supervisor (restart policies / autostart)
+          let supervisor = modules::processes::supervisor::ProcessSupervisor::new(
+              process_state,
+              pool.clone(),
+              Arc::new(app_handle.clone()),
+          );
+          app_handle.manage(supervisor.clone());
+          tauri::async_runtime::spawn(supervisor.run());
+
+          // Per-process CPU / memory sampling
+          let sampler = modules::processes::stats::StatsSampler::new(
+              app_handle.state::<modules::processes::models::ProcessState>().inner().clone(),
+              Arc::new(app_handle.clone()),
+          );
+          tauri::async_runtime::spawn(sampler.run());
+
+          // SSH port forwards
+          let tunnels = modules::ssh::tunnels::TunnelManager::new(pool.clone(), Arc::new(app_handle.clone()));
+          app_handle.manage(tunnels.clone());
+          tauri::async_runtime::spawn(tunnels.run());
+
+          // Database panel connection pools
+          let connections = modules::databases::pool::ConnectionManager::new(pool.clone());
+          app_handle.manage(connections.clone());
+          tauri::async_runtime::spawn(connections.run());
+
+          // Built-in project shells
+          app_handle.manage(modules::terminal::service::TerminalService::new(pool.clone()));
+
           app_handle.manage(pool);
       });
 
@@ -53,6 +103,36 @@ pub fn run() {
     .invoke_handler(tauri::generate_handler![
         modules::ssh::commands::get_ssh_hosts,
         modules::ssh::commands::add_ssh_host,
+        modules::ssh::commands::update_ssh_host,
+        modules::ssh::commands::delete_ssh_host,
+        modules::ssh::commands::ssh_connect,
+        modules::ssh::commands::ssh_write,
+        modules::ssh::commands::ssh_resize,
+        modules::ssh::commands::ssh_disconnect,
+        modules::ssh::commands::list_ssh_sessions,
+        modules::ssh::commands::trust_ssh_host,
+        modules::ssh::commands::create_tunnel,
+        modules::ssh::commands::get_tunnels,
+        modules::ssh::commands::update_tunnel,
+        modules::ssh::commands::delete_tunnel,
+        modules::ssh::commands::start_tunnel,
+        modules::ssh::commands::stop_tunnel,
+        modules::ssh::commands::get_tunnel_states,
+        modules::ssh::commands::start_connection_tunnels,
+        modules::ssh::commands::list_ssh_keys,
+        modules::ssh::commands::generate_ssh_key,
+        modules::ssh::commands::inspect_ssh_key,
+        modules::ssh::commands::get_public_key,
+        modules::ssh::commands::add_key_to_agent,
+        modules::ssh::commands::remove_key_from_agent,
+        modules::ssh::commands::sftp_list_dir,
+        modules::ssh::commands::sftp_stat,
+        modules::ssh::commands::sftp_download,
+        modules::ssh::commands::sftp_upload,
+        modules::ssh::commands::sftp_rename,
+        modules::ssh::commands::sftp_delete,
+        modules::ssh::commands::sftp_mkdir,
+        modules::ssh::commands::sftp_disconnect,
         modules::projects::commands::create_project,
         modules::projects::commands::list_projects,
         modules::projects::commands::get_project,
@@ -87,22 +167,71 @@ pub fn run() {
         modules::terminal::commands::spawn_shell,
         modules::terminal::commands::write_to_shell,
         modules::terminal::commands::resize_shell,
+        modules::terminal::commands::close_shell,
+        modules::terminal::commands::list_shells,
+        modules::terminal::commands::attach_shell,
+        modules::terminal::commands::get_shell_profiles,
+        modules::terminal::commands::create_shell_profile,
+        modules::terminal::commands::update_shell_profile,
+        modules::terminal::commands::delete_shell_profile,
+        modules::terminal::commands::detect_shells,
+        modules::terminal::commands::start_recording,
+        modules::terminal::commands::stop_recording,
+        modules::terminal::commands::list_recordings,
+        modules::terminal::commands::get_recording,
+        modules::terminal::commands::delete_recording,
         modules::terminal::commands::open_external_terminal,
         // Process Manager
         modules::processes::commands::start_process,
         modules::processes::commands::write_to_process,
         modules::processes::commands::resize_process,
         modules::processes::commands::stop_process,
+        modules::processes::commands::remove_process,
         modules::processes::commands::get_process_history,
+        modules::processes::commands::set_process_history_limit,
+        modules::processes::commands::set_process_kill_grace,
         modules::processes::commands::get_active_processes,
+        modules::processes::commands::find_port_owner,
+        modules::processes::commands::kill_port_owner,
+        modules::processes::commands::create_process_definition,
+        modules::processes::commands::get_process_definitions,
+        modules::processes::commands::update_process_definition,
+        modules::processes::commands::delete_process_definition,
+        modules::processes::commands::start_process_definition,
+        modules::processes::commands::stop_process_definition,
+        modules::processes::commands::open_project_processes,
+        modules::processes::commands::import_procfile,
+        modules::processes::commands::import_stack_file,
+        modules::processes::commands::start_stack,
+        modules::processes::commands::stop_stack,
+        modules::processes::commands::create_alert_rule,
+        modules::processes::commands::get_alert_rules,
+        modules::processes::commands::update_alert_rule,
+        modules::processes::commands::delete_alert_rule,
         // Database Manager
         modules::databases::commands::create_connection,
         modules::databases::commands::get_connections,
         modules::databases::commands::delete_connection,
         modules::databases::commands::test_connection,
         modules::databases::commands::execute_query,
+        modules::databases::commands::cancel_query,
         modules::databases::commands::get_tables,
+        modules::databases::commands::check_db_connection,
+        modules::databases::commands::get_db_connection_states,
+        modules::databases::commands::disconnect_db,
     ])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|app_handle, event| {
+      if let tauri::RunEvent::Exit = event {
+        // Don't leave dev servers holding their ports after the app is gone
+        let state = app_handle.state::<modules::processes::models::ProcessState>();
+        modules::processes::service::ProcessService::new(state.inner().clone())
+            .shutdown(std::time::Duration::from_secs(3));
+        // Nor ssh forwards holding theirs
+        if let Some(tunnels) = app_handle.try_state::<modules::ssh::tunnels::TunnelManager>() {
+            tunnels.stop_all();
+        }
+      }
+    });
 }
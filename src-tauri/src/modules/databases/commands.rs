@@ -1,7 +1,10 @@
-use tauri::{command, State};
+use tauri::{command, AppHandle, State};
 use sqlx::SqlitePool;
 use super::service::DatabaseService;
-use super::models::ProjectConnection;
+use super::pool::ConnectionManager;
+use super::query::{QueryRunner, DEFAULT_MAX_ROWS};
+use super::models::{DbConnectionState, ProjectConnection, QueryStats};
+use crate::shared::events::EventSink;
 
 #[command]
 pub async fn create_connection(
@@ -31,8 +34,10 @@ pub async fn get_connections(
 #[command]
 pub async fn delete_connection(
     pool: State<'_, SqlitePool>,
+    connections: State<'_, ConnectionManager>,
     id: String
 ) -> Result<(), String> {
+    connections.disconnect(&id).await;
     let service = DatabaseService::new(pool.inner().clone());
     service.delete_connection(&id)
         .await
@@ -52,16 +57,36 @@ pub async fn test_connection(
         .map_err(|e| e.to_string())
 }
 
+/// Streams the rows as `query_page` events and returns once the query is done.
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_query(
-    pool: State<'_, SqlitePool>,
-    kind: String,
-    details: String,
+    app_handle: AppHandle,
+    connections: State<'_, ConnectionManager>,
+    runner: State<'_, QueryRunner>,
+    connection_id: String,
+    query_id: String,
     query: String,
+    max_rows: Option<u64>,
     password: Option<String>
-) -> Result<super::models::QueryResult, String> {
-    let service = DatabaseService::new(pool.inner().clone());
-    service.execute_query(&kind, &details, &query, password.as_deref())
+) -> Result<QueryStats, String> {
+    let db = connections.get(&connection_id, password.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    let max_rows = max_rows.unwrap_or(DEFAULT_MAX_ROWS);
+    runner.run(&db, &query_id, &query, max_rows, |page| {
+        app_handle.emit_event("query_page", serde_json::to_value(page).unwrap_or_default());
+    })
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn cancel_query(
+    runner: State<'_, QueryRunner>,
+    query_id: String
+) -> Result<bool, String> {
+    runner.cancel(&query_id)
         .await
         .map_err(|e| e.to_string())
 }
@@ -69,12 +94,37 @@ pub async fn execute_query(
 #[command]
 pub async fn get_tables(
     pool: State<'_, SqlitePool>,
-    kind: String,
-    details: String,
+    connections: State<'_, ConnectionManager>,
+    connection_id: String,
     password: Option<String>
 ) -> Result<Vec<super::models::TableInfo>, String> {
+    let db = connections.get(&connection_id, password.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
     let service = DatabaseService::new(pool.inner().clone());
-    service.get_tables(&kind, &details, password.as_deref())
+    service.get_tables(&db)
         .await
         .map_err(|e| e.to_string())
 }
+
+#[command]
+pub async fn check_db_connection(
+    connections: State<'_, ConnectionManager>,
+    connection_id: String
+) -> Result<DbConnectionState, String> {
+    Ok(connections.health_check(&connection_id).await)
+}
+
+#[command]
+pub fn get_db_connection_states(connections: State<'_, ConnectionManager>) -> Result<Vec<DbConnectionState>, String> {
+    Ok(connections.states())
+}
+
+#[command]
+pub async fn disconnect_db(
+    connections: State<'_, ConnectionManager>,
+    connection_id: String
+) -> Result<(), String> {
+    connections.disconnect(&connection_id).await;
+    Ok(())
+}
@@ -1,4 +1,7 @@
 pub mod models;
 pub mod repository;
+pub mod decode;
+pub mod pool;
+pub mod query;
 pub mod service;
 pub mod commands;
@@ -7,7 +7,8 @@ pub struct ProjectConnection {
     pub project_id: String,
     pub name: String,
     pub kind: String, // 'postgres', 'mysql', 'sqlite'
-    pub details: String, // JSON
+    pub details: String, // JSON, without the password
+    pub password_ref: Option<String>, // Vault key of the password
     #[sqlx(default)]
     pub created_at: String,
     #[sqlx(default)]
@@ -20,9 +21,8 @@ pub struct ConnectionConfig {
     pub port: Option<u16>,
     pub username: Option<String>,
     pub database: String,
-    // Password is sent separately or retrieved from vault, never stored in struct returned to UI usually? 
-    // Actually for config we need it.
-    pub password: Option<String>, 
+    // Only set for unsaved settings; saved connections keep it in the vault
+    pub password: Option<String>,
     pub file_path: Option<String>, // For SQLite
 }
 
@@ -32,9 +32,52 @@ pub struct TableInfo {
     pub schema: Option<String>,
 }
 
+/// Rows streamed from a running query, sent as a `query_page` event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct QueryResult {
+pub struct QueryPage {
+    pub query_id: String,
+    pub result_set: usize, // Index of the statement the rows belong to
     pub columns: Vec<String>,
+    pub column_types: Vec<String>, // Type names as the driver reports them, e.g. "INT4", "TEXT"
     pub rows: Vec<Vec<serde_json::Value>>,
     pub affected_rows: u64,
+    pub done: bool, // Last page of this result set
+    pub truncated: bool, // Set on the last page when this result set hit the row cap
+}
+
+/// Returned once a query has finished, hit its row cap or was cancelled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryStats {
+    pub query_id: String,
+    pub rows_fetched: u64,
+    pub affected_rows: u64,
+    pub result_sets: usize,
+    pub elapsed_ms: u64,
+    pub truncated: bool, // Some result set hit the row cap with more rows left
+    pub cancelled: bool,
+    pub completed_statements: usize, // When cancelled, the statements after these never ran or were cut short
+}
+
+/// An open pool in the connection registry, as the database panel shows it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbConnectionState {
+    pub connection_id: String,
+    pub connected: bool,
+    pub connected_at: Option<u64>, // Unix millis
+    pub idle_secs: u64,
+    pub latency_ms: Option<u64>, // Set by health checks
+    pub error: Option<String>,
+}
+
+impl DbConnectionState {
+    pub fn disconnected(connection_id: &str) -> Self {
+        Self {
+            connection_id: connection_id.to_string(),
+            connected: false,
+            connected_at: None,
+            idle_secs: 0,
+            latency_ms: None,
+            error: None,
+        }
+    }
 }
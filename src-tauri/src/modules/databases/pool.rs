@@ -0,0 +1,334 @@
+use super::models::{DbConnectionState, ProjectConnection};
+use super::repository::DatabaseRepository;
+use crate::modules::vault::service::VaultService;
+use anyhow::{anyhow, Context, Result};
+use sqlx::SqlitePool;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Pools nobody has queried for this long are closed
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_CONNECTIONS: u32 = 4;
+const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A connection pool to one of the user's databases. Cloning shares the pool.
+#[derive(Clone)]
+pub enum DbPool {
+    Sqlite(sqlx::SqlitePool),
+    Postgres(sqlx::PgPool),
+    MySql(sqlx::MySqlPool),
+}
+
+impl DbPool {
+    /// Connects with the settings from a connection's `details` JSON. `password`
+    /// overrides one in the details, which only unsaved settings have.
+    pub async fn connect(kind: &str, details: &str, password: Option<&str>) -> Result<Self> {
+        let config: serde_json::Value = serde_json::from_str(details)?;
+
+        match kind {
+            "postgres" => {
+                use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+                let host = config["host"].as_str().unwrap_or("localhost");
+                let port = config["port"].as_u64().unwrap_or(5432) as u16;
+                let user = config["username"].as_str().unwrap_or("postgres");
+                let db_name = config["database"].as_str().unwrap_or("postgres");
+                let config_pass = config["password"].as_str().unwrap_or("");
+                let pass = password.unwrap_or(config_pass);
+
+                let options = PgConnectOptions::new()
+                    .host(host)
+                    .port(port)
+                    .username(user)
+                    .password(pass)
+                    .database(db_name);
+
+                let pool = PgPoolOptions::new()
+                    .max_connections(MAX_CONNECTIONS)
+                    .acquire_timeout(ACQUIRE_TIMEOUT)
+                    .connect_with(options)
+                    .await?;
+                Ok(DbPool::Postgres(pool))
+            },
+            "mysql" => {
+                use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions};
+
+                let host = config["host"].as_str().unwrap_or("localhost");
+                let port = config["port"].as_u64().unwrap_or(3306) as u16;
+                let user = config["username"].as_str().unwrap_or("root");
+                let db_name = config["database"].as_str().unwrap_or("mysql");
+                let config_pass = config["password"].as_str().unwrap_or("");
+                let pass = password.unwrap_or(config_pass);
+
+                let options = MySqlConnectOptions::new()
+                    .host(host)
+                    .port(port)
+                    .username(user)
+                    .password(pass)
+                    .database(db_name);
+
+                let pool = MySqlPoolOptions::new()
+                    .max_connections(MAX_CONNECTIONS)
+                    .acquire_timeout(ACQUIRE_TIMEOUT)
+                    .connect_with(options)
+                    .await?;
+                Ok(DbPool::MySql(pool))
+            },
+            "sqlite" => {
+                use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+                use std::str::FromStr;
+
+                let path = config["file_path"].as_str().ok_or(anyhow!("Missing file_path"))?;
+                let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path))?;
+
+                let pool = SqlitePoolOptions::new()
+                    .max_connections(MAX_CONNECTIONS)
+                    .acquire_timeout(ACQUIRE_TIMEOUT)
+                    .connect_with(options)
+                    .await?;
+                Ok(DbPool::Sqlite(pool))
+            },
+            _ => Err(anyhow!("Unsupported database type")),
+        }
+    }
+
+    /// Round trip to the server.
+    pub async fn ping(&self) -> Result<()> {
+        match self {
+            DbPool::Sqlite(pool) => { sqlx::query("SELECT 1").execute(pool).await?; }
+            DbPool::Postgres(pool) => { sqlx::query("SELECT 1").execute(pool).await?; }
+            DbPool::MySql(pool) => { sqlx::query("SELECT 1").execute(pool).await?; }
+        }
+        Ok(())
+    }
+
+    pub async fn close(&self) {
+        match self {
+            DbPool::Sqlite(pool) => pool.close().await,
+            DbPool::Postgres(pool) => pool.close().await,
+            DbPool::MySql(pool) => pool.close().await,
+        }
+    }
+}
+
+/// Identifies the settings a pool was opened with, so editing a connection or
+/// entering another password opens a new pool instead of reusing the old one.
+fn settings_key(kind: &str, details: &str, password: Option<&str>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (kind, details, password).hash(&mut hasher);
+    hasher.finish()
+}
+
+struct PoolEntry {
+    pool: DbPool,
+    settings: u64,
+    connected_at: u64,
+    last_used: Instant,
+}
+
+/// Managed state holding one pool per `ProjectConnection`, opened on first use and
+/// closed after `IDLE_TIMEOUT` without queries or on `disconnect`.
+#[derive(Clone)]
+pub struct ConnectionManager {
+    repo: Arc<DatabaseRepository>,
+    pools: Arc<Mutex<HashMap<String, PoolEntry>>>,
+}
+
+impl ConnectionManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            repo: Arc::new(DatabaseRepository::new(pool)),
+            pools: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Closes idle pools until the app exits.
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.evict_idle(IDLE_TIMEOUT).await;
+        }
+    }
+
+    /// The pool for a saved connection, connecting if there is none yet.
+    pub async fn get(&self, connection_id: &str, password: Option<&str>) -> Result<DbPool> {
+        let connection = self.repo.get_connection(connection_id).await?
+            .ok_or_else(|| anyhow!("Connection not found"))?;
+        self.get_for(&connection, password).await
+    }
+
+    /// Uses `password` if given, else the one saved in the vault for the connection.
+    pub async fn get_for(&self, connection: &ProjectConnection, password: Option<&str>) -> Result<DbPool> {
+        let saved = match (password, &connection.password_ref) {
+            (None, Some(password_ref)) => {
+                // The keychain can block on an unlock prompt
+                let password_ref = password_ref.clone();
+                let secret = tokio::task::spawn_blocking(move || VaultService::new("switchboard-app").get_secret(&password_ref)).await?;
+                Some(secret.context("Saved password is missing from the keychain")?)
+            },
+            _ => None,
+        };
+        let password = password.or(saved.as_deref());
+
+        let settings = settings_key(&connection.kind, &connection.details, password);
+        let stale = {
+            let mut pools = self.pools.lock().unwrap();
+            match pools.get_mut(&connection.id) {
+                Some(entry) if entry.settings == settings => {
+                    entry.last_used = Instant::now();
+                    return Ok(entry.pool.clone());
+                }
+                Some(_) => pools.remove(&connection.id).map(|e| e.pool),
+                None => None,
+            }
+        };
+        if let Some(pool) = stale {
+            pool.close().await;
+        }
+
+        // Connect without holding the lock, other connections shouldn't wait on this one
+        let pool = DbPool::connect(&connection.kind, &connection.details, password).await?;
+        let existing = {
+            let mut pools = self.pools.lock().unwrap();
+            match pools.get_mut(&connection.id) {
+                // Another call connected first; keep theirs
+                Some(entry) if entry.settings == settings => {
+                    entry.last_used = Instant::now();
+                    Some(entry.pool.clone())
+                }
+                _ => {
+                    pools.insert(connection.id.clone(), PoolEntry {
+                        pool: pool.clone(),
+                        settings,
+                        connected_at: crate::shared::utils::now_millis(),
+                        last_used: Instant::now(),
+                    });
+                    None
+                }
+            }
+        };
+        match existing {
+            Some(theirs) => {
+                pool.close().await;
+                Ok(theirs)
+            }
+            None => Ok(pool),
+        }
+    }
+
+    fn state_of(connection_id: &str, entry: &PoolEntry) -> DbConnectionState {
+        DbConnectionState {
+            connection_id: connection_id.to_string(),
+            connected: true,
+            connected_at: Some(entry.connected_at),
+            idle_secs: entry.last_used.elapsed().as_secs(),
+            latency_ms: None,
+            error: None,
+        }
+    }
+
+    /// Pings an open pool. A pool that fails the ping is closed, so the next query reconnects.
+    pub async fn health_check(&self, connection_id: &str) -> DbConnectionState {
+        let entry = self.pools.lock().unwrap().get(connection_id)
+            .map(|e| (e.pool.clone(), Self::state_of(connection_id, e)));
+        let Some((pool, mut state)) = entry else {
+            return DbConnectionState::disconnected(connection_id);
+        };
+
+        let started = Instant::now();
+        match pool.ping().await {
+            Ok(()) => state.latency_ms = Some(started.elapsed().as_millis() as u64),
+            Err(e) => {
+                self.disconnect(connection_id).await;
+                state = DbConnectionState::disconnected(connection_id);
+                state.error = Some(e.to_string());
+            }
+        }
+        state
+    }
+
+    pub fn states(&self) -> Vec<DbConnectionState> {
+        self.pools.lock().unwrap()
+            .iter()
+            .map(|(id, entry)| Self::state_of(id, entry))
+            .collect()
+    }
+
+    pub async fn disconnect(&self, connection_id: &str) {
+        let entry = self.pools.lock().unwrap().remove(connection_id);
+        if let Some(entry) = entry {
+            entry.pool.close().await;
+        }
+    }
+
+    /// Closes pools unused for longer than `max_idle`.
+    pub async fn evict_idle(&self, max_idle: Duration) {
+        let idle: Vec<PoolEntry> = {
+            let mut pools = self.pools.lock().unwrap();
+            let ids: Vec<String> = pools.iter()
+                .filter(|(_, e)| e.last_used.elapsed() >= max_idle)
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.iter().filter_map(|id| pools.remove(id)).collect()
+        };
+        for entry in idle {
+            entry.pool.close().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::projects::repository::ProjectRepository;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_pools_are_reused_and_evicted() -> Result<()> {
+        let app_pool = SqlitePoolOptions::new().connect("sqlite::memory:").await?;
+        sqlx::migrate!("./migrations").run(&app_pool).await?;
+        let project = ProjectRepository::new(app_pool.clone()).create_project("db".into(), "/tmp/db".into(), None).await?;
+
+        let dir = std::env::temp_dir().join(format!("switchboard-pools-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::File::create(dir.join("app.db"))?;
+        let details = serde_json::json!({ "file_path": dir.join("app.db") }).to_string();
+        let connection = DatabaseRepository::new(app_pool.clone())
+            .create_connection(project.id, "local".into(), "sqlite".into(), details, None).await?;
+
+        let manager = ConnectionManager::new(app_pool);
+        assert!(!manager.health_check(&connection.id).await.connected);
+
+        manager.get(&connection.id, None).await?;
+        let connected_at = manager.states()[0].connected_at;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        manager.get(&connection.id, None).await?;
+        assert_eq!(manager.states().len(), 1);
+        assert_eq!(manager.states()[0].connected_at, connected_at);
+
+        let health = manager.health_check(&connection.id).await;
+        assert!(health.connected && health.latency_ms.is_some());
+
+        // A different password means a different pool
+        manager.get(&connection.id, Some("other")).await?;
+        assert_ne!(manager.states()[0].connected_at, connected_at);
+
+        manager.evict_idle(Duration::from_secs(60)).await;
+        assert_eq!(manager.states().len(), 1);
+        manager.evict_idle(Duration::ZERO).await;
+        assert!(manager.states().is_empty());
+
+        manager.get(&connection.id, None).await?;
+        manager.disconnect(&connection.id).await;
+        assert!(manager.states().is_empty());
+        assert!(manager.get("missing", None).await.is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
@@ -12,15 +12,16 @@ impl DatabaseRepository {
         Self { pool }
     }
 
-    pub async fn create_connection(&self, project_id: String, name: String, kind: String, details: String) -> Result<ProjectConnection> {
+    pub async fn create_connection(&self, project_id: String, name: String, kind: String, details: String, password_ref: Option<String>) -> Result<ProjectConnection> {
         let id = Uuid::new_v4().to_string();
 
-        sqlx::query("INSERT INTO project_connections (id, project_id, name, kind, details) VALUES (?, ?, ?, ?, ?)")
+        sqlx::query("INSERT INTO project_connections (id, project_id, name, kind, details, password_ref) VALUES (?, ?, ?, ?, ?, ?)")
             .bind(&id)
             .bind(&project_id)
             .bind(&name)
             .bind(&kind)
             .bind(&details)
+            .bind(&password_ref)
             .execute(&self.pool)
             .await?;
 
@@ -30,13 +31,14 @@ impl DatabaseRepository {
             name,
             kind,
             details,
+            password_ref,
             created_at: String::new(),
             updated_at: String::new(),
         })
     }
 
     pub async fn get_connections(&self, project_id: &str) -> Result<Vec<ProjectConnection>> {
-        let connections = sqlx::query_as::<_, ProjectConnection>("SELECT id, project_id, name, kind, details, created_at, updated_at FROM project_connections WHERE project_id = ? ORDER BY created_at DESC")
+        let connections = sqlx::query_as::<_, ProjectConnection>("SELECT id, project_id, name, kind, details, password_ref, created_at, updated_at FROM project_connections WHERE project_id = ? ORDER BY created_at DESC")
             .bind(project_id)
             .fetch_all(&self.pool)
             .await?;
@@ -44,18 +46,61 @@ impl DatabaseRepository {
     }
 
     pub async fn get_connection(&self, id: &str) -> Result<Option<ProjectConnection>> {
-        let connection = sqlx::query_as::<_, ProjectConnection>("SELECT id, project_id, name, kind, details, created_at, updated_at FROM project_connections WHERE id = ?")
+        let connection = sqlx::query_as::<_, ProjectConnection>("SELECT id, project_id, name, kind, details, password_ref, created_at, updated_at FROM project_connections WHERE id = ?")
             .bind(id)
             .fetch_optional(&self.pool)
             .await?;
         Ok(connection)
     }
 
+    /// Connections saved before passwords moved to the vault, whose `details` still have a
+    /// `password` key, null or not.
+    pub async fn get_connections_with_plaintext_password(&self) -> Result<Vec<ProjectConnection>> {
+        let connections = sqlx::query_as::<_, ProjectConnection>("SELECT id, project_id, name, kind, details, password_ref, created_at, updated_at FROM project_connections WHERE json_valid(details) AND json_type(details, '$.password') IS NOT NULL")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(connections)
+    }
+
+    /// Replaces `details` that held a password, given as (id, details, password_ref), and
+    /// scrubs the old rows from free pages and the WAL so no plaintext copy survives.
+    pub async fn clear_plaintext_passwords(&self, updates: &[(String, String, Option<String>)]) -> Result<()> {
+        if updates.is_empty() {
+            return Ok(());
+        }
+        // secure_delete is per connection, so everything runs on this one
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("PRAGMA secure_delete = ON").execute(&mut *conn).await?;
+        let outcome = async {
+            for (id, details, password_ref) in updates {
+                sqlx::query("UPDATE project_connections SET details = ?, password_ref = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(details)
+                    .bind(password_ref)
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+            sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&mut *conn).await?;
+            sqlx::query("VACUUM").execute(&mut *conn).await?;
+            anyhow::Ok(())
+        }
+        .await;
+        sqlx::query("PRAGMA secure_delete = OFF").execute(&mut *conn).await?;
+        outcome
+    }
+
     pub async fn delete_connection(&self, id: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        // Tunnels outlive the connection, they just stop being tied to it
+        sqlx::query("UPDATE ssh_tunnels SET connection_id = NULL WHERE connection_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
         sqlx::query("DELETE FROM project_connections WHERE id = ?")
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
         Ok(())
     }
 }
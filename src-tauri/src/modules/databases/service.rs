@@ -1,32 +1,23 @@
-use super::models::{ProjectConnection, ConnectionConfig, TableInfo, QueryResult};
+use super::models::{ProjectConnection, TableInfo};
+use super::pool::DbPool;
 use super::repository::DatabaseRepository;
+use crate::modules::vault::service::VaultService;
 use anyhow::Result;
 use sqlx::SqlitePool;
-// We will need dynamic dispatch or enum for different pools
-// For now, let's assume we handle connection testing and simple queries via specialized separate pools created on fly or cached.
-// Caching pools is complex in rust without global state or heavy Arc<Mutex>. 
-// Let's start with CREATE-USE-DISPOSE for simplicity or a simple DashMap if we can add dependencies.
-// Actually, Tauri State is perfect for this.
 
-// But wait, sqlx pools are expensive to create.
-// For the MVP, let's just create a pool, run the query, and close it. 
-// Optimization: Keep a map in a Mutex wrapper managed by Tauri.
-
-use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
-
-// We need an enum to hold different pool types
-pub enum DbPool {
-    Sqlite(sqlx::SqlitePool),
-    Postgres(sqlx::PgPool),
-    MySql(sqlx::MySqlPool),
+/// Removes the password from a connection's `details` JSON, returning the remaining
+/// details and the password if one was set.
+pub fn take_password(details: &str) -> Result<(String, Option<String>)> {
+    let mut config: serde_json::Value = serde_json::from_str(details)?;
+    let password = config.as_object_mut()
+        .and_then(|fields| fields.remove("password"))
+        .and_then(|value| value.as_str().map(str::to_string))
+        .filter(|password| !password.is_empty());
+    Ok((config.to_string(), password))
 }
 
 pub struct DatabaseService {
     repo: DatabaseRepository,
-    // This would be injected or managed. For now, since service is transient in our commands (re-created), 
-    // we can't easily hold state HERE. State must be passed from AppHandle.
-    // So Service should probably take the ConnectionManager State.
 }
 
 impl DatabaseService {
@@ -36,8 +27,24 @@ impl DatabaseService {
         }
     }
 
+    /// Saves a connection, moving its password from `details` into the vault.
     pub async fn create_connection(&self, project_id: String, name: String, kind: String, details: String) -> Result<ProjectConnection> {
-        self.repo.create_connection(project_id, name, kind, details).await
+        let (details, password) = take_password(&details)?;
+        let Some(password) = password else {
+            return self.repo.create_connection(project_id, name, kind, details, None).await;
+        };
+
+        let password_ref = uuid::Uuid::new_v4().to_string();
+        let stored_ref = password_ref.clone();
+        tokio::task::spawn_blocking(move || VaultService::new("switchboard-app").store_secret(&stored_ref, &password)).await??;
+
+        match self.repo.create_connection(project_id, name, kind, details, Some(password_ref.clone())).await {
+            Ok(connection) => Ok(connection),
+            Err(e) => {
+                let _ = tokio::task::spawn_blocking(move || VaultService::new("switchboard-app").delete_secret(&password_ref)).await;
+                Err(e)
+            }
+        }
     }
 
     pub async fn get_connections(&self, project_id: &str) -> Result<Vec<ProjectConnection>> {
@@ -45,190 +52,55 @@ impl DatabaseService {
     }
 
     pub async fn delete_connection(&self, id: &str) -> Result<()> {
-        self.repo.delete_connection(id).await
-    }
-
-    // Connection Logic
-    // We will parse the "details" JSON
-    pub async fn test_connection(&self, kind: &str, details: &str, password: Option<&str>) -> Result<bool> {
-        let config: serde_json::Value = serde_json::from_str(details)?;
-        
-        match kind {
-            "postgres" => {
-                use sqlx::postgres::PgConnectOptions;
-                use std::str::FromStr;
-                
-                let host = config["host"].as_str().unwrap_or("localhost");
-                let port = config["port"].as_u64().unwrap_or(5432) as u16;
-                let user = config["username"].as_str().unwrap_or("postgres");
-                let db_name = config["database"].as_str().unwrap_or("postgres");
-                let config_pass = config["password"].as_str().unwrap_or("");
-                let pass = password.unwrap_or(config_pass);
-
-                let options = PgConnectOptions::new()
-                    .host(host)
-                    .port(port)
-                    .username(user)
-                    .password(pass)
-                    .database(db_name);
-
-                let pool = sqlx::PgPool::connect_with(options).await?;
-                pool.close().await;
-                Ok(true)
-            },
-            "mysql" => {
-                use sqlx::mysql::MySqlConnectOptions;
-                
-                let host = config["host"].as_str().unwrap_or("localhost");
-                let port = config["port"].as_u64().unwrap_or(3306) as u16;
-                let user = config["username"].as_str().unwrap_or("root");
-                let db_name = config["database"].as_str().unwrap_or("mysql");
-                let config_pass = config["password"].as_str().unwrap_or("");
-                let pass = password.unwrap_or(config_pass);
-
-                let options = MySqlConnectOptions::new()
-                    .host(host)
-                    .port(port)
-                    .username(user)
-                    .password(pass)
-                    .database(db_name);
-
-                let pool = sqlx::MySqlPool::connect_with(options).await?;
-                pool.close().await;
-                Ok(true)
-            },
-            "sqlite" => {
-                use sqlx::sqlite::SqliteConnectOptions;
-                use std::str::FromStr;
-
-                let path = config["file_path"].as_str().ok_or(anyhow::anyhow!("Missing file_path"))?;
-                let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path))?;
-                
-                let pool = sqlx::SqlitePool::connect_with(options).await?;
-                pool.close().await;
-                Ok(true)
-            },
-            _ => Err(anyhow::anyhow!("Unsupported database type")),
+        let connection = self.repo.get_connection(id).await?;
+        self.repo.delete_connection(id).await?;
+        if let Some(password_ref) = connection.and_then(|c| c.password_ref) {
+            // The connection is gone either way, a leftover keychain entry is harmless
+            let _ = tokio::task::spawn_blocking(move || VaultService::new("switchboard-app").delete_secret(&password_ref)).await;
         }
+        Ok(())
     }
 
-    pub async fn execute_query(&self, kind: &str, details: &str, query: &str, password: Option<&str>) -> Result<QueryResult> {
-         let config: serde_json::Value = serde_json::from_str(details)?;
-         
-         match kind {
-            "postgres" => {
-                use sqlx::postgres::{PgConnectOptions, PgRow};
-                use sqlx::Row;
-                use sqlx::Column;
-                
-                let host = config["host"].as_str().unwrap_or("localhost");
-                let port = config["port"].as_u64().unwrap_or(5432) as u16;
-                let user = config["username"].as_str().unwrap_or("postgres");
-                let db_name = config["database"].as_str().unwrap_or("postgres");
-                let config_pass = config["password"].as_str().unwrap_or("");
-                let pass = password.unwrap_or(config_pass);
-
-                let options = PgConnectOptions::new()
-                    .host(host)
-                    .port(port)
-                    .username(user)
-                    .password(pass)
-                    .database(db_name);
-
-                let pool = sqlx::PgPool::connect_with(options).await?;
-                // Simple execution of fetching all rows
-                // This is risky for large tables, should limit.
-                let rows = sqlx::query(query).fetch_all(&pool).await?;
-                
-                let mut columns = Vec::new();
-                if let Some(first) = rows.first() {
-                    for col in first.columns() {
-                        columns.push(col.name().to_string());
-                    }
-                }
-
-                let mut result_rows = Vec::new();
-                for row in rows {
-                    let mut values = Vec::new();
-                    for col_name in &columns {
-                        let val_str: String = row.try_get(col_name.as_str()).unwrap_or_else(|_| "NULL".to_string());
-                         values.push(serde_json::Value::String(val_str));
+    /// Moves passwords saved in `details` by earlier versions into the vault.
+    /// Returns how many connections were migrated.
+    pub async fn migrate_plaintext_passwords(&self) -> Result<usize> {
+        let connections = self.repo.get_connections_with_plaintext_password().await?;
+        let updates = tokio::task::spawn_blocking(move || -> Result<Vec<(String, String, Option<String>)>> {
+            let vault = VaultService::new("switchboard-app");
+            let mut updates = Vec::new();
+            for connection in connections {
+                let (details, password) = take_password(&connection.details)?;
+                let password_ref = match password {
+                    Some(password) => {
+                        let password_ref = uuid::Uuid::new_v4().to_string();
+                        vault.store_secret(&password_ref, &password)?;
+                        if let Some(old) = &connection.password_ref {
+                            let _ = vault.delete_secret(old);
+                        }
+                        Some(password_ref)
                     }
-                    result_rows.push(values);
-                }
-                
-                pool.close().await;
-                
-                Ok(QueryResult {
-                    columns,
-                    rows: result_rows,
-                    affected_rows: 0, // Fetch doesn't usually give affected
-                })
-            },
-             "sqlite" => {
-                use sqlx::sqlite::{SqliteConnectOptions, SqliteRow};
-                use sqlx::Row;
-                use sqlx::Column;
-                use std::str::FromStr;
-
-                let path = config["file_path"].as_str().ok_or(anyhow::anyhow!("Missing file_path"))?;
-                let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path))?;
-                
-                let pool = sqlx::SqlitePool::connect_with(options).await?;
-                let rows = sqlx::query(query).fetch_all(&pool).await?;
-
-                let mut columns = Vec::new();
-                if let Some(first) = rows.first() {
-                     for col in first.columns() {
-                        columns.push(col.name().to_string());
-                    }
-                }
-                
-                let mut result_rows = Vec::new();
-                 for row in rows {
-                    let mut values = Vec::new();
-                    for col_name in &columns {
-                         let val_str: String = row.try_get(col_name.as_str()).unwrap_or_else(|_| "NULL".to_string());
-                         values.push(serde_json::Value::String(val_str));
-                    }
-                     result_rows.push(values);
-                }
+                    None => connection.password_ref.clone(),
+                };
+                updates.push((connection.id, details, password_ref));
+            }
+            Ok(updates)
+        }).await??;
+        self.repo.clear_plaintext_passwords(&updates).await?;
+        Ok(updates.len())
+    }
 
-                pool.close().await;
-                 Ok(QueryResult {
-                    columns,
-                    rows: result_rows,
-                    affected_rows: 0, 
-                })
-             },
-             _ => Err(anyhow::anyhow!("Query execution for {} not implemented yet", kind)),
-         }
+    /// Connects once with unsaved settings and closes again.
+    pub async fn test_connection(&self, kind: &str, details: &str, password: Option<&str>) -> Result<bool> {
+        let pool = DbPool::connect(kind, details, password).await?;
+        pool.close().await;
+        Ok(true)
     }
 
-    pub async fn get_tables(&self, kind: &str, details: &str, password: Option<&str>) -> Result<Vec<TableInfo>> {
-        let config: serde_json::Value = serde_json::from_str(details)?;
-        
-        match kind {
-            "postgres" => {
-                use sqlx::postgres::PgConnectOptions;
+    pub async fn get_tables(&self, pool: &DbPool) -> Result<Vec<TableInfo>> {
+        match pool {
+            DbPool::Postgres(pool) => {
                 use sqlx::Row;
                 
-                let host = config["host"].as_str().unwrap_or("localhost");
-                let port = config["port"].as_u64().unwrap_or(5432) as u16;
-                let user = config["username"].as_str().unwrap_or("postgres");
-                let db_name = config["database"].as_str().unwrap_or("postgres");
-                let config_pass = config["password"].as_str().unwrap_or("");
-                let pass = password.unwrap_or(config_pass);
-                
-                let options = PgConnectOptions::new()
-                    .host(host)
-                    .port(port)
-                    .username(user)
-                    .password(pass)
-                    .database(db_name);
-                    
-                let pool = sqlx::PgPool::connect_with(options).await?;
-                
                 let query = "
                     SELECT table_name, table_schema 
                     FROM information_schema.tables 
@@ -236,7 +108,7 @@ impl DatabaseService {
                     ORDER BY table_name
                 ";
                 
-                let rows = sqlx::query(query).fetch_all(&pool).await?;
+                let rows = sqlx::query(query).fetch_all(pool).await?;
                 
                 let mut tables = Vec::new();
                 for row in rows {
@@ -245,21 +117,13 @@ impl DatabaseService {
                     tables.push(TableInfo { name, schema: Some(schema) });
                 }
                 
-                pool.close().await;
                 Ok(tables)
             },
-            "sqlite" => {
-                use sqlx::sqlite::SqliteConnectOptions;
+            DbPool::Sqlite(pool) => {
                 use sqlx::Row;
-                use std::str::FromStr;
-                
-                let path = config["file_path"].as_str().ok_or(anyhow::anyhow!("Missing file_path"))?;
-                let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path))?;
-                
-                let pool = sqlx::SqlitePool::connect_with(options).await?;
                 
                 let query = "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'";
-                let rows = sqlx::query(query).fetch_all(&pool).await?;
+                let rows = sqlx::query(query).fetch_all(pool).await?;
                 
                 let mut tables = Vec::new();
                 for row in rows {
@@ -267,31 +131,13 @@ impl DatabaseService {
                     tables.push(TableInfo { name, schema: None });
                 }
                 
-                pool.close().await;
                 Ok(tables)
             },
-            "mysql" => {
-                 use sqlx::mysql::MySqlConnectOptions;
+            DbPool::MySql(pool) => {
                  use sqlx::Row;
                  
-                 let host = config["host"].as_str().unwrap_or("localhost");
-                 let port = config["port"].as_u64().unwrap_or(3306) as u16;
-                 let user = config["username"].as_str().unwrap_or("root");
-                 let db_name = config["database"].as_str().unwrap_or("mysql");
-                 let config_pass = config["password"].as_str().unwrap_or("");
-                 let pass = password.unwrap_or(config_pass);
- 
-                 let options = MySqlConnectOptions::new()
-                     .host(host)
-                     .port(port)
-                     .username(user)
-                     .password(pass)
-                     .database(db_name);
- 
-                 let pool = sqlx::MySqlPool::connect_with(options).await?;
-                 
                  // Show tables in the connected database
-                 let rows = sqlx::query("SHOW TABLES").fetch_all(&pool).await?;
+                 let rows = sqlx::query("SHOW TABLES").fetch_all(pool).await?;
                  
                  let mut tables = Vec::new();
                  for row in rows {
@@ -300,11 +146,114 @@ impl DatabaseService {
                      tables.push(TableInfo { name, schema: None });
                  }
                  
-                 pool.close().await;
                  Ok(tables)
             },
-            _ => Err(anyhow::anyhow!("Get tables for {} not implemented yet", kind)),
         }
     }
     }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_password() -> Result<()> {
+        let (details, password) = take_password(r#"{"host":"db","password":"hunter2"}"#)?;
+        assert_eq!(details, r#"{"host":"db"}"#);
+        assert_eq!(password.as_deref(), Some("hunter2"));
+
+        // Empty and null passwords are dropped without going to the vault
+        assert_eq!(take_password(r#"{"password":""}"#)?, ("{}".to_string(), None));
+        assert_eq!(take_password(r#"{"file_path":"a.db","password":null}"#)?.1, None);
+        assert!(take_password("not json").is_err());
+        Ok(())
+    }
+
+    async fn connections_db() -> Result<(SqlitePool, String)> {
+        use crate::modules::projects::repository::ProjectRepository;
+
+        let pool = sqlx::sqlite::SqlitePoolOptions::new().connect("sqlite::memory:").await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        let project = ProjectRepository::new(pool.clone()).create_project("db".into(), "/tmp/db".into(), None).await?;
+        Ok((pool, project.id))
+    }
+
+    #[tokio::test]
+    async fn test_migrate_strips_empty_passwords() -> Result<()> {
+        let (pool, project_id) = connections_db().await?;
+        let repo = DatabaseRepository::new(pool.clone());
+        let sqlite = repo.create_connection(project_id.clone(), "local".into(), "sqlite".into(),
+            r#"{"file_path":"a.db","password":null}"#.into(), None).await?;
+        let named = repo.create_connection(project_id, "odd".into(), "postgres".into(),
+            r#"{"host":"db","user":"password","file_path":"\"password\".db"}"#.into(), None).await?;
+
+        let service = DatabaseService::new(pool);
+        assert_eq!(service.migrate_plaintext_passwords().await?, 1);
+        let sqlite = repo.get_connection(&sqlite.id).await?.unwrap();
+        assert_eq!(sqlite.details, r#"{"file_path":"a.db"}"#);
+        assert_eq!(sqlite.password_ref, None);
+        assert_eq!(repo.get_connection(&named.id).await?.unwrap().details, named.details);
+
+        assert_eq!(service.migrate_plaintext_passwords().await?, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cleared_password_leaves_no_copy_on_disk() -> Result<()> {
+        use crate::modules::projects::repository::ProjectRepository;
+
+        let dir = std::env::temp_dir().join(format!("switchboard-scrub-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        // In WAL mode the old row lives on in the log until a checkpoint
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(dir.join("app.db"))
+            .create_if_missing(true)
+            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal);
+        let pool = sqlx::sqlite::SqlitePoolOptions::new().connect_with(options).await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        let project = ProjectRepository::new(pool.clone()).create_project("db".into(), "/tmp/db".into(), None).await?;
+        let repo = DatabaseRepository::new(pool.clone());
+        let connection = repo.create_connection(project.id, "legacy".into(), "postgres".into(),
+            r#"{"host":"db","password":"hunter2-in-plain-text"}"#.into(), None).await?;
+
+        let password_ref = uuid::Uuid::new_v4().to_string();
+        repo.clear_plaintext_passwords(&[(connection.id.clone(), r#"{"host":"db"}"#.into(), Some(password_ref.clone()))]).await?;
+        assert_eq!(repo.get_connection(&connection.id).await?.unwrap().password_ref, Some(password_ref));
+
+        // Read while the pool is open, closing it checkpoints the WAL anyway
+        for entry in std::fs::read_dir(&dir)? {
+            let bytes = std::fs::read(entry?.path())?;
+            assert!(!bytes.windows(21).any(|w| w == b"hunter2-in-plain-text"));
+        }
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore] // Uses the system keychain
+    async fn test_passwords_move_to_vault() -> Result<()> {
+        let (pool, project_id) = connections_db().await?;
+        let repo = DatabaseRepository::new(pool.clone());
+        let legacy = repo.create_connection(project_id.clone(), "legacy".into(), "postgres".into(),
+            r#"{"host":"db","password":"hunter2"}"#.into(), None).await?;
+
+        let service = DatabaseService::new(pool);
+        assert_eq!(service.migrate_plaintext_passwords().await?, 1);
+        let legacy = repo.get_connection(&legacy.id).await?.unwrap();
+        assert_eq!(legacy.details, r#"{"host":"db"}"#);
+        let vault = VaultService::new("switchboard-app");
+        assert_eq!(vault.get_secret(legacy.password_ref.as_deref().unwrap())?, "hunter2");
+
+        let created = service.create_connection(project_id, "new".into(), "postgres".into(),
+            r#"{"host":"db","password":"swordfish"}"#.into()).await?;
+        assert!(!created.details.contains("swordfish"));
+        let password_ref = created.password_ref.clone().unwrap();
+        assert_eq!(vault.get_secret(&password_ref)?, "swordfish");
+
+        service.delete_connection(&created.id).await?;
+        service.delete_connection(&legacy.id).await?;
+        assert!(vault.get_secret(&password_ref).is_err());
+        Ok(())
+    }
+}
@@ -31,7 +31,7 @@ pub fn get_git_status(path: &str) -> Result<Option<GitStatus>> {
     
     // Check if it's a git repo using git command (more robust than checking .git folder)
     let output = new_command("git")
-        .args(&["rev-parse", "--is-inside-work-tree"])
+        .args(["rev-parse", "--is-inside-work-tree"])
         .current_dir(repo_path)
         .output();
 
@@ -42,7 +42,7 @@ pub fn get_git_status(path: &str) -> Result<Option<GitStatus>> {
 
     // Get branch
     let output = new_command("git")
-        .args(&["symbolic-ref", "--short", "HEAD"])
+        .args(["symbolic-ref", "--short", "HEAD"])
         .current_dir(repo_path)
         .output()?;
     
@@ -50,7 +50,7 @@ pub fn get_git_status(path: &str) -> Result<Option<GitStatus>> {
     if branch.is_empty() {
         // Detached HEAD or fresh repo
         let output = new_command("git")
-            .args(&["rev-parse", "--short", "HEAD"])
+            .args(["rev-parse", "--short", "HEAD"])
             .current_dir(repo_path)
             .output()?;
         let hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
@@ -65,7 +65,7 @@ pub fn get_git_status(path: &str) -> Result<Option<GitStatus>> {
 
     // Get modified count (porcelain is stable)
     let output = new_command("git")
-        .args(&["status", "--porcelain"])
+        .args(["status", "--porcelain"])
         .current_dir(repo_path)
         .output()?;
     let status_lines = String::from_utf8_lossy(&output.stdout);
@@ -73,7 +73,7 @@ pub fn get_git_status(path: &str) -> Result<Option<GitStatus>> {
 
     // Get Remote URL
     let output = new_command("git")
-        .args(&["remote", "get-url", "origin"])
+        .args(["remote", "get-url", "origin"])
         .current_dir(repo_path)
         .output()?;
     let remote_url = if output.status.success() {
@@ -85,7 +85,7 @@ pub fn get_git_status(path: &str) -> Result<Option<GitStatus>> {
     // Helper to get ahead/behind
     // git rev-list --left-right --count HEAD...@{u}
     let output = new_command("git")
-        .args(&["rev-list", "--left-right", "--count", "HEAD...@{u}"])
+        .args(["rev-list", "--left-right", "--count", "HEAD...@{u}"])
         .current_dir(repo_path)
         .output();
     
@@ -116,7 +116,7 @@ pub fn clone_repo(url: &str, target_path: &str) -> Result<()> {
     // Usually no, as we should handle stdout/stderr in the app if long running.
     // For now, suppress it to match user request.
     let status = new_command("git")
-        .args(&["clone", url, target_path])
+        .args(["clone", url, target_path])
         .status()?;
 
     if status.success() {
@@ -141,7 +141,7 @@ pub fn get_git_history(path: &str, limit: usize) -> Result<Vec<Commit>> {
     
     // Quick check if git works here
     let check = new_command("git")
-        .args(&["rev-parse", "--is-inside-work-tree"])
+        .args(["rev-parse", "--is-inside-work-tree"])
         .current_dir(repo_path)
         .output();
         
@@ -152,7 +152,7 @@ pub fn get_git_history(path: &str, limit: usize) -> Result<Vec<Commit>> {
 
     // Format: %H|%|%P|%|%an|%|%aI|%|%s|%|%d
     let output = new_command("git")
-        .args(&[
+        .args([
             "log",
             &format!("-n{}", limit),
             "--pretty=format:%H|%|%P|%|%an|%|%aI|%|%s|%|%d",
@@ -0,0 +1,238 @@
+use super::models::{AlertRule, AlertSeverity, ProcessState};
+use super::repository::ProcessRepository;
+use crate::shared::events::EventSink;
+use crate::shared::utils::now_millis;
+use anyhow::Result;
+use regex::Regex;
+use sqlx::SqlitePool;
+use std::sync::{Arc, OnceLock};
+
+// A line without a newline this long (progress bars, minified dumps) gets evaluated as is
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct CompiledAlertRule {
+    pub id: String,
+    pub label: Option<String>,
+    pub severity: AlertSeverity,
+    pub regex: Regex,
+}
+
+impl CompiledAlertRule {
+    pub fn compile(rule: &AlertRule) -> Result<Self> {
+        Ok(Self {
+            id: rule.id.clone(),
+            label: rule.label.clone(),
+            severity: rule.severity.parse()?,
+            regex: Regex::new(&rule.pattern)?,
+        })
+    }
+}
+
+/// Reloads a project's rules into `ProcessState`, so running processes pick up edits immediately.
+pub async fn refresh_rules(state: &ProcessState, pool: &SqlitePool, project_id: &str) -> Result<()> {
+    let rules = ProcessRepository::new(pool.clone()).get_alert_rules(project_id).await?;
+    let compiled: Vec<CompiledAlertRule> = rules.iter()
+        .filter_map(|rule| CompiledAlertRule::compile(rule).ok())
+        .collect();
+    state.alert_rules.lock().unwrap().insert(project_id.to_string(), Arc::new(compiled));
+    Ok(())
+}
+
+fn ansi_escape() -> &'static Regex {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    ANSI.get_or_init(|| Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(\x07|\x1b\\)").unwrap())
+}
+
+/// Removes color and cursor escape sequences, dev servers rarely print plain text to a PTY.
+pub fn strip_ansi(line: &str) -> String {
+    ansi_escape().replace_all(line, "").to_string()
+}
+
+/// Cuts a stream of output chunks into complete lines, holding back the unfinished tail.
+#[derive(Default)]
+pub struct LineSplitter {
+    partial: String,
+}
+
+impl LineSplitter {
+    pub fn feed(&mut self, data: &str) -> Vec<String> {
+        self.partial.push_str(data);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=pos).collect();
+            lines.push(line.trim_end_matches(['\n', '\r']).to_string());
+        }
+        if self.partial.len() > MAX_LINE_BYTES {
+            lines.push(std::mem::take(&mut self.partial));
+        }
+        lines
+    }
+
+    /// The unfinished tail, once the stream has ended without a final newline.
+    pub fn flush(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.partial);
+        let line = line.trim_end_matches('\r');
+        (!line.is_empty()).then(|| line.to_string())
+    }
+}
+
+/// Runs a process's output through its project's alert rules, line by line.
+pub struct AlertMatcher {
+    process_id: String,
+    project_id: String,
+    state: ProcessState,
+    lines: LineSplitter,
+}
+
+impl AlertMatcher {
+    pub fn new(process_id: String, project_id: String, state: ProcessState) -> Self {
+        Self {
+            process_id,
+            project_id,
+            state,
+            lines: LineSplitter::default(),
+        }
+    }
+
+    pub fn feed(&mut self, data: &str, sink: &dyn EventSink) {
+        let lines = self.lines.feed(data);
+        self.evaluate(lines, sink);
+    }
+
+    /// Evaluates a last line the process printed without a newline. Call once output ended.
+    pub fn flush(&mut self, sink: &dyn EventSink) {
+        let lines = self.lines.flush().into_iter().collect();
+        self.evaluate(lines, sink);
+    }
+
+    fn evaluate(&self, lines: Vec<String>, sink: &dyn EventSink) {
+        if lines.is_empty() {
+            return;
+        }
+        let Some(rules) = self.state.alert_rules.lock().unwrap().get(&self.project_id).cloned() else {
+            return;
+        };
+        if rules.is_empty() {
+            return;
+        }
+
+        for line in lines {
+            let line = strip_ansi(&line);
+            for rule in rules.iter().filter(|rule| rule.regex.is_match(&line)) {
+                let count = {
+                    let mut sessions = self.state.sessions.lock().unwrap();
+                    sessions.get_mut(&self.process_id).map(|session| {
+                        let count = session.alert_counts.entry(rule.severity).or_insert(0);
+                        *count += 1;
+                        *count
+                    })
+                };
+                sink.emit_event("process_alert", serde_json::json!({
+                    "id": self.process_id,
+                    "project_id": self.project_id,
+                    "rule_id": rule.id,
+                    "label": rule.label,
+                    "severity": rule.severity,
+                    "line": line.trim(),
+                    "count": count,
+                    "timestamp": now_millis()
+                }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_splitter_joins_chunks() {
+        let mut splitter = LineSplitter::default();
+        assert!(splitter.feed("src/app.ts(3,1): error T").is_empty());
+        assert_eq!(splitter.feed("S2304: Cannot find name\r\nnext"), vec!["src/app.ts(3,1): error TS2304: Cannot find name"]);
+        assert_eq!(splitter.feed(" line\n\n"), vec!["next line", ""]);
+
+        assert!(splitter.feed("panicked at src/main.rs\r").is_empty());
+        assert_eq!(splitter.flush().as_deref(), Some("panicked at src/main.rs"));
+        assert_eq!(splitter.flush(), None);
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[32m\x1b[1mCompiled successfully\x1b[22m\x1b[39m"), "Compiled successfully");
+        assert_eq!(strip_ansi("\x1b]0;title\x07plain"), "plain");
+    }
+
+    #[test]
+    fn test_compile_rejects_bad_rules() {
+        let rule = AlertRule {
+            id: "r".into(),
+            project_id: "p".into(),
+            label: None,
+            pattern: r"error TS\d+".into(),
+            severity: "error".into(),
+            created_at: String::new(),
+        };
+        assert!(CompiledAlertRule::compile(&rule).is_ok());
+        assert!(CompiledAlertRule::compile(&AlertRule { severity: "fatal".into(), ..rule.clone() }).is_err());
+        assert!(CompiledAlertRule::compile(&AlertRule { pattern: "(".into(), ..rule }).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_alert_across_chunk_boundary() -> Result<()> {
+        use crate::modules::processes::models::ProcessSpec;
+        use crate::modules::processes::service::ProcessService;
+        use std::sync::Mutex;
+        use std::time::{Duration, Instant};
+
+        #[derive(Default)]
+        struct RecordingSink {
+            alerts: Mutex<Vec<serde_json::Value>>,
+        }
+
+        impl EventSink for RecordingSink {
+            fn emit_event(&self, event: &str, payload: serde_json::Value) {
+                if event == "process_alert" {
+                    self.alerts.lock().unwrap().push(payload);
+                }
+            }
+        }
+
+        let state = ProcessState::new(None);
+        let rule = AlertRule {
+            id: "ts".into(),
+            project_id: "p".into(),
+            label: Some("TypeScript".into()),
+            pattern: r"error TS\d+".into(),
+            severity: "error".into(),
+            created_at: String::new(),
+        };
+        state.alert_rules.lock().unwrap().insert("p".into(), Arc::new(vec![CompiledAlertRule::compile(&rule)?]));
+
+        let sink = Arc::new(RecordingSink::default());
+        let mut spec = ProcessSpec::new(
+            "printf 'src/a.ts: \\033[31merror T'; sleep 0.2; printf 'S2304\\033[0m oops\\n'; sleep 0.5; printf 'error TS1005 at exit'".into(),
+            std::env::temp_dir().to_string_lossy().to_string(),
+        );
+        spec.project_id = Some("p".into());
+        let service = ProcessService::new(state);
+        let process = service.spawn(sink.clone(), spec)?;
+
+        // The last line has no newline and only counts once output ends
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while sink.alerts.lock().unwrap().len() < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        let alerts = sink.alerts.lock().unwrap();
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0]["line"], "src/a.ts: error TS2304 oops");
+        assert_eq!(alerts[0]["severity"], "error");
+        assert_eq!(alerts[1]["line"], "error TS1005 at exit");
+        assert_eq!(service.get_process(&process.id)?.alert_counts.get(&AlertSeverity::Error), Some(&2));
+        Ok(())
+    }
+}
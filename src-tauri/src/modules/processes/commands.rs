@@ -1,16 +1,39 @@
-use tauri::{command, State, Window};
-use super::models::{Process, ProcessState};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{command, AppHandle, State, Window};
+use sqlx::SqlitePool;
+use super::alerts::refresh_rules;
+use super::environment::project_environment;
+use crate::shared::history::HistoryChunk;
+use super::ports::PortOwner;
+use super::models::{AlertRule, OpenProjectReport, Process, ProcessDefinition, ProcessDefinitionInput, ProcessSpec, ProcessState};
+use super::repository::ProcessRepository;
 use super::service::ProcessService;
+use super::stack::StackService;
+use super::supervisor::ProcessSupervisor;
+use super::tree::TerminateReport;
 
 #[command]
 pub async fn start_process(
     window: Window,
     state: State<'_, ProcessState>,
+    pool: State<'_, SqlitePool>,
+    project_id: Option<String>,
     command: String,
     cwd: String
 ) -> Result<Process, String> {
+    let mut spec = ProcessSpec::new(command, cwd);
+    if let Some(project_id) = project_id {
+        spec.env = project_environment(pool.inner(), &project_id, Vec::new())
+            .await
+            .map_err(|e| e.to_string())?;
+        refresh_rules(state.inner(), pool.inner(), &project_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        spec.project_id = Some(project_id);
+    }
     let service = ProcessService::new(state.inner().clone());
-    service.start_process(window, command, cwd).map_err(|e| e.to_string())
+    service.spawn(window, spec).map_err(|e| e.to_string())
 }
 
 #[command]
@@ -34,22 +57,57 @@ pub async fn resize_process(
     service.resize(&id, cols, rows).map_err(|e| e.to_string())
 }
 
+/// Stops the process tree, escalating to SIGKILL after `grace_ms` (or the configured default).
 #[command]
 pub async fn stop_process(
+    state: State<'_, ProcessState>,
+    id: String,
+    grace_ms: Option<u64>
+) -> Result<TerminateReport, String> {
+    let service = ProcessService::new(state.inner().clone());
+    tauri::async_runtime::spawn_blocking(move || service.terminate(&id, grace_ms.map(Duration::from_millis)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn set_process_kill_grace(
+    state: State<'_, ProcessState>,
+    grace_ms: u64
+) -> Result<(), String> {
+    let service = ProcessService::new(state.inner().clone());
+    service.set_kill_grace(Duration::from_millis(grace_ms));
+    Ok(())
+}
+
+#[command]
+pub async fn remove_process(
     state: State<'_, ProcessState>,
     id: String
 ) -> Result<(), String> {
     let service = ProcessService::new(state.inner().clone());
-    service.kill(&id).map_err(|e| e.to_string())
+    service.remove(&id).map_err(|e| e.to_string())
 }
 
 #[command]
 pub async fn get_process_history(
     state: State<'_, ProcessState>,
-    id: String
-) -> Result<String, String> {
+    id: String,
+    offset: Option<u64>,
+    limit: Option<usize>
+) -> Result<HistoryChunk, String> {
+    let service = ProcessService::new(state.inner().clone());
+    service.get_history(&id, offset, limit).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn set_process_history_limit(
+    state: State<'_, ProcessState>,
+    limit: usize
+) -> Result<(), String> {
     let service = ProcessService::new(state.inner().clone());
-    service.get_history(&id).map_err(|e| e.to_string())
+    service.set_history_limit(limit).map_err(|e| e.to_string())
 }
 
 #[command]
@@ -59,3 +117,239 @@ pub async fn get_active_processes(
     let service = ProcessService::new(state.inner().clone());
     service.list_processes().map_err(|e| e.to_string())
 }
+
+/// Lists whoever is listening on a TCP port, managed or not.
+#[command]
+pub async fn find_port_owner(
+    state: State<'_, ProcessState>,
+    port: u16
+) -> Result<Vec<PortOwner>, String> {
+    let service = ProcessService::new(state.inner().clone());
+    tauri::async_runtime::spawn_blocking(move || service.find_port_owner(port))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn kill_port_owner(
+    state: State<'_, ProcessState>,
+    port: u16,
+    pid: u32,
+    grace_ms: Option<u64>
+) -> Result<TerminateReport, String> {
+    let service = ProcessService::new(state.inner().clone());
+    tauri::async_runtime::spawn_blocking(move || service.kill_port_owner(port, pid, grace_ms.map(Duration::from_millis)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+// Saved process definitions
+
+#[command]
+pub async fn create_process_definition(
+    pool: State<'_, SqlitePool>,
+    project_id: String,
+    definition: ProcessDefinitionInput
+) -> Result<ProcessDefinition, String> {
+    let repo = ProcessRepository::new(pool.inner().clone());
+    repo.create_definition(project_id, definition)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_process_definitions(
+    pool: State<'_, SqlitePool>,
+    project_id: String
+) -> Result<Vec<ProcessDefinition>, String> {
+    let repo = ProcessRepository::new(pool.inner().clone());
+    repo.get_definitions(&project_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn update_process_definition(
+    pool: State<'_, SqlitePool>,
+    id: String,
+    definition: ProcessDefinitionInput
+) -> Result<(), String> {
+    let repo = ProcessRepository::new(pool.inner().clone());
+    repo.update_definition(&id, definition)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn delete_process_definition(
+    pool: State<'_, SqlitePool>,
+    supervisor: State<'_, ProcessSupervisor>,
+    id: String
+) -> Result<(), String> {
+    supervisor.stop(&id).map_err(|e| e.to_string())?;
+    let repo = ProcessRepository::new(pool.inner().clone());
+    repo.delete_definition(&id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn start_process_definition(
+    supervisor: State<'_, ProcessSupervisor>,
+    id: String
+) -> Result<Process, String> {
+    supervisor.start(&id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn stop_process_definition(
+    supervisor: State<'_, ProcessSupervisor>,
+    id: String
+) -> Result<(), String> {
+    supervisor.stop(&id).map_err(|e| e.to_string())
+}
+
+/// Called when a project is opened: starts its autostart processes.
+#[command]
+pub async fn open_project_processes(
+    supervisor: State<'_, ProcessSupervisor>,
+    project_id: String
+) -> Result<OpenProjectReport, String> {
+    supervisor.open_project(&project_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Alert rules
+
+#[command]
+pub async fn create_alert_rule(
+    state: State<'_, ProcessState>,
+    pool: State<'_, SqlitePool>,
+    project_id: String,
+    label: Option<String>,
+    pattern: String,
+    severity: String
+) -> Result<AlertRule, String> {
+    let repo = ProcessRepository::new(pool.inner().clone());
+    let rule = repo.create_alert_rule(project_id.clone(), label, pattern, severity)
+        .await
+        .map_err(|e| e.to_string())?;
+    refresh_rules(state.inner(), pool.inner(), &project_id).await.map_err(|e| e.to_string())?;
+    Ok(rule)
+}
+
+#[command]
+pub async fn get_alert_rules(
+    pool: State<'_, SqlitePool>,
+    project_id: String
+) -> Result<Vec<AlertRule>, String> {
+    let repo = ProcessRepository::new(pool.inner().clone());
+    repo.get_alert_rules(&project_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn update_alert_rule(
+    state: State<'_, ProcessState>,
+    pool: State<'_, SqlitePool>,
+    id: String,
+    label: Option<String>,
+    pattern: String,
+    severity: String
+) -> Result<(), String> {
+    let repo = ProcessRepository::new(pool.inner().clone());
+    let rule = repo.get_alert_rule(&id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("Alert rule not found")?;
+    repo.update_alert_rule(&id, label, pattern, severity)
+        .await
+        .map_err(|e| e.to_string())?;
+    refresh_rules(state.inner(), pool.inner(), &rule.project_id).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn delete_alert_rule(
+    state: State<'_, ProcessState>,
+    pool: State<'_, SqlitePool>,
+    id: String
+) -> Result<(), String> {
+    let repo = ProcessRepository::new(pool.inner().clone());
+    let Some(rule) = repo.get_alert_rule(&id).await.map_err(|e| e.to_string())? else {
+        return Ok(());
+    };
+    repo.delete_alert_rule(&id)
+        .await
+        .map_err(|e| e.to_string())?;
+    refresh_rules(state.inner(), pool.inner(), &rule.project_id).await.map_err(|e| e.to_string())
+}
+
+// Stacks
+
+fn stack_service(app_handle: AppHandle, supervisor: &ProcessSupervisor, state: &ProcessState, pool: &SqlitePool) -> StackService {
+    StackService::new(supervisor.clone(), state.clone(), pool.clone(), Arc::new(app_handle))
+}
+
+#[command]
+pub async fn import_procfile(
+    app_handle: AppHandle,
+    supervisor: State<'_, ProcessSupervisor>,
+    state: State<'_, ProcessState>,
+    pool: State<'_, SqlitePool>,
+    project_id: String,
+    path: Option<String>
+) -> Result<Vec<ProcessDefinition>, String> {
+    stack_service(app_handle, &supervisor, &state, &pool)
+        .import_procfile(&project_id, path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn import_stack_file(
+    app_handle: AppHandle,
+    supervisor: State<'_, ProcessSupervisor>,
+    state: State<'_, ProcessState>,
+    pool: State<'_, SqlitePool>,
+    project_id: String,
+    path: Option<String>
+) -> Result<Vec<ProcessDefinition>, String> {
+    stack_service(app_handle, &supervisor, &state, &pool)
+        .import_stack_file(&project_id, path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Starts every service of the project in dependency order, waiting on readiness checks.
+#[command]
+pub async fn start_stack(
+    app_handle: AppHandle,
+    supervisor: State<'_, ProcessSupervisor>,
+    state: State<'_, ProcessState>,
+    pool: State<'_, SqlitePool>,
+    project_id: String
+) -> Result<Vec<Process>, String> {
+    stack_service(app_handle, &supervisor, &state, &pool)
+        .start_stack(&project_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn stop_stack(
+    app_handle: AppHandle,
+    supervisor: State<'_, ProcessSupervisor>,
+    state: State<'_, ProcessState>,
+    pool: State<'_, SqlitePool>,
+    project_id: String
+) -> Result<(), String> {
+    stack_service(app_handle, &supervisor, &state, &pool)
+        .stop_stack(&project_id)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,116 @@
+use crate::modules::projects::models::ProjectKey;
+use crate::modules::projects::repository::ProjectRepository;
+use crate::modules::vault::service::VaultService;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use sqlx::SqlitePool;
+use std::sync::OnceLock;
+
+/// Env values may reference a project key as `${secret:NAME}`. Only the reference is
+/// stored in SQLite, the secret itself is read from the vault right before spawning.
+fn secret_ref() -> &'static Regex {
+    static SECRET_REF: OnceLock<Regex> = OnceLock::new();
+    SECRET_REF.get_or_init(|| Regex::new(r"\$\{secret:([^}]+)\}").unwrap())
+}
+
+/// Replaces every `${secret:NAME}` in `value` with the secret of the project key named NAME.
+pub fn resolve_secrets(value: &str, keys: &[ProjectKey], lookup: impl Fn(&str) -> Result<String>) -> Result<String> {
+    let mut resolved = String::with_capacity(value.len());
+    let mut last = 0;
+    for captures in secret_ref().captures_iter(value) {
+        let whole = captures.get(0).unwrap();
+        let name = captures[1].trim();
+        let key = keys.iter()
+            .find(|k| k.name == name)
+            .ok_or_else(|| anyhow!("Unknown secret '{}'", name))?;
+
+        resolved.push_str(&value[last..whole.start()]);
+        resolved.push_str(&lookup(&key.key_reference)?);
+        last = whole.end();
+    }
+    resolved.push_str(&value[last..]);
+    Ok(resolved)
+}
+
+/// Builds the environment for a process of a project: the project env vars, then
+/// `overrides` on top, with secret references resolved from the vault.
+pub async fn project_environment(pool: &SqlitePool, project_id: &str, overrides: Vec<(String, String)>) -> Result<Vec<(String, String)>> {
+    let repo = ProjectRepository::new(pool.clone());
+    let keys = repo.get_project_keys(project_id).await?;
+
+    let mut env: Vec<(String, String)> = repo.get_project_envs(project_id).await?
+        .into_iter()
+        .map(|e| (e.key, e.value))
+        .collect();
+    for (key, value) in overrides {
+        env.retain(|(k, _)| *k != key);
+        env.push((key, value));
+    }
+
+    // Only touch the keychain when something actually asks for a secret
+    if !env.iter().any(|(_, value)| secret_ref().is_match(value)) {
+        return Ok(env);
+    }
+    // The keychain can block on an unlock prompt
+    tokio::task::spawn_blocking(move || {
+        let vault = VaultService::new("switchboard-app");
+        env.into_iter()
+            .map(|(key, value)| {
+                let value = resolve_secrets(&value, &keys, |reference| vault.get_secret(reference))
+                    .map_err(|e| anyhow!("{}: {}", key, e))?;
+                Ok((key, value))
+            })
+            .collect()
+    }).await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str, reference: &str) -> ProjectKey {
+        ProjectKey {
+            id: reference.to_string(),
+            project_id: "p".into(),
+            name: name.to_string(),
+            key_reference: reference.to_string(),
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_secrets() -> Result<()> {
+        let keys = vec![key("STRIPE_KEY", "ref-1"), key("db password", "ref-2")];
+        let lookup = |reference: &str| Ok(format!("<{}>", reference));
+
+        assert_eq!(resolve_secrets("plain", &keys, lookup)?, "plain");
+        assert_eq!(resolve_secrets("${secret:STRIPE_KEY}", &keys, lookup)?, "<ref-1>");
+        assert_eq!(
+            resolve_secrets("postgres://app:${secret: db password }@localhost/${secret:STRIPE_KEY}", &keys, lookup)?,
+            "postgres://app:<ref-2>@localhost/<ref-1>"
+        );
+        assert!(resolve_secrets("${secret:MISSING}", &keys, lookup).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_project_environment_merges_overrides() -> Result<()> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await?;
+
+        let repo = ProjectRepository::new(pool.clone());
+        let project = repo.create_project("Env".into(), "/tmp/env".into(), None).await?;
+        repo.set_env_var(&project.id, "PORT".into(), "3000".into()).await?;
+        repo.set_env_var(&project.id, "NODE_ENV".into(), "development".into()).await?;
+
+        let env = project_environment(&pool, &project.id, vec![("PORT".into(), "4000".into())]).await?;
+        assert!(env.contains(&("NODE_ENV".to_string(), "development".to_string())));
+        assert!(env.contains(&("PORT".to_string(), "4000".to_string())));
+        assert_eq!(env.len(), 2);
+        Ok(())
+    }
+}
@@ -1,3 +1,11 @@
 pub mod models;
+pub mod environment;
+pub mod repository;
 pub mod service;
+pub mod supervisor;
+pub mod tree;
+pub mod stats;
+pub mod ports;
+pub mod alerts;
+pub mod stack;
 pub mod commands;
@@ -1,7 +1,23 @@
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use portable_pty::PtyPair;
+use std::time::Duration;
+use portable_pty::{ChildKiller, MasterPty};
+use super::alerts::CompiledAlertRule;
+use crate::shared::history::{OutputHistory, DEFAULT_HISTORY_LIMIT};
+use super::tree::DEFAULT_KILL_GRACE;
+use crate::modules::terminal::recording::RecordingSlot;
+use crate::shared::pty_stream::PtyWriter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessStatus {
+    Running,
+    Exited, // Finished on its own (check exit_code)
+    Killed, // Stopped by the user
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Process {
@@ -10,15 +26,325 @@ pub struct Process {
     pub cwd: String,
     pub running: bool,
     pub pid: u32,
+    pub status: ProcessStatus,
+    pub exit_code: Option<u32>,
+    pub started_at: u64, // Unix millis
+    pub ended_at: Option<u64>,
+    pub definition_id: Option<String>, // Set when started from a saved ProcessDefinition
+    pub stats: Option<ProcessStats>, // Latest sample, None until the first one lands
+    pub ports: Vec<u16>, // TCP ports the process tree is listening on
+    pub project_id: Option<String>,
+    pub alert_counts: HashMap<AlertSeverity, u32>, // Alert rule matches so far
+}
+
+/// What opening a project brought up. One failing autostart doesn't stop the others.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpenProjectReport {
+    pub started: Vec<Process>,
+    pub failed: Vec<AutostartFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutostartFailure {
+    pub definition_id: String,
+    pub name: String,
+    pub error: String,
+}
+
+/// Resource usage of a managed process and all of its descendants.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProcessStats {
+    pub cpu_percent: f64, // Share of one core, so it can exceed 100 on multi-threaded work
+    pub rss_bytes: u64,
+    pub threads: Option<u32>, // Not every platform reports it cheaply
+    pub process_count: u32,
+    pub sampled_at: u64,
+}
+
+/// Everything needed to spawn a managed process.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessSpec {
+    pub command: String,
+    pub cwd: String,
+    pub env: Vec<(String, String)>,
+    pub definition_id: Option<String>,
+    pub project_id: Option<String>, // Enables the project's alert rules
+}
+
+impl ProcessSpec {
+    pub fn new(command: String, cwd: String) -> Self {
+        Self {
+            command,
+            cwd,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    Never,
+    OnFailure, // Non-zero exit code, with exponential backoff
+    Always,    // Any exit that wasn't a user kill
+}
+
+impl std::str::FromStr for RestartPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(Self::Never),
+            "on-failure" => Ok(Self::OnFailure),
+            "always" => Ok(Self::Always),
+            _ => Err(anyhow::anyhow!("Unknown restart policy: {}", s)),
+        }
+    }
+}
+
+/// A named process saved per project (`project_processes` table).
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProcessDefinition {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub command: String,
+    pub cwd: Option<String>,
+    pub env: String, // JSON object
+    pub autostart: bool,
+    pub restart_policy: String, // 'never', 'on-failure', 'always'
+    pub max_restarts: Option<i64>,
+    pub backoff_ms: i64,
+    pub depends_on: String, // JSON array of definition names in the same project
+    pub ready_port: Option<i64>,
+    pub ready_pattern: Option<String>,
+    #[sqlx(default)]
+    pub created_at: String,
+    #[sqlx(default)]
+    pub updated_at: String,
+}
+
+/// Editable fields of a `ProcessDefinition`, as sent by the UI or an imported stack file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessDefinitionInput {
+    pub name: String,
+    pub command: String,
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub autostart: bool,
+    #[serde(default = "default_restart_policy")]
+    pub restart_policy: String,
+    pub max_restarts: Option<i64>,
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: i64,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    pub ready_port: Option<u16>,
+    pub ready_pattern: Option<String>,
+}
+
+fn default_restart_policy() -> String {
+    "never".to_string()
+}
+
+fn default_backoff_ms() -> i64 {
+    1000
+}
+
+impl ProcessDefinitionInput {
+    pub fn new(name: String, command: String) -> Self {
+        Self {
+            name,
+            command,
+            cwd: None,
+            env: HashMap::new(),
+            autostart: false,
+            restart_policy: default_restart_policy(),
+            max_restarts: None,
+            backoff_ms: default_backoff_ms(),
+            depends_on: Vec::new(),
+            ready_port: None,
+            ready_pattern: None,
+        }
+    }
+}
+
+/// A service read from a Procfile or stack file. Fields the file leaves out are `None`,
+/// so re-importing keeps whatever the user changed in the UI.
+#[derive(Debug, Clone, Default)]
+pub struct ImportedService {
+    pub name: String,
+    pub command: String,
+    pub cwd: Option<String>,
+    pub env: Option<HashMap<String, String>>,
+    pub autostart: Option<bool>,
+    pub restart_policy: Option<String>,
+    pub max_restarts: Option<i64>,
+    pub backoff_ms: Option<i64>,
+    pub depends_on: Option<Vec<String>>,
+    pub ready_port: Option<u16>,
+    pub ready_pattern: Option<String>,
+}
+
+impl ImportedService {
+    pub fn new(name: String, command: String) -> Self {
+        Self { name, command, ..Default::default() }
+    }
+
+    /// Overwrites the fields of `base` that the file sets.
+    pub fn apply_to(self, mut base: ProcessDefinitionInput) -> ProcessDefinitionInput {
+        base.name = self.name;
+        base.command = self.command;
+        if self.cwd.is_some() {
+            base.cwd = self.cwd;
+        }
+        if let Some(env) = self.env {
+            base.env = env;
+        }
+        if let Some(autostart) = self.autostart {
+            base.autostart = autostart;
+        }
+        if let Some(restart_policy) = self.restart_policy {
+            base.restart_policy = restart_policy;
+        }
+        if self.max_restarts.is_some() {
+            base.max_restarts = self.max_restarts;
+        }
+        if let Some(backoff_ms) = self.backoff_ms {
+            base.backoff_ms = backoff_ms;
+        }
+        if let Some(depends_on) = self.depends_on {
+            base.depends_on = depends_on;
+        }
+        if self.ready_port.is_some() {
+            base.ready_port = self.ready_port;
+        }
+        if self.ready_pattern.is_some() {
+            base.ready_pattern = self.ready_pattern;
+        }
+        base
+    }
+
+    pub fn into_input(self) -> ProcessDefinitionInput {
+        let base = ProcessDefinitionInput::new(self.name.clone(), self.command.clone());
+        self.apply_to(base)
+    }
+}
+
+impl From<&ProcessDefinition> for ProcessDefinitionInput {
+    fn from(definition: &ProcessDefinition) -> Self {
+        Self {
+            name: definition.name.clone(),
+            command: definition.command.clone(),
+            cwd: definition.cwd.clone(),
+            env: definition.env_vars().into_iter().collect(),
+            autostart: definition.autostart,
+            restart_policy: definition.restart_policy.clone(),
+            max_restarts: definition.max_restarts,
+            backoff_ms: definition.backoff_ms,
+            depends_on: definition.dependencies(),
+            ready_port: definition.ready_port.and_then(|p| u16::try_from(p).ok()),
+            ready_pattern: definition.ready_pattern.clone(),
+        }
+    }
+}
+
+impl ProcessDefinition {
+    pub fn policy(&self) -> RestartPolicy {
+        self.restart_policy.parse().unwrap_or(RestartPolicy::Never)
+    }
+
+    pub fn env_vars(&self) -> Vec<(String, String)> {
+        serde_json::from_str::<HashMap<String, String>>(&self.env)
+            .map(|env| env.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn dependencies(&self) -> Vec<String> {
+        serde_json::from_str(&self.depends_on).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSeverity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl std::str::FromStr for AlertSeverity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "info" => Ok(Self::Info),
+            "success" => Ok(Self::Success),
+            "warning" => Ok(Self::Warning),
+            "error" => Ok(Self::Error),
+            _ => Err(anyhow::anyhow!("Unknown alert severity: {}", s)),
+        }
+    }
+}
+
+/// Regex matched against every line a project's processes print.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AlertRule {
+    pub id: String,
+    pub project_id: String,
+    pub label: Option<String>,
+    pub pattern: String,
+    pub severity: String,
+    #[sqlx(default)]
+    pub created_at: String,
 }
 
 pub struct ProcessSession {
-    pub pty_pair: PtyPair,
-    pub process: Box<dyn portable_pty::Child>,
-    pub running: bool,
-    pub history: Arc<Mutex<String>>,
+    // The slave is dropped after spawn so the reader sees EOF once the process exits
+    pub master: Box<dyn MasterPty + Send>,
+    // Taken once at spawn; written to after the sessions lock is released.
+    pub writer: PtyWriter,
+    // The child itself is owned by the reaper thread, we only keep a handle to signal it.
+    pub killer: Box<dyn ChildKiller + Send + Sync>,
+    pub pid: u32,
+    pub status: ProcessStatus,
+    pub exit_code: Option<u32>,
+    pub kill_requested: bool,
+    pub started_at: u64,
+    pub ended_at: Option<u64>,
+    pub history: Arc<Mutex<OutputHistory>>,
     pub command: String,
     pub cwd: String,
+    pub definition_id: Option<String>,
+    pub stats: Option<ProcessStats>,
+    pub ports: Vec<u16>,
+    pub project_id: Option<String>,
+    pub alert_counts: HashMap<AlertSeverity, u32>,
+    pub recording: RecordingSlot,
+}
+
+impl ProcessSession {
+    pub fn to_process(&self, id: &str) -> Process {
+        Process {
+            id: id.to_string(),
+            command: self.command.clone(),
+            cwd: self.cwd.clone(),
+            running: self.status == ProcessStatus::Running,
+            pid: self.pid,
+            status: self.status,
+            exit_code: self.exit_code,
+            started_at: self.started_at,
+            ended_at: self.ended_at,
+            definition_id: self.definition_id.clone(),
+            stats: self.stats.clone(),
+            ports: self.ports.clone(),
+            project_id: self.project_id.clone(),
+            alert_counts: self.alert_counts.clone(),
+        }
+    }
 }
 
 // SAFETY: portable_pty types wrap OS handles (FDs on Unix, Handles on Windows) which are generally Send/Sync.
@@ -26,5 +352,33 @@ pub struct ProcessSession {
 unsafe impl Send for ProcessSession {}
 unsafe impl Sync for ProcessSession {}
 
+pub struct ProcessConfig {
+    pub history_limit: usize,
+    // Where evicted history is spilled; None keeps only the in-memory tail.
+    pub log_dir: Option<PathBuf>,
+    // How long a stopped process gets between SIGTERM and SIGKILL
+    pub kill_grace: Duration,
+}
+
 // Global state container
-pub type ProcessState = Arc<Mutex<HashMap<String, ProcessSession>>>;
+#[derive(Clone)]
+pub struct ProcessState {
+    pub sessions: Arc<Mutex<HashMap<String, ProcessSession>>>,
+    pub config: Arc<Mutex<ProcessConfig>>,
+    // Compiled alert rules per project id, shared with the reader threads
+    pub alert_rules: Arc<Mutex<HashMap<String, Arc<Vec<CompiledAlertRule>>>>>,
+}
+
+impl ProcessState {
+    pub fn new(log_dir: Option<PathBuf>) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            config: Arc::new(Mutex::new(ProcessConfig {
+                history_limit: DEFAULT_HISTORY_LIMIT,
+                log_dir,
+                kill_grace: DEFAULT_KILL_GRACE,
+            })),
+            alert_rules: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
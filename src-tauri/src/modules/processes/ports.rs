@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+
+/// A TCP socket in LISTEN state and whoever holds it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortOwner {
+    pub port: u16,
+    pub address: String,
+    pub pid: Option<u32>, // None when the socket belongs to a process we may not inspect
+    pub name: Option<String>,
+    pub command: Option<String>,
+    pub process_id: Option<String>, // Managed process whose tree holds the port
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListeningSocket {
+    pub port: u16,
+    pub address: String,
+    pub inode: u64,
+}
+
+/// Parses `/proc/net/tcp` or `/proc/net/tcp6`, keeping only sockets in LISTEN state.
+pub fn parse_proc_net_tcp(content: &str) -> Vec<ListeningSocket> {
+    const LISTEN: &str = "0A";
+    content.lines()
+        .skip(1) // Header
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(3) != Some(&LISTEN) {
+                return None;
+            }
+            let (address, port) = fields.get(1)?.split_once(':')?;
+            Some(ListeningSocket {
+                port: u16::from_str_radix(port, 16).ok()?,
+                address: parse_hex_address(address)?,
+                inode: fields.get(9)?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Addresses are written as native-endian 32-bit words in hex.
+fn parse_hex_address(hex: &str) -> Option<String> {
+    let words: Vec<u32> = (0..hex.len() / 8)
+        .map(|i| u32::from_str_radix(&hex[i * 8..i * 8 + 8], 16).ok())
+        .collect::<Option<_>>()?;
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_ne_bytes()).collect();
+    match bytes.len() {
+        4 => Some(std::net::Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]).to_string()),
+        16 => {
+            let octets: [u8; 16] = bytes.try_into().ok()?;
+            Some(std::net::Ipv6Addr::from(octets).to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Pulls the port out of an "address already in use" error line, e.g.
+/// `Error: listen EADDRINUSE: address already in use :::3000`.
+pub fn port_from_conflict(output: &str) -> Option<u16> {
+    output.lines()
+        .filter(|line| {
+            let lower = line.to_lowercase();
+            lower.contains("eaddrinuse") || lower.contains("address already in use")
+        })
+        .find_map(|line| {
+            line.rsplit(':')
+                .next()
+                .map(|tail| tail.chars().take_while(|c| c.is_ascii_digit()).collect::<String>())
+                .and_then(|digits| digits.parse().ok())
+                .filter(|port| *port > 0)
+        })
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    pub fn listening_sockets() -> Vec<ListeningSocket> {
+        ["/proc/net/tcp", "/proc/net/tcp6"].iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .flat_map(|content| parse_proc_net_tcp(&content))
+            .collect()
+    }
+
+    /// Socket inodes held open by a process, read from its fd links (`socket:[123]`).
+    fn socket_inodes(pid: u32) -> Vec<u64> {
+        let Ok(fds) = std::fs::read_dir(format!("/proc/{}/fd", pid)) else {
+            return Vec::new();
+        };
+        fds.filter_map(|fd| {
+            let target = std::fs::read_link(fd.ok()?.path()).ok()?;
+            let target = target.to_str()?;
+            target.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+        }).collect()
+    }
+
+    pub fn listening_ports(pids: &[u32]) -> Vec<u16> {
+        let sockets = listening_sockets();
+        if sockets.is_empty() {
+            return Vec::new();
+        }
+        let inodes: HashSet<u64> = pids.iter().flat_map(|pid| socket_inodes(*pid)).collect();
+        let mut ports: Vec<u16> = sockets.into_iter()
+            .filter(|s| inodes.contains(&s.inode))
+            .map(|s| s.port)
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        ports
+    }
+
+    pub fn port_owners(port: u16) -> Vec<PortOwner> {
+        let sockets: Vec<ListeningSocket> = listening_sockets().into_iter().filter(|s| s.port == port).collect();
+        if sockets.is_empty() {
+            return Vec::new();
+        }
+        let wanted: HashSet<u64> = sockets.iter().map(|s| s.inode).collect();
+
+        let mut holders: HashMap<u64, u32> = HashMap::new();
+        if let Ok(entries) = std::fs::read_dir("/proc") {
+            for pid in entries.filter_map(|e| e.ok()?.file_name().to_str()?.parse::<u32>().ok()) {
+                for inode in socket_inodes(pid) {
+                    if wanted.contains(&inode) {
+                        holders.entry(inode).or_insert(pid);
+                    }
+                }
+            }
+        }
+
+        sockets.into_iter().map(|socket| {
+            let pid = holders.get(&socket.inode).copied();
+            PortOwner {
+                port: socket.port,
+                address: socket.address,
+                pid,
+                name: pid.and_then(|p| std::fs::read_to_string(format!("/proc/{}/comm", p)).ok())
+                    .map(|n| n.trim().to_string()),
+                command: pid.and_then(|p| std::fs::read(format!("/proc/{}/cmdline", p)).ok())
+                    .map(|c| String::from_utf8_lossy(&c).split('\0').filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ")),
+                process_id: None,
+            }
+        }).collect()
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+mod platform {
+    use super::*;
+
+    /// `lsof -F` output: one field per line, prefixed by `p` (pid), `c` (command) or `n` (name).
+    fn lsof(args: &[&str]) -> Vec<PortOwner> {
+        let Ok(output) = std::process::Command::new("lsof")
+            .args(["-nP", "-sTCP:LISTEN", "-Fpcn"])
+            .args(args)
+            .output() else {
+            return Vec::new();
+        };
+        let mut owners = Vec::new();
+        let (mut pid, mut name) = (None, None);
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let (tag, value) = line.split_at(1.min(line.len()));
+            match tag {
+                "p" => pid = value.parse().ok(),
+                "c" => name = Some(value.to_string()),
+                "n" => {
+                    if let Some((address, port)) = value.rsplit_once(':') {
+                        if let Ok(port) = port.parse() {
+                            owners.push(PortOwner {
+                                port,
+                                address: address.to_string(),
+                                pid,
+                                name: name.clone(),
+                                command: name.clone(),
+                                process_id: None,
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        owners
+    }
+
+    pub fn listening_ports(pids: &[u32]) -> Vec<u16> {
+        if pids.is_empty() {
+            return Vec::new();
+        }
+        let pid_list: Vec<String> = pids.iter().map(|p| p.to_string()).collect();
+        let mut ports: Vec<u16> = lsof(&["-a", "-iTCP", "-p", &pid_list.join(",")])
+            .into_iter()
+            .map(|o| o.port)
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        ports
+    }
+
+    pub fn port_owners(port: u16) -> Vec<PortOwner> {
+        lsof(&[&format!("-iTCP:{}", port)])
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use super::*;
+
+    pub fn listening_ports(_pids: &[u32]) -> Vec<u16> {
+        Vec::new()
+    }
+
+    pub fn port_owners(_port: u16) -> Vec<PortOwner> {
+        Vec::new()
+    }
+}
+
+pub use platform::{listening_ports, port_owners};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_net_tcp() {
+        let tcp = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+            0: 0100007F:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 41234 1 0000000000000000 100 0 0 10 0\n\
+            1: 0100007F:0BB8 0100007F:C350 01 00000000:00000000 00:00000000 00000000  1000        0 41299 1 0000000000000000 20 4 30 10 -1\n";
+        assert_eq!(parse_proc_net_tcp(tcp), vec![ListeningSocket {
+            port: 3000,
+            address: "127.0.0.1".into(),
+            inode: 41234,
+        }]);
+
+        let tcp6 = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+            0: 00000000000000000000000000000000:1F90 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 5150 1 0000000000000000 100 0 0 10 0\n";
+        let sockets = parse_proc_net_tcp(tcp6);
+        assert_eq!(sockets[0].port, 8080);
+        assert_eq!(sockets[0].address, "::");
+    }
+
+    #[test]
+    fn test_port_from_conflict() {
+        assert_eq!(port_from_conflict("Error: listen EADDRINUSE: address already in use :::3000\n    at Server.setupListenHandle"), Some(3000));
+        assert_eq!(port_from_conflict("Error: address already in use 127.0.0.1:8000"), Some(8000));
+        assert_eq!(port_from_conflict("OSError: [Errno 98] Address already in use"), None);
+        assert_eq!(port_from_conflict("listening on :3000"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_finds_our_own_listener() -> anyhow::Result<()> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+
+        assert!(listening_ports(&[std::process::id()]).contains(&port));
+        let owners = port_owners(port);
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].pid, Some(std::process::id()));
+        assert_eq!(owners[0].address, "127.0.0.1");
+        Ok(())
+    }
+}
@@ -0,0 +1,302 @@
+use super::models::{AlertRule, AlertSeverity, ImportedService, ProcessDefinition, ProcessDefinitionInput, RestartPolicy};
+use anyhow::{anyhow, Result};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+const SELECT_DEFINITION: &str = "SELECT id, project_id, name, command, cwd, env, autostart, restart_policy, max_restarts, backoff_ms, depends_on, ready_port, ready_pattern, created_at, updated_at FROM project_processes";
+
+fn validate(input: &ProcessDefinitionInput) -> Result<()> {
+    if input.name.trim().is_empty() {
+        return Err(anyhow!("Process name is required"));
+    }
+    input.restart_policy.parse::<RestartPolicy>()?;
+    if let Some(pattern) = &input.ready_pattern {
+        regex::Regex::new(pattern)?;
+    }
+    Ok(())
+}
+
+fn validate_alert_rule(pattern: &str, severity: &str) -> Result<()> {
+    severity.parse::<AlertSeverity>()?;
+    regex::Regex::new(pattern)?;
+    Ok(())
+}
+
+pub struct ProcessRepository {
+    pool: SqlitePool,
+}
+
+impl ProcessRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_definition(&self, project_id: String, input: ProcessDefinitionInput) -> Result<ProcessDefinition> {
+        validate(&input)?;
+        let id = Uuid::new_v4().to_string();
+
+        sqlx::query("INSERT INTO project_processes (id, project_id, name, command, cwd, env, autostart, restart_policy, max_restarts, backoff_ms, depends_on, ready_port, ready_pattern) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(&id)
+            .bind(&project_id)
+            .bind(&input.name)
+            .bind(&input.command)
+            .bind(&input.cwd)
+            .bind(serde_json::to_string(&input.env)?)
+            .bind(input.autostart)
+            .bind(&input.restart_policy)
+            .bind(input.max_restarts)
+            .bind(input.backoff_ms)
+            .bind(serde_json::to_string(&input.depends_on)?)
+            .bind(input.ready_port.map(i64::from))
+            .bind(&input.ready_pattern)
+            .execute(&self.pool)
+            .await?;
+
+        self.get_definition(&id).await?.ok_or_else(|| anyhow!("Failed to retrieve created process definition"))
+    }
+
+    pub async fn get_definition(&self, id: &str) -> Result<Option<ProcessDefinition>> {
+        let definition = sqlx::query_as::<_, ProcessDefinition>(&format!("{} WHERE id = ?", SELECT_DEFINITION))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(definition)
+    }
+
+    pub async fn get_definitions(&self, project_id: &str) -> Result<Vec<ProcessDefinition>> {
+        let definitions = sqlx::query_as::<_, ProcessDefinition>(&format!("{} WHERE project_id = ? ORDER BY created_at ASC", SELECT_DEFINITION))
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(definitions)
+    }
+
+    pub async fn update_definition(&self, id: &str, input: ProcessDefinitionInput) -> Result<()> {
+        validate(&input)?;
+        sqlx::query("UPDATE project_processes SET name = ?, command = ?, cwd = ?, env = ?, autostart = ?, restart_policy = ?, max_restarts = ?, backoff_ms = ?, depends_on = ?, ready_port = ?, ready_pattern = ? WHERE id = ?")
+            .bind(input.name)
+            .bind(input.command)
+            .bind(input.cwd)
+            .bind(serde_json::to_string(&input.env)?)
+            .bind(input.autostart)
+            .bind(input.restart_policy)
+            .bind(input.max_restarts)
+            .bind(input.backoff_ms)
+            .bind(serde_json::to_string(&input.depends_on)?)
+            .bind(input.ready_port.map(i64::from))
+            .bind(input.ready_pattern)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Creates or updates definitions by name, used when importing a Procfile or stack file.
+    /// Existing definitions only take the fields the file sets.
+    pub async fn upsert_definitions(&self, project_id: &str, services: Vec<ImportedService>) -> Result<Vec<ProcessDefinition>> {
+        let existing = self.get_definitions(project_id).await?;
+        let mut saved = Vec::new();
+        for service in services {
+            match existing.iter().find(|d| d.name == service.name) {
+                Some(definition) => {
+                    self.update_definition(&definition.id, service.apply_to(definition.into())).await?;
+                    saved.push(self.get_definition(&definition.id).await?.ok_or_else(|| anyhow!("Process definition vanished during import"))?);
+                }
+                None => saved.push(self.create_definition(project_id.to_string(), service.into_input()).await?),
+            }
+        }
+        Ok(saved)
+    }
+
+    pub async fn delete_definition(&self, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM project_processes WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Alert rules
+
+    pub async fn create_alert_rule(&self, project_id: String, label: Option<String>, pattern: String, severity: String) -> Result<AlertRule> {
+        validate_alert_rule(&pattern, &severity)?;
+        let id = Uuid::new_v4().to_string();
+
+        sqlx::query("INSERT INTO process_alert_rules (id, project_id, label, pattern, severity) VALUES (?, ?, ?, ?, ?)")
+            .bind(&id)
+            .bind(&project_id)
+            .bind(&label)
+            .bind(&pattern)
+            .bind(&severity)
+            .execute(&self.pool)
+            .await?;
+
+        self.get_alert_rule(&id).await?.ok_or_else(|| anyhow!("Failed to retrieve created alert rule"))
+    }
+
+    pub async fn get_alert_rule(&self, id: &str) -> Result<Option<AlertRule>> {
+        let rule = sqlx::query_as::<_, AlertRule>("SELECT id, project_id, label, pattern, severity, created_at FROM process_alert_rules WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(rule)
+    }
+
+    pub async fn get_alert_rules(&self, project_id: &str) -> Result<Vec<AlertRule>> {
+        let rules = sqlx::query_as::<_, AlertRule>("SELECT id, project_id, label, pattern, severity, created_at FROM process_alert_rules WHERE project_id = ? ORDER BY created_at ASC")
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rules)
+    }
+
+    pub async fn update_alert_rule(&self, id: &str, label: Option<String>, pattern: String, severity: String) -> Result<()> {
+        validate_alert_rule(&pattern, &severity)?;
+        sqlx::query("UPDATE process_alert_rules SET label = ?, pattern = ?, severity = ? WHERE id = ?")
+            .bind(label)
+            .bind(pattern)
+            .bind(severity)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_alert_rule(&self, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM process_alert_rules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::projects::repository::ProjectRepository;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_process_definition_crud() -> Result<()> {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await?;
+
+        let project = ProjectRepository::new(pool.clone())
+            .create_project("Test Project".into(), "/tmp/test".into(), None)
+            .await?;
+        let repo = ProcessRepository::new(pool);
+
+        let mut input = ProcessDefinitionInput::new("api".into(), "npm run dev".into());
+        input.cwd = Some("server".into());
+        input.env.insert("PORT".into(), "3000".into());
+        input.autostart = true;
+        input.restart_policy = "on-failure".into();
+        input.max_restarts = Some(3);
+        input.depends_on = vec!["db".into()];
+        let created = repo.create_definition(project.id.clone(), input).await?;
+        assert!(created.autostart);
+        assert_eq!(created.env_vars(), vec![("PORT".to_string(), "3000".to_string())]);
+        assert_eq!(created.dependencies(), vec!["db".to_string()]);
+
+        let mut bad = ProcessDefinitionInput::new("bad".into(), "true".into());
+        bad.restart_policy = "sometimes".into();
+        assert!(repo.create_definition(project.id.clone(), bad).await.is_err());
+
+        let mut update = ProcessDefinitionInput::new("api".into(), "npm start".into());
+        update.restart_policy = "always".into();
+        update.ready_port = Some(3000);
+        repo.update_definition(&created.id, update).await?;
+        let updated = repo.get_definition(&created.id).await?.expect("definition");
+        assert_eq!(updated.command, "npm start");
+        assert!(!updated.autostart);
+        assert_eq!(updated.max_restarts, None);
+        assert_eq!(updated.ready_port, Some(3000));
+
+        // Upsert matches by name
+        let imported = repo.upsert_definitions(&project.id, vec![
+            ImportedService::new("api".into(), "npm run serve".into()),
+            ImportedService::new("worker".into(), "npm run worker".into()),
+        ]).await?;
+        assert_eq!(imported[0].id, created.id);
+        assert_eq!(imported[0].command, "npm run serve");
+        assert_eq!(repo.get_definitions(&project.id).await?.len(), 2);
+        repo.delete_definition(&imported[1].id).await?;
+        repo.delete_definition(&created.id).await?;
+        assert!(repo.get_definitions(&project.id).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reimport_keeps_customised_fields() -> Result<()> {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await?;
+
+        let project = ProjectRepository::new(pool.clone())
+            .create_project("Test Project".into(), "/tmp/test".into(), None)
+            .await?;
+        let repo = ProcessRepository::new(pool);
+
+        let imported = repo.upsert_definitions(&project.id, vec![ImportedService::new("api".into(), "npm run dev".into())]).await?;
+        let mut customised = ProcessDefinitionInput::from(&imported[0]);
+        customised.autostart = true;
+        customised.restart_policy = "on-failure".into();
+        customised.max_restarts = Some(5);
+        customised.backoff_ms = 250;
+        customised.depends_on = vec!["db".into()];
+        customised.ready_pattern = Some("Listening".into());
+        repo.update_definition(&imported[0].id, customised).await?;
+
+        // The Procfile only knows the command, a stack file also sets the ready port
+        let mut reimported = ImportedService::new("api".into(), "npm run serve".into());
+        reimported.ready_port = Some(4000);
+        let api = repo.upsert_definitions(&project.id, vec![reimported]).await?.remove(0);
+        assert_eq!(api.id, imported[0].id);
+        assert_eq!(api.command, "npm run serve");
+        assert_eq!(api.ready_port, Some(4000));
+        assert!(api.autostart);
+        assert_eq!(api.restart_policy, "on-failure");
+        assert_eq!((api.max_restarts, api.backoff_ms), (Some(5), 250));
+        assert_eq!(api.dependencies(), vec!["db".to_string()]);
+        assert_eq!(api.ready_pattern.as_deref(), Some("Listening"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_alert_rule_crud() -> Result<()> {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await?;
+
+        let project = ProjectRepository::new(pool.clone())
+            .create_project("Test Project".into(), "/tmp/test".into(), None)
+            .await?;
+        let repo = ProcessRepository::new(pool);
+
+        let rule = repo.create_alert_rule(project.id.clone(), Some("TypeScript".into()), r"error TS\d+".into(), "error".into()).await?;
+        assert!(repo.create_alert_rule(project.id.clone(), None, "(".into(), "error".into()).await.is_err());
+        assert!(repo.create_alert_rule(project.id.clone(), None, "ok".into(), "loud".into()).await.is_err());
+
+        repo.update_alert_rule(&rule.id, None, "Compiled successfully".into(), "success".into()).await?;
+        let rules = repo.get_alert_rules(&project.id).await?;
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].severity, "success");
+
+        repo.delete_alert_rule(&rule.id).await?;
+        assert!(repo.get_alert_rule(&rule.id).await?.is_none());
+
+        Ok(())
+    }
+}
@@ -1,13 +1,26 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-// Actually checkuse std::sync::{Arc, Mutex};
-use tauri::{Window, Emitter};
-use portable_pty::{CommandBuilder, NativePtySystem, PtySize, PtySystem};
+use portable_pty::{ChildKiller, CommandBuilder, NativePtySystem, PtySize, PtySystem};
 
-use std::io::{Read};
+use std::io::Write;
 use std::thread;
+use std::time::Duration;
 use anyhow::{Result, anyhow};
 use uuid::Uuid;
-use super::models::{Process, ProcessSession, ProcessState};
+use super::alerts::AlertMatcher;
+use crate::shared::history::{HistoryChunk, OutputHistory};
+use super::models::{Process, ProcessSession, ProcessSpec, ProcessState, ProcessStatus};
+use super::ports::{self, PortOwner};
+use super::tree::{self, TerminateReport};
+use crate::modules::terminal::recording::{record_output, record_resize, RecordingSlot, RecordingTarget};
+use crate::shared::events::EventSink;
+use crate::shared::pty_stream::{stream_pty_with_end, PtyWriter, StreamConfig};
+use crate::shared::utils::now_millis;
+
+// Enough of the output tail to catch a stack trace that mentions EADDRINUSE
+const CONFLICT_SCAN_BYTES: usize = 8 * 1024;
+// Grandchildren can hold the PTY open long after the child is gone
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
 
 pub struct ProcessService {
     state: ProcessState,
@@ -18,7 +31,12 @@ impl ProcessService {
         Self { state }
     }
 
-    pub fn start_process(&self, window: Window, command: String, cwd: String) -> Result<Process> {
+    pub fn start_process<S: EventSink + Clone>(&self, sink: S, command: String, cwd: String) -> Result<Process> {
+        self.spawn(sink, ProcessSpec::new(command, cwd))
+    }
+
+    pub fn spawn<S: EventSink + Clone>(&self, sink: S, spec: ProcessSpec) -> Result<Process> {
+        let ProcessSpec { command, cwd, env, definition_id, project_id } = spec;
         let pty_system = NativePtySystem::default();
 
         // Wrap command in shell to handle arguments and PATH resolution properly
@@ -30,6 +48,9 @@ impl ProcessService {
         let mut cmd = CommandBuilder::new(shell);
         cmd.args(&args);
         cmd.cwd(&cwd);
+        for (key, value) in env {
+            cmd.env(key, value);
+        }
 
         let pair = pty_system.openpty(PtySize {
             rows: 24,
@@ -38,125 +59,559 @@ impl ProcessService {
             pixel_height: 0,
         })?;
 
-        let child = pair.slave.spawn_command(cmd)?;
+        let mut child = pair.slave.spawn_command(cmd)?;
+        drop(pair.slave);
         
         let pid = child.process_id().unwrap_or(0);
+        let killer = child.clone_killer();
 
         let id = Uuid::new_v4().to_string();
         
         
-        let mut reader = pair.master.try_clone_reader()?;
+        let reader = pair.master.try_clone_reader()?;
+        let writer: PtyWriter = Arc::new(Mutex::new(pair.master.take_writer()?));
         let process_id = id.clone();
-        let window_clone = window.clone();
+        let output_sink = sink.clone();
         
-        let history = Arc::new(Mutex::new(String::new()));
+        let history = {
+            let config = self.state.config.lock().unwrap();
+            let spill_path = config.log_dir.as_ref().map(|dir| dir.join(format!("{}.log", id)));
+            Arc::new(Mutex::new(OutputHistory::new(config.history_limit, spill_path)))
+        };
         let history_clone = history.clone();
+        // Shared with the end of the stream, which evaluates a last line without a newline
+        let alerts = project_id.clone()
+            .map(|project_id| Arc::new(Mutex::new(AlertMatcher::new(id.clone(), project_id, self.state.clone()))));
+        let alerts_at_end = alerts.clone();
+        let end_sink = output_sink.clone();
+        let recording: RecordingSlot = Arc::new(Mutex::new(None));
+        let recording_clone = recording.clone();
 
-        thread::spawn(move || {
-            let mut buf = [0u8; 1024];
-            loop {
-                match reader.read(&mut buf) {
-                    Ok(n) if n > 0 => {
-                        let data = String::from_utf8_lossy(&buf[..n]).to_string();
-                        // Append to history
-                        if let Ok(mut lock) = history_clone.lock() {
-                            lock.push_str(&data);
-                        }
-                        
-                        // Emit event to frontend
-                        let _ = window_clone.emit("process_output", serde_json::json!({
-                            "id": process_id,
-                            "data": data
-                        }));
-                    }
-                    Ok(_) => break, // EOF
-                    Err(_) => break, // Error
-                }
+        let output = stream_pty_with_end(reader, StreamConfig::default(), move |data| {
+            // Append to history
+            if let Ok(mut lock) = history_clone.lock() {
+                lock.push(data.as_bytes());
             }
-            let _ = window_clone.emit("process_exit", serde_json::json!({
-                "id": process_id
+
+            // Emit event to frontend
+            output_sink.emit_event("process_output", serde_json::json!({
+                "id": process_id,
+                "data": data
             }));
+
+            if let Some(alerts) = &alerts {
+                alerts.lock().unwrap().feed(data, &output_sink);
+            }
+
+            record_output(&recording_clone, data);
+        }, move || {
+            if let Some(alerts) = alerts_at_end {
+                alerts.lock().unwrap().flush(&end_sink);
+            }
         });
 
+        let started_at = now_millis();
         let session = ProcessSession {
-            pty_pair: pair,
-            process: child,
-            running: true,
+            master: pair.master,
+            writer,
+            killer,
+            pid,
+            status: ProcessStatus::Running,
+            exit_code: None,
+            kill_requested: false,
+            started_at,
+            ended_at: None,
             history,
             command: command.clone(),
             cwd: cwd.clone(),
+            definition_id,
+            stats: None,
+            ports: Vec::new(),
+            project_id,
+            alert_counts: HashMap::new(),
+            recording,
         };
 
-        self.state.lock().unwrap().insert(id.clone(), session);
+        let process = session.to_process(&id);
+        self.state.sessions.lock().unwrap().insert(id.clone(), session);
 
-        Ok(Process {
-            id,
-            command,
-            cwd,
-            running: true,
-            pid,
-        })
+        // Reaper: owns the child and blocks on wait() so exit status is recorded
+        // even if nobody is looking at the process list.
+        let state = self.state.clone();
+        thread::spawn(move || {
+            let exit_code = child.wait().map(|status| status.exit_code()).ok();
+            let ended_at = now_millis();
+
+            let exited = {
+                let mut state = state.sessions.lock().unwrap();
+                state.get_mut(&id).map(|session| {
+                    session.status = if session.kill_requested {
+                        ProcessStatus::Killed
+                    } else {
+                        ProcessStatus::Exited
+                    };
+                    session.exit_code = exit_code;
+                    session.ended_at = Some(ended_at);
+                    session.status
+                })
+            };
+
+            if let Some(status) = exited {
+                sink.emit_event("process_exit", serde_json::json!({
+                    "id": id,
+                    "status": status,
+                    "exit_code": exit_code,
+                    "ended_at": ended_at
+                }));
+
+                // A crashed dev server usually means EADDRINUSE, say who has the port
+                if status == ProcessStatus::Exited && exit_code != Some(0) {
+                    // The reader may still be draining the last lines of output
+                    output.wait(OUTPUT_DRAIN_TIMEOUT);
+                    let service = ProcessService::new(state);
+                    let conflict = service.get_history(&id, None, Some(CONFLICT_SCAN_BYTES))
+                        .ok()
+                        .and_then(|tail| ports::port_from_conflict(&tail.data));
+                    if let Some(port) = conflict {
+                        sink.emit_event("process_port_conflict", serde_json::json!({
+                            "id": id,
+                            "port": port,
+                            "owners": service.find_port_owner(port)
+                        }));
+                    }
+                }
+            }
+        });
+
+        Ok(process)
     }
 
-    pub fn write(&self, _id: &str, _data: &str) -> Result<()> {
-        // FIXME: portable-pty trait bounds for Write are fighting with Box<dyn MasterPty + Send>
-        // For now, we stub this to allow compilation of the read-loop and frontend.
-        // We will fix interaction later (maybe by downcasting or using raw FDs).
-        Err(anyhow!("Writing to process is currently disabled due to backend trait issues"))
+    pub fn write(&self, id: &str, data: &str) -> Result<()> {
+        // The write can block on a full PTY buffer, so it must not hold the sessions lock
+        let writer = {
+            let state = self.state.sessions.lock().unwrap();
+            let session = state.get(id).ok_or_else(|| anyhow!("Process not found"))?;
+            if session.status != ProcessStatus::Running {
+                return Err(anyhow!("Process is not running"));
+            }
+            session.writer.clone()
+        };
+        let mut writer = writer.lock().unwrap();
+        writer.write_all(data.as_bytes())?;
+        writer.flush()?;
+        Ok(())
     }
 
 
     pub fn resize(&self, id: &str, cols: u16, rows: u16) -> Result<()> {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state.sessions.lock().unwrap();
         if let Some(session) = state.get_mut(id) {
-            session.pty_pair.master.resize(PtySize {
+            session.master.resize(PtySize {
                 rows,
                 cols,
                 pixel_width: 0,
                 pixel_height: 0,
             })?;
+            record_resize(&session.recording, cols, rows);
             Ok(())
         } else {
             Err(anyhow!("Process not found"))
         }
     }
 
+    pub fn recording_target(&self, id: &str) -> Result<RecordingTarget> {
+        let state = self.state.sessions.lock().unwrap();
+        let session = state.get(id).ok_or_else(|| anyhow!("Process not found"))?;
+        let size = session.master.get_size()?;
+        Ok(RecordingTarget {
+            slot: session.recording.clone(),
+            project_id: session.project_id.clone(),
+            title: session.command.clone(),
+            width: size.cols,
+            height: size.rows,
+        })
+    }
+
+    /// Stops the whole process tree: SIGTERM, then SIGKILL for anything still alive
+    /// after the grace period. Blocks until done; the reaper thread records the final status.
+    pub fn terminate(&self, id: &str, grace: Option<Duration>) -> Result<TerminateReport> {
+        let target = {
+            let mut state = self.state.sessions.lock().unwrap();
+            let session = state.get_mut(id).ok_or_else(|| anyhow!("Process not found"))?;
+            begin_kill(session)
+        };
+        let grace = grace.unwrap_or_else(|| self.state.config.lock().unwrap().kill_grace);
+        match target {
+            Some((pid, killer)) => terminate_pid(pid, killer, grace),
+            None => Ok(TerminateReport::default()),
+        }
+    }
+
+    /// Same as `terminate` but returns immediately, for callers that don't need the report.
     pub fn kill(&self, id: &str) -> Result<()> {
-        let mut state = self.state.lock().unwrap();
+        let target = {
+            let mut state = self.state.sessions.lock().unwrap();
+            let session = state.get_mut(id).ok_or_else(|| anyhow!("Process not found"))?;
+            begin_kill(session)
+        };
+        self.terminate_in_background(target);
+        Ok(())
+    }
+
+    /// Drops a process from the list, killing it first if it is still running.
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let mut state = self.state.sessions.lock().unwrap();
         if let Some(mut session) = state.remove(id) {
-            session.process.kill()?;
+            self.terminate_in_background(begin_kill(&mut session));
+            session.history.lock().unwrap().discard();
             Ok(())
         } else {
             Err(anyhow!("Process not found"))
         }
     }
 
-    pub fn get_history(&self, id: &str) -> Result<String> {
-        let state = self.state.lock().unwrap();
-        if let Some(session) = state.get(id) {
-             let history = session.history.lock().unwrap();
-             Ok(history.clone())
-        } else {
-            Err(anyhow!("Process not found"))
+    fn terminate_in_background(&self, target: Option<(u32, Box<dyn ChildKiller + Send + Sync>)>) {
+        if let Some((pid, killer)) = target {
+            let grace = self.state.config.lock().unwrap().kill_grace;
+            thread::spawn(move || {
+                if let Err(e) = terminate_pid(pid, killer, grace) {
+                    println!("ERROR: Failed to stop process {}: {}", pid, e);
+                }
+            });
         }
     }
 
-    pub fn list_processes(&self) -> Result<Vec<Process>> {
-        let state = self.state.lock().unwrap();
-        let mut processes = Vec::new();
-        for (id, session) in state.iter() {
-             // Check if process is still running?
-             // Or rely on 'running' flag?
-             // Ideally we check session.process.try_wait()? 
-             
-             processes.push(Process {
-                 id: id.clone(),
-                 command: session.command.clone(),
-                 cwd: session.cwd.clone(),
-                 running: session.running,
-                 pid: 0, // session.process.process_id().unwrap_or(0), can't easily access
-             });
+    /// Stops every running process in parallel, used when the app exits.
+    pub fn shutdown(&self, grace: Duration) {
+        let targets: Vec<_> = {
+            let mut state = self.state.sessions.lock().unwrap();
+            state.values_mut().filter_map(begin_kill).collect()
+        };
+        let handles: Vec<_> = targets.into_iter()
+            .map(|(pid, killer)| thread::spawn(move || terminate_pid(pid, killer, grace)))
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+    }
+
+    pub fn set_kill_grace(&self, grace: Duration) {
+        self.state.config.lock().unwrap().kill_grace = grace;
+    }
+
+    /// Pages through process output, see `HistoryChunk` for the offset semantics.
+    /// Without a limit the in-memory tail is returned.
+    pub fn get_history(&self, id: &str, offset: Option<u64>, limit: Option<usize>) -> Result<HistoryChunk> {
+        let history = {
+            let state = self.state.sessions.lock().unwrap();
+            let session = state.get(id).ok_or_else(|| anyhow!("Process not found"))?;
+            session.history.clone()
+        };
+        let limit = limit.unwrap_or_else(|| self.state.config.lock().unwrap().history_limit);
+        let history = history.lock().unwrap();
+        history.read(offset, limit)
+    }
+
+    /// Applies a new per-process history cap to new and running processes.
+    pub fn set_history_limit(&self, limit: usize) -> Result<()> {
+        if limit == 0 {
+            return Err(anyhow!("History limit must be greater than zero"));
+        }
+        self.state.config.lock().unwrap().history_limit = limit;
+        let state = self.state.sessions.lock().unwrap();
+        for session in state.values() {
+            session.history.lock().unwrap().set_capacity(limit);
+        }
+        Ok(())
+    }
+
+    /// Everything listening on `port`, with owners inside a managed process tree linked to it.
+    pub fn find_port_owner(&self, port: u16) -> Vec<PortOwner> {
+        let mut owners = ports::port_owners(port);
+        let running: Vec<(String, u32)> = {
+            let state = self.state.sessions.lock().unwrap();
+            state.iter()
+                .filter(|(_, s)| s.status == ProcessStatus::Running && s.pid != 0)
+                .map(|(id, s)| (id.clone(), s.pid))
+                .collect()
+        };
+        if owners.iter().any(|o| o.pid.is_some()) && !running.is_empty() {
+            let table = tree::process_table();
+            for (id, pid) in running {
+                let members = tree::process_tree(pid, &table);
+                for owner in owners.iter_mut() {
+                    if owner.pid.is_some_and(|p| members.contains(&p)) {
+                        owner.process_id = Some(id.clone());
+                    }
+                }
+            }
+        }
+        owners
+    }
+
+    /// Stops the process holding `port`, provided `pid` still holds it. Managed processes go
+    /// through `terminate` so their status is kept; anything else is signalled on its own,
+    /// never together with its group or children.
+    pub fn kill_port_owner(&self, port: u16, pid: u32, grace: Option<Duration>) -> Result<TerminateReport> {
+        if pid <= 1 || pid == std::process::id() {
+            return Err(anyhow!("Refusing to kill PID {}", pid));
+        }
+        if !ports::port_owners(port).iter().any(|o| o.pid == Some(pid)) {
+            return Err(anyhow!("PID {} no longer holds port {}", pid, port));
+        }
+
+        let managed = {
+            let state = self.state.sessions.lock().unwrap();
+            state.iter()
+                .find(|(_, s)| s.pid == pid && s.status == ProcessStatus::Running)
+                .map(|(id, _)| id.clone())
+        };
+        if let Some(id) = managed {
+            return self.terminate(&id, grace);
         }
+        let grace = grace.unwrap_or_else(|| self.state.config.lock().unwrap().kill_grace);
+        #[cfg(unix)]
+        return Ok(tree::terminate_process(pid, grace));
+        #[cfg(not(unix))]
+        return Err(anyhow!("Killing unmanaged processes is not supported on this platform"));
+    }
+
+    pub fn get_process(&self, id: &str) -> Result<Process> {
+        let state = self.state.sessions.lock().unwrap();
+        state.get(id)
+            .map(|session| session.to_process(id))
+            .ok_or_else(|| anyhow!("Process not found"))
+    }
+
+    pub fn list_processes(&self) -> Result<Vec<Process>> {
+        let state = self.state.sessions.lock().unwrap();
+        let mut processes: Vec<Process> = state.iter()
+            .map(|(id, session)| session.to_process(id))
+            .collect();
+        processes.sort_by_key(|p| p.started_at);
         Ok(processes)
     }
 }
+
+/// Marks a running session as user-killed and hands back what is needed to stop it.
+fn begin_kill(session: &mut ProcessSession) -> Option<(u32, Box<dyn ChildKiller + Send + Sync>)> {
+    if session.status != ProcessStatus::Running || session.kill_requested {
+        return None;
+    }
+    session.kill_requested = true;
+    Some((session.pid, session.killer.clone_killer()))
+}
+
+#[cfg(unix)]
+fn terminate_pid(pid: u32, mut killer: Box<dyn ChildKiller + Send + Sync>, grace: Duration) -> Result<TerminateReport> {
+    if pid == 0 {
+        // The platform didn't give us a pid, all we can do is hang up on the PTY child
+        killer.kill()?;
+        return Ok(TerminateReport::default());
+    }
+    Ok(tree::terminate_tree(pid, grace))
+}
+
+#[cfg(not(unix))]
+fn terminate_pid(pid: u32, mut killer: Box<dyn ChildKiller + Send + Sync>, _grace: Duration) -> Result<TerminateReport> {
+    killer.kill()?;
+    Ok(TerminateReport { terminated: Vec::new(), killed: vec![pid] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[derive(Clone)]
+    struct NullSink;
+
+    impl EventSink for NullSink {
+        fn emit_event(&self, _event: &str, _payload: serde_json::Value) {}
+    }
+
+    fn wait_for_history(service: &ProcessService, id: &str, needle: &str) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if service.get_history(id, None, None).map(|h| h.data.contains(needle)).unwrap_or(false) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_to_process_echoes_through_history() -> Result<()> {
+        let service = ProcessService::new(ProcessState::new(None));
+        let cwd = std::env::temp_dir().to_string_lossy().to_string();
+
+        let process = service.start_process(NullSink, "cat".into(), cwd)?;
+        service.write(&process.id, "hello switchboard\n")?;
+        assert!(wait_for_history(&service, &process.id, "hello switchboard"));
+
+        service.resize(&process.id, 120, 40)?;
+        service.kill(&process.id)?;
+        assert!(wait_for_status(&service, &process.id, ProcessStatus::Killed));
+        assert!(service.write(&process.id, "gone\n").is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_blocked_write_does_not_hold_the_sessions_lock() -> Result<()> {
+        let state = ProcessState::new(None);
+        let service = ProcessService::new(state.clone());
+        let cwd = std::env::temp_dir().to_string_lossy().to_string();
+
+        // Never reads stdin, so a big enough write fills the PTY and blocks
+        let process = service.start_process(NullSink, "sleep 30".into(), cwd)?;
+        let id = process.id.clone();
+        let writer = thread::spawn(move || ProcessService::new(state).write(&id, &"line\n".repeat(256 * 1024)));
+        thread::sleep(Duration::from_millis(300));
+        assert!(!writer.is_finished());
+
+        let started = Instant::now();
+        service.list_processes()?;
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        // The stuck writer only holds up writes to this process
+        service.kill(&process.id)?;
+        assert!(wait_for_status(&service, &process.id, ProcessStatus::Killed));
+        Ok(())
+    }
+
+    fn wait_for_status(service: &ProcessService, id: &str, status: ProcessStatus) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if service.get_process(id).map(|p| p.status == status).unwrap_or(false) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_code_and_status_are_recorded() -> Result<()> {
+        let service = ProcessService::new(ProcessState::new(None));
+        let cwd = std::env::temp_dir().to_string_lossy().to_string();
+
+        let finished = service.start_process(NullSink, "exit 3".into(), cwd.clone())?;
+        assert!(finished.pid > 0);
+        assert!(wait_for_status(&service, &finished.id, ProcessStatus::Exited));
+        let finished = service.get_process(&finished.id)?;
+        assert_eq!(finished.exit_code, Some(3));
+        assert!(!finished.running);
+        assert!(finished.ended_at.is_some());
+
+        let sleeper = service.start_process(NullSink, "sleep 30".into(), cwd)?;
+        let listed = service.list_processes()?;
+        assert!(listed.iter().any(|p| p.id == sleeper.id && p.running && p.pid == sleeper.pid));
+
+        service.kill(&sleeper.id)?;
+        assert!(wait_for_status(&service, &sleeper.id, ProcessStatus::Killed));
+
+        service.remove(&sleeper.id)?;
+        assert!(service.get_process(&sleeper.id).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_kills_the_whole_tree() -> Result<()> {
+        let service = ProcessService::new(ProcessState::new(None));
+        let cwd = std::env::temp_dir().to_string_lossy().to_string();
+
+        let process = service.start_process(NullSink, "sleep 30 & sleep 31 & echo started; wait".into(), cwd.clone())?;
+        assert!(wait_for_history(&service, &process.id, "started"));
+        let report = service.terminate(&process.id, Some(Duration::from_secs(2)))?;
+        assert_eq!(report.terminated.len(), 3);
+        assert!(report.killed.is_empty());
+        assert!(report.terminated.iter().all(|pid| !tree::is_alive(*pid)));
+        assert!(wait_for_status(&service, &process.id, ProcessStatus::Killed));
+
+        // Ignoring SIGTERM only buys the grace period
+        let stubborn = service.start_process(NullSink, "trap '' TERM; sleep 30 & echo started; wait".into(), cwd)?;
+        assert!(wait_for_history(&service, &stubborn.id, "started"));
+        let report = service.terminate(&stubborn.id, Some(Duration::from_millis(200)))?;
+        assert!(report.killed.contains(&stubborn.pid));
+        assert!(wait_for_status(&service, &stubborn.id, ProcessStatus::Killed));
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_port_conflict_names_the_owner() -> Result<()> {
+        #[derive(Default)]
+        struct RecordingSink {
+            events: Mutex<Vec<(String, serde_json::Value)>>,
+        }
+
+        impl EventSink for RecordingSink {
+            fn emit_event(&self, event: &str, payload: serde_json::Value) {
+                self.events.lock().unwrap().push((event.to_string(), payload));
+            }
+        }
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+
+        let sink = Arc::new(RecordingSink::default());
+        let service = ProcessService::new(ProcessState::new(None));
+        let cwd = std::env::temp_dir().to_string_lossy().to_string();
+        let command = format!("echo 'Error: listen EADDRINUSE: address already in use :::{}'; exit 1", port);
+        let process = service.start_process(sink.clone(), command, cwd)?;
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let conflict = loop {
+            let found = sink.events.lock().unwrap().iter()
+                .find(|(event, _)| event == "process_port_conflict")
+                .map(|(_, payload)| payload.clone());
+            if let Some(payload) = found {
+                break payload;
+            }
+            assert!(Instant::now() < deadline, "no conflict reported");
+            thread::sleep(Duration::from_millis(50));
+        };
+
+        assert_eq!(conflict["id"], process.id.as_str());
+        assert_eq!(conflict["port"], port);
+        assert_eq!(conflict["owners"][0]["pid"], std::process::id());
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_kill_port_owner_checks_the_pid() -> Result<()> {
+        use std::io::BufRead;
+
+        let service = ProcessService::new(ProcessState::new(None));
+        let own = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let own_port = own.local_addr()?.port();
+        assert!(service.kill_port_owner(own_port, std::process::id(), None).is_err());
+        assert!(service.kill_port_owner(own_port, 1, None).is_err());
+
+        let mut listener = std::process::Command::new("python3")
+            .args(["-c", "import socket, time; s = socket.socket(); s.bind(('127.0.0.1', 0)); s.listen(); print(s.getsockname()[1], flush=True); time.sleep(30)"])
+            .stdout(std::process::Stdio::piped())
+            .spawn()?;
+        let mut line = String::new();
+        std::io::BufReader::new(listener.stdout.take().unwrap()).read_line(&mut line)?;
+        let port: u16 = line.trim().parse()?;
+
+        // Right PID, wrong port
+        assert!(service.kill_port_owner(own_port, listener.id(), None).is_err());
+        assert!(tree::is_alive(listener.id()));
+
+        let report = service.kill_port_owner(port, listener.id(), Some(Duration::from_secs(2)))?;
+        assert_eq!(report.terminated, vec![listener.id()]);
+        listener.wait()?;
+        Ok(())
+    }
+}
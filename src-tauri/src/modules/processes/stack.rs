@@ -0,0 +1,394 @@
+use super::models::{ImportedService, Process, ProcessDefinition, ProcessState, ProcessStatus};
+use super::repository::ProcessRepository;
+use super::service::ProcessService;
+use super::supervisor::ProcessSupervisor;
+use crate::modules::projects::repository::ProjectRepository;
+use crate::shared::events::EventSink;
+use crate::shared::utils::expand_path;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const PROCFILE: &str = "Procfile";
+const STACK_FILE: &str = "switchboard.toml";
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Parses a Procfile: one `name: command` per line, `#` comments allowed.
+pub fn parse_procfile(content: &str) -> Result<Vec<ImportedService>> {
+    let mut services = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, command) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Procfile line {}: expected 'name: command'", index + 1))?;
+        let (name, command) = (name.trim(), command.trim());
+        if name.is_empty() || command.is_empty() {
+            return Err(anyhow!("Procfile line {}: expected 'name: command'", index + 1));
+        }
+        services.push(ImportedService::new(name.to_string(), command.to_string()));
+    }
+    Ok(services)
+}
+
+#[derive(Debug, Deserialize)]
+struct StackFile {
+    #[serde(default)]
+    services: BTreeMap<String, StackFileService>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StackFileService {
+    command: String,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    autostart: Option<bool>,
+    restart: Option<String>,
+    max_restarts: Option<i64>,
+    backoff_ms: Option<i64>,
+    depends_on: Option<Vec<String>>,
+    ready_port: Option<u16>,
+    ready_log: Option<String>,
+}
+
+/// Parses a TOML stack file, e.g.
+///
+/// ```toml
+/// [services.api]
+/// command = "npm run dev"
+/// depends_on = ["db"]
+/// ready_port = 3000
+/// ```
+pub fn parse_stack_file(content: &str) -> Result<Vec<ImportedService>> {
+    let file: StackFile = toml::from_str(content).context("Invalid stack file")?;
+    Ok(file.services.into_iter().map(|(name, service)| ImportedService {
+        name,
+        command: service.command,
+        cwd: service.cwd,
+        env: service.env,
+        autostart: service.autostart,
+        restart_policy: service.restart,
+        max_restarts: service.max_restarts,
+        backoff_ms: service.backoff_ms,
+        depends_on: service.depends_on,
+        ready_port: service.ready_port,
+        ready_pattern: service.ready_log,
+    }).collect())
+}
+
+/// Orders definitions so every service comes after its dependencies.
+/// Ties keep the original order, so an unordered Procfile starts top to bottom.
+pub fn start_order(definitions: &[ProcessDefinition]) -> Result<Vec<ProcessDefinition>> {
+    let names: HashSet<&str> = definitions.iter().map(|d| d.name.as_str()).collect();
+    for definition in definitions {
+        for dependency in definition.dependencies() {
+            if !names.contains(dependency.as_str()) {
+                return Err(anyhow!("{} depends on unknown service {}", definition.name, dependency));
+            }
+        }
+    }
+
+    let mut ordered: Vec<ProcessDefinition> = Vec::new();
+    let mut placed: HashSet<String> = HashSet::new();
+    while ordered.len() < definitions.len() {
+        let next = definitions.iter().find(|d| {
+            !placed.contains(&d.name) && d.dependencies().iter().all(|dep| placed.contains(dep))
+        });
+        match next {
+            Some(definition) => {
+                placed.insert(definition.name.clone());
+                ordered.push(definition.clone());
+            }
+            None => {
+                let stuck: Vec<&str> = definitions.iter()
+                    .filter(|d| !placed.contains(&d.name))
+                    .map(|d| d.name.as_str())
+                    .collect();
+                return Err(anyhow!("Dependency cycle between: {}", stuck.join(", ")));
+            }
+        }
+    }
+    Ok(ordered)
+}
+
+/// Starts and stops a project's process definitions as one stack.
+pub struct StackService {
+    supervisor: ProcessSupervisor,
+    processes: ProcessState,
+    pool: SqlitePool,
+    repo: ProcessRepository,
+    sink: Arc<dyn EventSink>,
+}
+
+impl StackService {
+    pub fn new(supervisor: ProcessSupervisor, processes: ProcessState, pool: SqlitePool, sink: Arc<dyn EventSink>) -> Self {
+        Self {
+            supervisor,
+            processes,
+            repo: ProcessRepository::new(pool.clone()),
+            pool,
+            sink,
+        }
+    }
+
+    pub async fn import(&self, project_id: &str, services: Vec<ImportedService>) -> Result<Vec<ProcessDefinition>> {
+        self.repo.upsert_definitions(project_id, services).await
+    }
+
+    /// Imports `<project>/Procfile`, or the given path relative to the project.
+    pub async fn import_procfile(&self, project_id: &str, path: Option<String>) -> Result<Vec<ProcessDefinition>> {
+        let content = self.read_project_file(project_id, path.as_deref().unwrap_or(PROCFILE)).await?;
+        self.import(project_id, parse_procfile(&content)?).await
+    }
+
+    /// Imports `<project>/switchboard.toml`, or the given path relative to the project.
+    pub async fn import_stack_file(&self, project_id: &str, path: Option<String>) -> Result<Vec<ProcessDefinition>> {
+        let content = self.read_project_file(project_id, path.as_deref().unwrap_or(STACK_FILE)).await?;
+        self.import(project_id, parse_stack_file(&content)?).await
+    }
+
+    async fn read_project_file(&self, project_id: &str, path: &str) -> Result<String> {
+        let project = ProjectRepository::new(self.pool.clone())
+            .get_project(project_id)
+            .await?
+            .context("Project not found")?;
+        let path = Path::new(&expand_path(&project.path)).join(expand_path(path));
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))
+    }
+
+    /// Starts services in dependency order, waiting for each one to be ready before
+    /// moving on. Services that are already running are reused.
+    pub async fn start_stack(&self, project_id: &str) -> Result<Vec<Process>> {
+        let order = start_order(&self.repo.get_definitions(project_id).await?)?;
+        let service = ProcessService::new(self.processes.clone());
+        let mut started = Vec::new();
+
+        for definition in order {
+            self.progress(project_id, &definition, "starting", None);
+
+            let running = self.supervisor.current_process(&definition.id)
+                .and_then(|id| service.get_process(&id).ok())
+                .filter(|p| p.status == ProcessStatus::Running);
+            let process = match running {
+                Some(process) => process,
+                None => self.supervisor.start(&definition.id).await?,
+            };
+
+            if let Err(e) = self.wait_until_ready(&process.id, &definition).await {
+                self.progress(project_id, &definition, "failed", Some(e.to_string()));
+                return Err(e);
+            }
+            self.progress(project_id, &definition, "ready", None);
+            started.push(process);
+        }
+        Ok(started)
+    }
+
+    /// Stops services in reverse dependency order, waiting for each to exit.
+    pub async fn stop_stack(&self, project_id: &str) -> Result<()> {
+        let mut order = start_order(&self.repo.get_definitions(project_id).await?)?;
+        order.reverse();
+        let service = ProcessService::new(self.processes.clone());
+
+        for definition in order {
+            let Some(process_id) = self.supervisor.current_process(&definition.id) else {
+                continue;
+            };
+            self.supervisor.stop(&definition.id)?;
+            self.progress(project_id, &definition, "stopping", None);
+
+            let deadline = Instant::now() + STOP_TIMEOUT;
+            while Instant::now() < deadline {
+                match service.get_process(&process_id) {
+                    Ok(process) if process.status == ProcessStatus::Running => {
+                        tokio::time::sleep(POLL_INTERVAL).await;
+                    }
+                    _ => break,
+                }
+            }
+            self.progress(project_id, &definition, "stopped", None);
+        }
+        Ok(())
+    }
+
+    async fn wait_until_ready(&self, process_id: &str, definition: &ProcessDefinition) -> Result<()> {
+        let pattern = definition.ready_pattern.as_deref().map(regex::Regex::new).transpose()?;
+        let port = definition.ready_port.and_then(|p| u16::try_from(p).ok());
+        if pattern.is_none() && port.is_none() {
+            return Ok(());
+        }
+
+        let service = ProcessService::new(self.processes.clone());
+        let deadline = Instant::now() + READY_TIMEOUT;
+        let mut log_ready = pattern.is_none();
+        let mut port_ready = port.is_none();
+        let mut scanned = 0u64;
+        let mut partial_line = String::new();
+
+        loop {
+            if !log_ready {
+                if let Some(regex) = &pattern {
+                    let chunk = service.get_history(process_id, Some(scanned), None)?;
+                    scanned = chunk.end;
+                    partial_line.push_str(&chunk.data);
+                    log_ready = partial_line.lines().any(|line| regex.is_match(line));
+                    // Keep only the unfinished last line for the next poll
+                    if let Some(pos) = partial_line.rfind('\n') {
+                        partial_line.drain(..=pos);
+                    }
+                }
+            }
+            if !port_ready {
+                if let Some(port) = port {
+                    port_ready = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_ok();
+                }
+            }
+            if log_ready && port_ready {
+                return Ok(());
+            }
+
+            let process = service.get_process(process_id)?;
+            if process.status != ProcessStatus::Running {
+                return Err(anyhow!("{} exited before becoming ready", definition.name));
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow!("{} did not become ready within {}s", definition.name, READY_TIMEOUT.as_secs()));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    fn progress(&self, project_id: &str, definition: &ProcessDefinition, state: &str, error: Option<String>) {
+        self.sink.emit_event("stack_progress", serde_json::json!({
+            "project_id": project_id,
+            "definition_id": definition.id,
+            "name": definition.name,
+            "state": state,
+            "error": error
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    struct NullSink;
+
+    impl EventSink for NullSink {
+        fn emit_event(&self, _event: &str, _payload: serde_json::Value) {}
+    }
+
+    fn definition(name: &str, depends_on: &[&str]) -> ProcessDefinition {
+        ProcessDefinition {
+            id: name.to_string(),
+            project_id: "p".into(),
+            name: name.to_string(),
+            command: "true".into(),
+            cwd: None,
+            env: "{}".into(),
+            autostart: false,
+            restart_policy: "never".into(),
+            max_restarts: None,
+            backoff_ms: 1000,
+            depends_on: serde_json::to_string(depends_on).unwrap(),
+            ready_port: None,
+            ready_pattern: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_procfile() -> Result<()> {
+        let services = parse_procfile("# services\nweb: npm run dev\nworker: node worker.js --queue=a:b\n\n")?;
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[1].name, "worker");
+        assert_eq!(services[1].command, "node worker.js --queue=a:b");
+        assert!(parse_procfile("just a command").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_stack_file() -> Result<()> {
+        let services = parse_stack_file(r#"
+            [services.db]
+            command = "docker compose up postgres"
+            ready_port = 5432
+
+            [services.api]
+            command = "cargo run"
+            cwd = "api"
+            depends_on = ["db"]
+            restart = "on-failure"
+            ready_log = "Listening on"
+            env = { RUST_LOG = "debug" }
+        "#)?;
+        let api = services.iter().find(|s| s.name == "api").expect("api");
+        assert_eq!(api.depends_on, Some(vec!["db".to_string()]));
+        assert_eq!(api.restart_policy.as_deref(), Some("on-failure"));
+        assert_eq!(api.ready_pattern.as_deref(), Some("Listening on"));
+        assert_eq!(api.env.as_ref().and_then(|env| env.get("RUST_LOG")).map(String::as_str), Some("debug"));
+        // Left out, so an import keeps what is already saved
+        let db = services.iter().find(|s| s.name == "db").expect("db");
+        assert_eq!((db.autostart, db.depends_on.as_ref()), (None, None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_order() -> Result<()> {
+        let defs = vec![definition("web", &["api"]), definition("api", &["db"]), definition("db", &[]), definition("tunnel", &[])];
+        let order: Vec<String> = start_order(&defs)?.into_iter().map(|d| d.name).collect();
+        assert_eq!(order, vec!["db", "api", "web", "tunnel"]);
+
+        assert!(start_order(&[definition("a", &["b"]), definition("b", &["a"])]).is_err());
+        assert!(start_order(&[definition("a", &["missing"])]).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_start_and_stop_stack() -> Result<()> {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await?;
+
+        let project = ProjectRepository::new(pool.clone())
+            .create_project("Stack".into(), std::env::temp_dir().to_string_lossy().to_string(), None)
+            .await?;
+
+        let processes = ProcessState::new(None);
+        let sink: Arc<dyn EventSink> = Arc::new(NullSink);
+        let supervisor = ProcessSupervisor::new(processes.clone(), pool.clone(), sink.clone());
+        let stacks = StackService::new(supervisor.clone(), processes.clone(), pool, sink);
+
+        let mut db = ImportedService::new("db".into(), "echo 'db: ready to accept'; sleep 30".into());
+        db.ready_pattern = Some("ready to accept".into());
+        let mut api = ImportedService::new("api".into(), "sleep 30".into());
+        api.depends_on = Some(vec!["db".into()]);
+        stacks.import(&project.id, vec![api, db]).await?;
+
+        let started = stacks.start_stack(&project.id).await?;
+        let commands: Vec<&str> = started.iter().map(|p| p.command.as_str()).collect();
+        assert_eq!(commands, vec!["echo 'db: ready to accept'; sleep 30", "sleep 30"]);
+
+        stacks.stop_stack(&project.id).await?;
+        let service = ProcessService::new(processes);
+        for process in started {
+            assert_eq!(service.get_process(&process.id)?.status, ProcessStatus::Killed);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use super::models::{ProcessState, ProcessStats, ProcessStatus};
+use super::ports::listening_ports;
+use super::tree::{process_table, process_tree};
+use crate::shared::events::EventSink;
+use crate::shared::utils::now_millis;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// CPU time per pid at the previous sample of a managed process.
+#[cfg(target_os = "linux")]
+struct CpuSample {
+    ticks: HashMap<u32, u64>,
+    at: Instant,
+}
+
+/// Periodically measures every running managed process tree and emits `process_stats`,
+/// plus `process_ports` whenever the set of listening ports changes.
+pub struct StatsSampler {
+    processes: ProcessState,
+    sink: Arc<dyn EventSink>,
+    #[cfg(target_os = "linux")]
+    previous: HashMap<String, CpuSample>, // Keyed by process id
+}
+
+impl StatsSampler {
+    pub fn new(processes: ProcessState, sink: Arc<dyn EventSink>) -> Self {
+        Self {
+            processes,
+            sink,
+            #[cfg(target_os = "linux")]
+            previous: HashMap::new(),
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        let mut sampler = self;
+        loop {
+            interval.tick().await;
+            // /proc reads and the port scan block, so the sampler moves to the blocking
+            // pool for each pass and comes back with its CPU baselines
+            sampler = match tokio::task::spawn_blocking(move || {
+                sampler.sample_all();
+                sampler
+            }).await {
+                Ok(sampler) => sampler,
+                Err(e) => {
+                    println!("ERROR: Process stats sampler stopped: {}", e);
+                    return;
+                }
+            };
+        }
+    }
+
+    /// Samples every running process, stores the result on its session and emits it.
+    pub fn sample_all(&mut self) -> HashMap<String, ProcessStats> {
+        let running: Vec<(String, u32)> = {
+            let sessions = self.processes.sessions.lock().unwrap();
+            sessions.iter()
+                .filter(|(_, s)| s.status == ProcessStatus::Running && s.pid != 0)
+                .map(|(id, s)| (id.clone(), s.pid))
+                .collect()
+        };
+
+        #[cfg(target_os = "linux")]
+        {
+            let ids: std::collections::HashSet<&String> = running.iter().map(|(id, _)| id).collect();
+            self.previous.retain(|id, _| ids.contains(id));
+        }
+        if running.is_empty() {
+            return HashMap::new();
+        }
+
+        let table = process_table();
+        let mut samples = HashMap::new();
+        let mut listening = HashMap::new();
+        for (id, pid) in running {
+            let pids = process_tree(pid, &table);
+            samples.insert(id.clone(), self.sample(&id, &pids));
+            listening.insert(id, listening_ports(&pids));
+        }
+
+        let mut sessions = self.processes.sessions.lock().unwrap();
+        for (id, stats) in &samples {
+            let Some(session) = sessions.get_mut(id) else {
+                continue;
+            };
+            session.stats = Some(stats.clone());
+            self.sink.emit_event("process_stats", serde_json::json!({
+                "id": id,
+                "stats": stats
+            }));
+
+            // Ports change rarely, only tell the UI when they do
+            if let Some(ports) = listening.remove(id).filter(|ports| *ports != session.ports) {
+                session.ports = ports;
+                self.sink.emit_event("process_ports", serde_json::json!({
+                    "id": id,
+                    "ports": session.ports
+                }));
+            }
+        }
+        samples
+    }
+
+    #[cfg(target_os = "linux")]
+    fn sample(&mut self, id: &str, pids: &[u32]) -> ProcessStats {
+        let now = Instant::now();
+        let mut stats = ProcessStats {
+            threads: Some(0),
+            sampled_at: now_millis(),
+            ..Default::default()
+        };
+        let mut ticks = HashMap::new();
+
+        for pid in pids {
+            let Some(stat) = read_stat(*pid) else {
+                continue; // Exited between listing and reading
+            };
+            ticks.insert(*pid, stat.cpu_ticks);
+            stats.rss_bytes += stat.rss_pages * page_size();
+            stats.threads = stats.threads.map(|t| t + stat.threads);
+            stats.process_count += 1;
+        }
+
+        // The first sample has nothing to compare against
+        if let Some(previous) = self.previous.get(id) {
+            let delta: u64 = ticks.iter()
+                .map(|(pid, t)| t.saturating_sub(previous.ticks.get(pid).copied().unwrap_or(0)))
+                .sum();
+            stats.cpu_percent = cpu_percent(delta, now.duration_since(previous.at), clock_ticks());
+        }
+        self.previous.insert(id.to_string(), CpuSample { ticks, at: now });
+        stats
+    }
+
+    #[cfg(all(unix, not(target_os = "linux")))]
+    fn sample(&mut self, _id: &str, pids: &[u32]) -> ProcessStats {
+        // ps already reports a decaying CPU average, so no previous sample is needed
+        let mut stats = ProcessStats {
+            sampled_at: now_millis(),
+            ..Default::default()
+        };
+        let pid_list: Vec<String> = pids.iter().map(|p| p.to_string()).collect();
+        let Ok(output) = std::process::Command::new("ps")
+            .args(["-o", "rss=,%cpu=", "-p", &pid_list.join(",")])
+            .output() else {
+            return stats;
+        };
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let mut fields = line.split_whitespace();
+            let rss_kb: u64 = fields.next().and_then(|f| f.parse().ok()).unwrap_or(0);
+            let cpu: f64 = fields.next().and_then(|f| f.parse().ok()).unwrap_or(0.0);
+            stats.rss_bytes += rss_kb * 1024;
+            stats.cpu_percent += cpu;
+            stats.process_count += 1;
+        }
+        stats
+    }
+
+    #[cfg(not(unix))]
+    fn sample(&mut self, _id: &str, _pids: &[u32]) -> ProcessStats {
+        ProcessStats {
+            sampled_at: now_millis(),
+            ..Default::default()
+        }
+    }
+}
+
+/// CPU time used over a wall-clock window, as a percentage of one core.
+pub fn cpu_percent(delta_ticks: u64, elapsed: Duration, ticks_per_second: u64) -> f64 {
+    let elapsed = elapsed.as_secs_f64();
+    if elapsed <= 0.0 || ticks_per_second == 0 {
+        return 0.0;
+    }
+    delta_ticks as f64 / ticks_per_second as f64 / elapsed * 100.0
+}
+
+#[cfg(target_os = "linux")]
+struct ProcStat {
+    cpu_ticks: u64,
+    threads: u32,
+    rss_pages: u64,
+}
+
+#[cfg(target_os = "linux")]
+fn read_stat(pid: u32) -> Option<ProcStat> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Fields after the parenthesised command name, starting at `state` (field 3 in proc(5))
+    let fields: Vec<&str> = stat[stat.rfind(')')? + 2..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3)?.parse::<u64>().ok();
+    Some(ProcStat {
+        cpu_ticks: field(14)? + field(15)?, // utime + stime
+        threads: field(20)? as u32,
+        rss_pages: field(24)?,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn clock_ticks() -> u64 {
+    unsafe { libc::sysconf(libc::_SC_CLK_TCK).max(1) as u64 }
+}
+
+#[cfg(target_os = "linux")]
+fn page_size() -> u64 {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE).max(1) as u64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::processes::service::ProcessService;
+
+    struct NullSink;
+
+    impl EventSink for NullSink {
+        fn emit_event(&self, _event: &str, _payload: serde_json::Value) {}
+    }
+
+    #[test]
+    fn test_cpu_percent() {
+        assert_eq!(cpu_percent(100, Duration::from_secs(2), 100), 50.0);
+        assert_eq!(cpu_percent(400, Duration::from_secs(1), 100), 400.0);
+        assert_eq!(cpu_percent(10, Duration::ZERO, 100), 0.0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_samples_running_process_tree() -> anyhow::Result<()> {
+        let processes = ProcessState::new(None);
+        let service = ProcessService::new(processes.clone());
+        let cwd = std::env::temp_dir().to_string_lossy().to_string();
+        let process = service.start_process(Arc::new(NullSink), "sleep 30 & sleep 30; wait".into(), cwd)?;
+
+        let mut sampler = StatsSampler::new(processes, Arc::new(NullSink));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut stats = sampler.sample_all().remove(&process.id).expect("sampled");
+        while stats.process_count < 3 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+            stats = sampler.sample_all().remove(&process.id).expect("sampled");
+        }
+
+        assert_eq!(stats.process_count, 3);
+        assert!(stats.rss_bytes > 0);
+        assert!(stats.threads.unwrap_or(0) >= 3);
+        assert_eq!(service.get_process(&process.id)?.stats, Some(stats));
+
+        service.terminate(&process.id, Some(Duration::from_secs(1)))?;
+        while service.get_process(&process.id)?.running && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert!(sampler.sample_all().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,428 @@
+use super::alerts::refresh_rules;
+use super::environment::project_environment;
+use super::models::{AutostartFailure, OpenProjectReport, Process, ProcessDefinition, ProcessSpec, ProcessState, ProcessStatus, RestartPolicy};
+use super::repository::ProcessRepository;
+use super::service::ProcessService;
+use crate::modules::projects::repository::ProjectRepository;
+use crate::shared::events::EventSink;
+use anyhow::{anyhow, Context, Result};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// A run that lasts this long counts as healthy and resets the backoff.
+const STABLE_AFTER: Duration = Duration::from_secs(30);
+// Finished runs of a restarted definition kept in the list, with their output, so a
+// crash loop can be looked into without piling up one entry per attempt.
+const KEPT_RUNS: usize = 3;
+
+struct SupervisedProcess {
+    definition: ProcessDefinition,
+    cwd: String,
+    env: Vec<(String, String)>, // Resolved once per start, secrets included
+    process_id: Option<String>,
+    previous_runs: VecDeque<String>, // Oldest first
+    started: Instant,
+    restarts: u32,
+    next_start: Option<Instant>,
+    respawning: bool, // Spawn in flight outside the lock
+}
+
+/// A respawn claimed under the lock, carried out after releasing it.
+struct DueRestart {
+    definition_id: String,
+    definition: ProcessDefinition,
+    cwd: String,
+    env: Vec<(String, String)>,
+}
+
+/// Keeps saved process definitions alive according to their restart policy.
+/// Processes themselves still live in `ProcessState`; the supervisor only watches
+/// their status and respawns them, so the Process Manager sees them like any other.
+#[derive(Clone)]
+pub struct ProcessSupervisor {
+    processes: ProcessState,
+    pool: SqlitePool,
+    sink: Arc<dyn EventSink>,
+    supervised: Arc<Mutex<HashMap<String, SupervisedProcess>>>, // Keyed by definition id
+}
+
+impl ProcessSupervisor {
+    pub fn new(processes: ProcessState, pool: SqlitePool, sink: Arc<dyn EventSink>) -> Self {
+        Self {
+            processes,
+            pool,
+            sink,
+            supervised: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            // Respawning forks and sets up a PTY, keep that off the runtime
+            let supervisor = self.clone();
+            let _ = tokio::task::spawn_blocking(move || supervisor.tick()).await;
+        }
+    }
+
+    /// Brings up every autostart definition of a project that isn't already supervised,
+    /// carrying on past the ones that fail to start.
+    pub async fn open_project(&self, project_id: &str) -> Result<OpenProjectReport> {
+        let definitions = ProcessRepository::new(self.pool.clone()).get_definitions(project_id).await?;
+        let mut report = OpenProjectReport::default();
+        for definition in definitions.into_iter().filter(|d| d.autostart) {
+            if self.is_supervised(&definition.id) {
+                continue;
+            }
+            let (definition_id, name) = (definition.id.clone(), definition.name.clone());
+            match self.start_definition(definition).await {
+                Ok(process) => report.started.push(process),
+                Err(e) => report.failed.push(AutostartFailure { definition_id, name, error: e.to_string() }),
+            }
+        }
+        Ok(report)
+    }
+
+    pub async fn start(&self, definition_id: &str) -> Result<Process> {
+        if self.is_supervised(definition_id) {
+            return Err(anyhow!("Process is already running"));
+        }
+        let definition = ProcessRepository::new(self.pool.clone())
+            .get_definition(definition_id)
+            .await?
+            .context("Process definition not found")?;
+        self.start_definition(definition).await
+    }
+
+    /// Stops supervising a definition and kills its current run, if any.
+    pub fn stop(&self, definition_id: &str) -> Result<()> {
+        let entry = self.supervised.lock().unwrap().remove(definition_id);
+        if let Some(process_id) = entry.and_then(|e| e.process_id) {
+            ProcessService::new(self.processes.clone()).kill(&process_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_supervised(&self, definition_id: &str) -> bool {
+        self.supervised.lock().unwrap().contains_key(definition_id)
+    }
+
+    /// Id of the latest run of a supervised definition.
+    pub fn current_process(&self, definition_id: &str) -> Option<String> {
+        self.supervised.lock().unwrap().get(definition_id).and_then(|e| e.process_id.clone())
+    }
+
+    async fn start_definition(&self, definition: ProcessDefinition) -> Result<Process> {
+        let cwd = self.resolve_cwd(&definition).await?;
+        let env = project_environment(&self.pool, &definition.project_id, definition.env_vars()).await?;
+        refresh_rules(&self.processes, &self.pool, &definition.project_id).await?;
+        let process = self.spawn(&definition, &cwd, &env)?;
+
+        self.supervised.lock().unwrap().insert(definition.id.clone(), SupervisedProcess {
+            definition,
+            cwd,
+            env,
+            process_id: Some(process.id.clone()),
+            previous_runs: VecDeque::new(),
+            started: Instant::now(),
+            restarts: 0,
+            next_start: None,
+            respawning: false,
+        });
+        Ok(process)
+    }
+
+    async fn resolve_cwd(&self, definition: &ProcessDefinition) -> Result<String> {
+        let project = ProjectRepository::new(self.pool.clone())
+            .get_project(&definition.project_id)
+            .await?
+            .context("Project not found")?;
+        let project_path = crate::shared::utils::expand_path(&project.path);
+
+        Ok(match &definition.cwd {
+            Some(cwd) if !cwd.is_empty() => {
+                let cwd = crate::shared::utils::expand_path(cwd);
+                Path::new(&project_path).join(cwd).to_string_lossy().to_string()
+            }
+            _ => project_path,
+        })
+    }
+
+    fn spawn(&self, definition: &ProcessDefinition, cwd: &str, env: &[(String, String)]) -> Result<Process> {
+        let spec = ProcessSpec {
+            command: definition.command.clone(),
+            cwd: cwd.to_string(),
+            env: env.to_vec(),
+            definition_id: Some(definition.id.clone()),
+            project_id: Some(definition.project_id.clone()),
+        };
+        ProcessService::new(self.processes.clone()).spawn(self.sink.clone(), spec)
+    }
+
+    /// One pass of the supervisor loop: notice exits, schedule restarts, respawn when due.
+    /// Spawning happens outside the lock so start/stop aren't held up by a fork.
+    pub fn tick(&self) {
+        for due in self.due_restarts(Instant::now()) {
+            let result = self.spawn(&due.definition, &due.cwd, &due.env);
+            self.finish_restart(&due.definition_id, result, Instant::now());
+        }
+    }
+
+    /// Updates every entry from its process status and claims the restarts that are due.
+    fn due_restarts(&self, now: Instant) -> Vec<DueRestart> {
+        let service = ProcessService::new(self.processes.clone());
+        let mut supervised = self.supervised.lock().unwrap();
+        let mut finished = Vec::new();
+        let mut due = Vec::new();
+
+        for (definition_id, entry) in supervised.iter_mut() {
+            if entry.respawning {
+                continue;
+            }
+
+            // Waiting out the backoff of a scheduled restart
+            if let Some(at) = entry.next_start {
+                if at <= now {
+                    entry.next_start = None;
+                    entry.respawning = true;
+                    due.push(DueRestart {
+                        definition_id: definition_id.clone(),
+                        definition: entry.definition.clone(),
+                        cwd: entry.cwd.clone(),
+                        env: entry.env.clone(),
+                    });
+                }
+                continue;
+            }
+
+            let Some(process_id) = entry.process_id.clone() else {
+                continue;
+            };
+
+            // Removed from the list by the user, nothing left to supervise
+            let Ok(process) = service.get_process(&process_id) else {
+                finished.push(definition_id.clone());
+                continue;
+            };
+
+            match process.status {
+                ProcessStatus::Running => {
+                    if now.duration_since(entry.started) >= STABLE_AFTER {
+                        entry.restarts = 0;
+                    }
+                }
+                ProcessStatus::Killed => finished.push(definition_id.clone()),
+                ProcessStatus::Exited => {
+                    let wants_restart = match entry.definition.policy() {
+                        RestartPolicy::Never => false,
+                        RestartPolicy::OnFailure => process.exit_code != Some(0),
+                        RestartPolicy::Always => true,
+                    };
+                    let within_limit = entry.definition.max_restarts
+                        .map_or(true, |max| i64::from(entry.restarts) < max);
+
+                    if wants_restart && within_limit {
+                        entry.restarts += 1;
+                        let delay = backoff(entry.definition.backoff_ms, entry.restarts);
+                        entry.next_start = Some(now + delay);
+                        self.sink.emit_event("process_restarting", serde_json::json!({
+                            "definition_id": definition_id,
+                            "process_id": process_id,
+                            "exit_code": process.exit_code,
+                            "attempt": entry.restarts,
+                            "delay_ms": delay.as_millis() as u64
+                        }));
+                    } else {
+                        finished.push(definition_id.clone());
+                    }
+                }
+            }
+        }
+
+        for definition_id in finished {
+            supervised.remove(&definition_id);
+        }
+        due
+    }
+
+    /// Records the outcome of a respawn. If the definition was stopped (or stopped and
+    /// started again) while spawning, the new run is not ours anymore and is killed.
+    fn finish_restart(&self, definition_id: &str, result: Result<Process>, now: Instant) {
+        let service = ProcessService::new(self.processes.clone());
+        let mut supervised = self.supervised.lock().unwrap();
+        let Some(entry) = supervised.get_mut(definition_id).filter(|e| e.respawning) else {
+            drop(supervised);
+            if let Ok(process) = result {
+                let _ = service.kill(&process.id);
+            }
+            return;
+        };
+        entry.respawning = false;
+
+        match result {
+            Ok(process) => {
+                // The run that ended stays listed as exited until newer ones push it out
+                if let Some(previous) = entry.process_id.replace(process.id.clone()) {
+                    entry.previous_runs.push_back(previous);
+                }
+                while entry.previous_runs.len() > KEPT_RUNS {
+                    if let Some(oldest) = entry.previous_runs.pop_front() {
+                        // May already be gone if the user removed it
+                        let _ = service.remove(&oldest);
+                    }
+                }
+                entry.started = now;
+                self.sink.emit_event("process_started", serde_json::json!(process));
+            }
+            Err(e) => {
+                println!("ERROR: Failed to restart {}: {}", entry.definition.name, e);
+                if entry.definition.max_restarts.is_some_and(|max| i64::from(entry.restarts) >= max) {
+                    supervised.remove(definition_id);
+                    return;
+                }
+                entry.restarts += 1;
+                entry.next_start = Some(now + backoff(entry.definition.backoff_ms, entry.restarts));
+            }
+        }
+    }
+}
+
+fn backoff(base_ms: i64, attempt: u32) -> Duration {
+    let base = Duration::from_millis(base_ms.max(0) as u64);
+    base.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::processes::models::ProcessDefinitionInput;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl EventSink for RecordingSink {
+        fn emit_event(&self, event: &str, _payload: serde_json::Value) {
+            self.events.lock().unwrap().push(event.to_string());
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert_eq!(backoff(500, 1), Duration::from_millis(500));
+        assert_eq!(backoff(500, 3), Duration::from_millis(2000));
+        assert_eq!(backoff(500, 30), MAX_BACKOFF);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_during_respawn_kills_the_new_run() -> Result<()> {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await?;
+
+        let project = ProjectRepository::new(pool.clone())
+            .create_project("Test Project".into(), std::env::temp_dir().to_string_lossy().to_string(), None)
+            .await?;
+        let mut input = ProcessDefinitionInput::new("flaky".into(), "sleep 0.1; exit 1".into());
+        input.restart_policy = "always".into();
+        input.backoff_ms = 0;
+        let definition = ProcessRepository::new(pool.clone())
+            .create_definition(project.id.clone(), input)
+            .await?;
+
+        let processes = ProcessState::new(None);
+        let supervisor = ProcessSupervisor::new(processes.clone(), pool, Arc::new(RecordingSink::default()));
+        supervisor.start(&definition.id).await?;
+
+        // Claim the respawn like tick does, then stop the definition before it lands
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let due = loop {
+            let due = supervisor.due_restarts(Instant::now());
+            if !due.is_empty() || Instant::now() > deadline {
+                break due;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        assert_eq!(due.len(), 1);
+        supervisor.stop(&definition.id)?;
+        let process = supervisor.spawn(&due[0].definition, &due[0].cwd, &due[0].env)?;
+        supervisor.finish_restart(&definition.id, Ok(process.clone()), Instant::now());
+
+        assert!(!supervisor.is_supervised(&definition.id));
+        let service = ProcessService::new(processes);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while service.get_process(&process.id)?.status == ProcessStatus::Running && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(service.get_process(&process.id)?.status, ProcessStatus::Killed);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_on_failure_restarts_until_limit() -> Result<()> {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await?;
+
+        let project = ProjectRepository::new(pool.clone())
+            .create_project("Test Project".into(), std::env::temp_dir().to_string_lossy().to_string(), None)
+            .await?;
+        let mut input = ProcessDefinitionInput::new("crashy".into(), "exit 1".into());
+        input.autostart = true;
+        input.restart_policy = "on-failure".into();
+        input.max_restarts = Some(4);
+        input.backoff_ms = 10;
+        let repo = ProcessRepository::new(pool.clone());
+        // Fails to start, but doesn't keep the one after it from starting
+        let mut broken = ProcessDefinitionInput::new("broken".into(), "true".into());
+        broken.autostart = true;
+        broken.env.insert("TOKEN".into(), "${secret:missing}".into());
+        repo.create_definition(project.id.clone(), broken).await?;
+        let definition = repo.create_definition(project.id.clone(), input).await?;
+
+        let sink = Arc::new(RecordingSink::default());
+        let processes = ProcessState::new(None);
+        let supervisor = ProcessSupervisor::new(processes.clone(), pool, sink.clone());
+
+        let report = supervisor.open_project(&project.id).await?;
+        assert_eq!(report.started.len(), 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].name, "broken");
+        // Opening again doesn't start a second copy
+        assert!(supervisor.open_project(&project.id).await?.started.is_empty());
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while supervisor.is_supervised(&definition.id) && Instant::now() < deadline {
+            supervisor.tick();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        assert!(!supervisor.is_supervised(&definition.id));
+        let events = sink.events.lock().unwrap();
+        assert_eq!(events.iter().filter(|e| *e == "process_restarting").count(), 4);
+        assert_eq!(events.iter().filter(|e| *e == "process_started").count(), 4);
+
+        // Five runs in all; the last one and the KEPT_RUNS before it stay listed as exited
+        let runs = ProcessService::new(processes).list_processes()?;
+        assert_eq!(runs.len(), KEPT_RUNS + 1);
+        assert!(runs.iter().all(|p| p.status == ProcessStatus::Exited && p.exit_code == Some(1)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_KILL_GRACE: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Which PIDs went down on SIGTERM and which had to be SIGKILLed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TerminateReport {
+    pub terminated: Vec<u32>,
+    pub killed: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProcEntry {
+    pub pid: u32,
+    pub ppid: u32,
+    pub pgid: u32,
+}
+
+/// Snapshot of every process on the machine.
+#[cfg(target_os = "linux")]
+pub fn process_table() -> Vec<ProcEntry> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| {
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+            // The command name is in parentheses and may itself contain spaces
+            let fields: Vec<&str> = stat[stat.rfind(')')? + 2..].split_whitespace().collect();
+            Some(ProcEntry {
+                pid,
+                ppid: fields.get(1)?.parse().ok()?,
+                pgid: fields.get(2)?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn process_table() -> Vec<ProcEntry> {
+    let Ok(output) = std::process::Command::new("ps").args(["-A", "-o", "pid=,ppid=,pgid="]).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().map(|f| f.parse::<u32>().ok());
+            Some(ProcEntry {
+                pid: fields.next()??,
+                ppid: fields.next()??,
+                pgid: fields.next()??,
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+pub fn process_table() -> Vec<ProcEntry> {
+    Vec::new()
+}
+
+/// `root` and everything below it, plus anything still in its process group.
+/// Managed processes are session leaders, so their pgid is their own pid.
+pub fn process_tree(root: u32, table: &[ProcEntry]) -> Vec<u32> {
+    let mut tree = vec![root];
+    let mut seen: HashSet<u32> = HashSet::from([root]);
+    let mut index = 0;
+    while index < tree.len() {
+        let parent = tree[index];
+        for entry in table {
+            if (entry.ppid == parent || entry.pgid == root) && seen.insert(entry.pid) {
+                tree.push(entry.pid);
+            }
+        }
+        index += 1;
+    }
+    tree
+}
+
+#[cfg(target_os = "linux")]
+pub fn is_alive(pid: u32) -> bool {
+    // Zombies still answer kill(0) but are already dead for our purposes
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => stat.rfind(')').and_then(|i| stat[i + 2..].chars().next()) != Some('Z'),
+        Err(_) => false,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn is_alive(pid: u32) -> bool {
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+#[cfg(unix)]
+fn signal(pid: u32, signal: libc::c_int) {
+    unsafe {
+        libc::kill(pid as libc::pid_t, signal);
+    }
+}
+
+/// SIGTERMs the whole tree under `root`, waits up to `grace` for it to go away,
+/// then SIGKILLs whatever is left.
+#[cfg(unix)]
+pub fn terminate_tree(root: u32, grace: Duration) -> TerminateReport {
+    // Snapshot first: once the parents die their children get reparented to init
+    let pids = process_tree(root, &process_table());
+
+    unsafe {
+        libc::kill(-(root as libc::pid_t), libc::SIGTERM);
+    }
+    for pid in &pids {
+        signal(*pid, libc::SIGTERM);
+    }
+
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline && pids.iter().any(|pid| is_alive(*pid)) {
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    let (killed, terminated): (Vec<u32>, Vec<u32>) = pids.into_iter().partition(|pid| is_alive(*pid));
+    for pid in &killed {
+        signal(*pid, libc::SIGKILL);
+    }
+    TerminateReport { terminated, killed }
+}
+
+/// SIGTERMs a single process, then SIGKILLs it if it's still around after `grace`.
+#[cfg(unix)]
+pub fn terminate_process(pid: u32, grace: Duration) -> TerminateReport {
+    signal(pid, libc::SIGTERM);
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline && is_alive(pid) {
+        thread::sleep(POLL_INTERVAL);
+    }
+    if is_alive(pid) {
+        signal(pid, libc::SIGKILL);
+        return TerminateReport { terminated: Vec::new(), killed: vec![pid] };
+    }
+    TerminateReport { terminated: vec![pid], killed: Vec::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_tree_follows_parents_and_group() {
+        let table = [
+            ProcEntry { pid: 10, ppid: 1, pgid: 10 },
+            ProcEntry { pid: 11, ppid: 10, pgid: 10 },
+            ProcEntry { pid: 12, ppid: 11, pgid: 12 }, // Started its own group
+            ProcEntry { pid: 13, ppid: 1, pgid: 10 },  // Orphaned but still in the group
+            ProcEntry { pid: 20, ppid: 1, pgid: 20 },
+        ];
+        let mut tree = process_tree(10, &table);
+        tree.sort();
+        assert_eq!(tree, vec![10, 11, 12, 13]);
+    }
+}
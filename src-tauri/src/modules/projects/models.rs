@@ -76,7 +76,7 @@ pub struct ProjectLink {
     pub url: String,
     pub icon: Option<String>,
     #[sqlx(default)]
-    pub kind: String, // 'url' or 'command'
+    pub kind: String, // 'url', 'command', 'repository' or 'sftp'
     pub working_directory: Option<String>,
     #[sqlx(default)]
     pub created_at: String,
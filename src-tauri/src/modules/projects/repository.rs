@@ -147,6 +147,10 @@ impl ProjectRepository {
         sqlx::query("DELETE FROM project_snippets WHERE project_id = ?").bind(id).execute(&mut *tx).await?;
         sqlx::query("DELETE FROM project_notes WHERE project_id = ?").bind(id).execute(&mut *tx).await?; 
         sqlx::query("DELETE FROM project_links WHERE project_id = ?").bind(id).execute(&mut *tx).await?; 
+        sqlx::query("DELETE FROM project_processes WHERE project_id = ?").bind(id).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM process_alert_rules WHERE project_id = ?").bind(id).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM terminal_profiles WHERE project_id = ?").bind(id).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM ssh_tunnels WHERE project_id = ?").bind(id).execute(&mut *tx).await?;
         sqlx::query("DELETE FROM projects WHERE id = ?").bind(id).execute(&mut *tx).await?;
 
         tx.commit().await?;
@@ -23,7 +23,7 @@ impl ProjectService {
         // Auto-create directory if it doesn't exist
         let path_obj = std::path::Path::new(&expanded_path);
         if !path_obj.exists() {
-            std::fs::create_dir_all(&path_obj)?;
+            std::fs::create_dir_all(path_obj)?;
         }
         
         self.repo.create_project(name, path, ssh_key_path).await
@@ -131,6 +131,11 @@ impl ProjectService {
 
     // Links
     pub async fn create_link(&self, project_id: String, title: String, url: String, icon: Option<String>, kind: String, working_directory: Option<String>) -> Result<ProjectLink> {
+        if kind == "sftp" {
+            // Normalized so the browser can always split it back into host and path
+            let url = crate::modules::ssh::sftp::SftpUrl::parse(&url)?.to_string();
+            return self.repo.create_link(project_id, title, url, icon, kind, working_directory).await;
+        }
         self.repo.create_link(project_id, title, url, icon, kind, working_directory).await
     }
 
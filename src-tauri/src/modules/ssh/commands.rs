@@ -1,22 +1,318 @@
-use tauri::command;
-use super::models::SshHostModel;
+use tauri::{command, AppHandle, State};
+use sqlx::SqlitePool;
+use std::time::Duration;
+use std::path::{Path, PathBuf};
+use super::connection::SshCommandError;
+use super::keys::KeyService;
+use super::models::{GenerateKeyInput, SftpEntry, SshHostModel, SshKeyInfo, SshSessionInfo, SshTunnel, SshTunnelInput, TunnelState};
+use super::repository::TunnelRepository;
 use super::service::SshService;
+use super::session::SshSessionService;
+use super::sftp::SftpService;
+use super::tunnels::TunnelManager;
+
+// How long the database panel waits for a connection's tunnels before giving up
+const CONNECTION_TUNNEL_TIMEOUT: Duration = Duration::from_secs(15);
 
 #[command]
-pub fn get_ssh_hosts() -> Result<Vec<SshHostModel>, String> {
-    let service = SshService::new();
+pub fn get_ssh_hosts(service: State<'_, SshService>) -> Result<Vec<SshHostModel>, String> {
     service::list_hosts(&service).map_err(|e| e.to_string())
 }
 
 #[command]
-pub fn add_ssh_host(host: SshHostModel) -> Result<(), String> {
-    let service = SshService::new();
-    service::add_host(&service, host).map_err(|e| e.to_string())
+pub async fn add_ssh_host(state: State<'_, SshService>, host: SshHostModel) -> Result<(), String> {
+    // Checking the identity file runs ssh-keygen
+    let service = state.inner().clone();
+    blocking(move || service::add_host(&service, host)).await
+}
+
+#[command]
+pub async fn update_ssh_host(state: State<'_, SshService>, alias: String, host: SshHostModel) -> Result<(), String> {
+    let service = state.inner().clone();
+    blocking(move || service::update_host(&service, &alias, host)).await
+}
+
+#[command]
+pub fn delete_ssh_host(service: State<'_, SshService>, alias: String) -> Result<(), String> {
+    service::delete_host(&service, &alias).map_err(|e| e.to_string())
+}
+
+// Interactive sessions
+
+#[command]
+pub async fn ssh_connect(
+    app_handle: AppHandle,
+    state: State<'_, SshSessionService>,
+    host: String,
+    cols: u16,
+    rows: u16,
+    password: Option<String>,
+) -> Result<String, SshCommandError> {
+    // Connecting and authenticating block on the network
+    let service = state.inner().clone();
+    Ok(tauri::async_runtime::spawn_blocking(move || service.connect(app_handle, &host, cols, rows, password)).await??)
+}
+
+#[command]
+pub fn ssh_write(
+    state: State<'_, SshSessionService>,
+    session_id: String,
+    data: String
+) -> Result<(), String> {
+    state.write(&session_id, &data).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn ssh_resize(
+    state: State<'_, SshSessionService>,
+    session_id: String,
+    cols: u16,
+    rows: u16
+) -> Result<(), String> {
+    state.resize(&session_id, cols, rows).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn ssh_disconnect(
+    state: State<'_, SshSessionService>,
+    session_id: String
+) -> Result<(), String> {
+    state.disconnect(&session_id).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn list_ssh_sessions(state: State<'_, SshSessionService>) -> Result<Vec<SshSessionInfo>, String> {
+    Ok(state.list())
+}
+
+#[command]
+pub async fn trust_ssh_host(
+    state: State<'_, SshSessionService>,
+    host: String,
+    fingerprint: String
+) -> Result<String, SshCommandError> {
+    let service = state.inner().clone();
+    Ok(tauri::async_runtime::spawn_blocking(move || service.trust_host(&host, &fingerprint)).await??)
+}
+
+// Tunnels
+
+#[command]
+pub async fn create_tunnel(
+    pool: State<'_, SqlitePool>,
+    project_id: String,
+    input: SshTunnelInput
+) -> Result<SshTunnel, String> {
+    TunnelRepository::new(pool.inner().clone())
+        .create_tunnel(project_id, input)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_tunnels(
+    pool: State<'_, SqlitePool>,
+    project_id: String
+) -> Result<Vec<SshTunnel>, String> {
+    TunnelRepository::new(pool.inner().clone())
+        .get_tunnels(&project_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn update_tunnel(
+    pool: State<'_, SqlitePool>,
+    manager: State<'_, TunnelManager>,
+    id: String,
+    input: SshTunnelInput
+) -> Result<(), String> {
+    TunnelRepository::new(pool.inner().clone())
+        .update_tunnel(&id, input)
+        .await
+        .map_err(|e| e.to_string())?;
+    // A running tunnel picks up the new definition right away
+    if manager.is_running(&id) {
+        manager.stop(&id).map_err(|e| e.to_string())?;
+        manager.start(&id).await.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn delete_tunnel(
+    pool: State<'_, SqlitePool>,
+    manager: State<'_, TunnelManager>,
+    id: String
+) -> Result<(), String> {
+    manager.stop(&id).map_err(|e| e.to_string())?;
+    TunnelRepository::new(pool.inner().clone())
+        .delete_tunnel(&id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn start_tunnel(
+    manager: State<'_, TunnelManager>,
+    id: String
+) -> Result<TunnelState, String> {
+    manager.start(&id).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn stop_tunnel(
+    manager: State<'_, TunnelManager>,
+    id: String
+) -> Result<(), String> {
+    manager.stop(&id).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_tunnel_states(
+    manager: State<'_, TunnelManager>,
+    project_id: String
+) -> Result<Vec<TunnelState>, String> {
+    manager.project_states(&project_id).await.map_err(|e| e.to_string())
+}
+
+/// Called by the database panel before it connects.
+#[command]
+pub async fn start_connection_tunnels(
+    manager: State<'_, TunnelManager>,
+    connection_id: String
+) -> Result<Vec<TunnelState>, String> {
+    manager.start_for_connection(&connection_id, CONNECTION_TUNNEL_TIMEOUT)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Keys
+
+#[command]
+pub async fn list_ssh_keys() -> Result<Vec<SshKeyInfo>, String> {
+    tauri::async_runtime::spawn_blocking(|| KeyService::new().list_keys())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn generate_ssh_key(input: GenerateKeyInput) -> Result<SshKeyInfo, String> {
+    // RSA generation can take a few seconds
+    tauri::async_runtime::spawn_blocking(move || KeyService::new().generate(input))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn inspect_ssh_key(path: String) -> Result<SshKeyInfo, String> {
+    // Runs ssh-keygen and looks up a saved passphrase in the keychain
+    tauri::async_runtime::spawn_blocking(move || KeyService::new().inspect(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn get_public_key(path: String) -> Result<String, String> {
+    KeyService::new().public_key(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn add_key_to_agent(path: String, passphrase: Option<String>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || KeyService::new().add_to_agent(Path::new(&path), passphrase))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn remove_key_from_agent(path: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || KeyService::new().remove_from_agent(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+// SFTP. Everything blocks on the network, so it runs off the async runtime.
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> anyhow::Result<T> + Send + 'static) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn sftp_list_dir(state: State<'_, SftpService>, host: String, path: String, password: Option<String>) -> Result<Vec<SftpEntry>, String> {
+    let service = state.inner().clone();
+    blocking(move || service.list_dir(&host, &path, password.as_deref())).await
+}
+
+#[command]
+pub async fn sftp_stat(state: State<'_, SftpService>, host: String, path: String, password: Option<String>) -> Result<SftpEntry, String> {
+    let service = state.inner().clone();
+    blocking(move || service.stat(&host, &path, password.as_deref())).await
+}
+
+#[command]
+pub async fn sftp_download(
+    app_handle: AppHandle,
+    state: State<'_, SftpService>,
+    transfer_id: String,
+    host: String,
+    remote_path: String,
+    local_path: String,
+    password: Option<String>,
+) -> Result<u64, String> {
+    let service = state.inner().clone();
+    let local_path = PathBuf::from(crate::shared::utils::expand_path(&local_path));
+    blocking(move || service.download(&app_handle, &transfer_id, &host, &remote_path, &local_path, password.as_deref())).await
+}
+
+#[command]
+pub async fn sftp_upload(
+    app_handle: AppHandle,
+    state: State<'_, SftpService>,
+    transfer_id: String,
+    host: String,
+    local_path: String,
+    remote_path: String,
+    password: Option<String>,
+) -> Result<u64, String> {
+    let service = state.inner().clone();
+    let local_path = PathBuf::from(crate::shared::utils::expand_path(&local_path));
+    blocking(move || service.upload(&app_handle, &transfer_id, &host, &local_path, &remote_path, password.as_deref())).await
+}
+
+#[command]
+pub async fn sftp_rename(state: State<'_, SftpService>, host: String, from: String, to: String, overwrite: Option<bool>, password: Option<String>) -> Result<(), String> {
+    let service = state.inner().clone();
+    blocking(move || service.rename(&host, &from, &to, overwrite.unwrap_or(false), password.as_deref())).await
+}
+
+#[command]
+pub async fn sftp_delete(state: State<'_, SftpService>, host: String, path: String, password: Option<String>) -> Result<(), String> {
+    let service = state.inner().clone();
+    blocking(move || service.delete(&host, &path, password.as_deref())).await
+}
+
+#[command]
+pub async fn sftp_mkdir(state: State<'_, SftpService>, host: String, path: String, password: Option<String>) -> Result<(), String> {
+    let service = state.inner().clone();
+    blocking(move || service.mkdir(&host, &path, password.as_deref())).await
+}
+
+#[command]
+pub fn sftp_disconnect(state: State<'_, SftpService>, host: String) {
+    state.disconnect(&host);
 }
 
 // Wrapper for service calls because service methods methods take &self
 // But typically in Tauri commands we might want to dependency inject or just instantiate.
-// Since SshService holds a path that is essentially statis (home dir), instantiation is cheap.
+// SshService is managed state, so every command edits the same config with the same backup dir.
 mod service {
     use super::SshService;
     use super::SshHostModel;
@@ -29,4 +325,12 @@ mod service {
     pub fn add_host(service: &SshService, host: SshHostModel) -> Result<()> {
         service.add_host(host)
     }
+
+    pub fn update_host(service: &SshService, alias: &str, host: SshHostModel) -> Result<()> {
+        service.update_host(alias, host)
+    }
+
+    pub fn delete_host(service: &SshService, alias: &str) -> Result<()> {
+        service.delete_host(alias)
+    }
 }
@@ -0,0 +1,590 @@
+use anyhow::{anyhow, bail, Result};
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+// Same nesting limit as OpenSSH's readconf.c
+const MAX_INCLUDE_DEPTH: usize = 16;
+// Directives that may appear several times and all apply, instead of first-one-wins
+const MULTI_VALUED: &[&str] = &["identityfile", "certificatefile", "localforward", "remoteforward", "dynamicforward", "sendenv", "setenv"];
+
+/// One `Keyword args...` line. Arguments keep their byte spans so edits can
+/// replace exactly the value and leave spacing, `=` and comments alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Directive {
+    pub keyword: String,
+    pub args: Vec<String>,
+    spans: Vec<Range<usize>>,
+    content_end: usize,
+}
+
+impl Directive {
+    pub fn is(&self, keyword: &str) -> bool {
+        self.keyword.eq_ignore_ascii_case(keyword)
+    }
+
+    pub fn value(&self) -> String {
+        self.args.join(" ")
+    }
+
+    fn is_block_start(&self) -> bool {
+        self.is("Host") || self.is("Match")
+    }
+}
+
+fn line_content(line: &str) -> &str {
+    line.trim_end_matches(['\n', '\r'])
+}
+
+/// Parses a single line the way ssh does: `Keyword value`, `Keyword=value`,
+/// double-quoted arguments, `#` comments. Blank and comment lines give `None`.
+pub fn parse_line(line: &str) -> Option<Directive> {
+    let content = line_content(line);
+    let bytes = content.as_bytes();
+    let mut pos = content.len() - content.trim_start().len();
+    if pos == content.len() || bytes[pos] == b'#' {
+        return None;
+    }
+
+    let keyword_start = pos;
+    while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'=' {
+        pos += 1;
+    }
+    let keyword = content[keyword_start..pos].to_string();
+    while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    if pos < bytes.len() && bytes[pos] == b'=' {
+        pos += 1;
+    }
+
+    let mut args = Vec::new();
+    let mut spans = Vec::new();
+    loop {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos == bytes.len() || bytes[pos] == b'#' {
+            break;
+        }
+        let start = pos;
+        if bytes[pos] == b'"' {
+            let end = content[pos + 1..].find('"').map(|i| pos + 1 + i);
+            match end {
+                Some(end) => {
+                    args.push(content[pos + 1..end].to_string());
+                    pos = end + 1;
+                }
+                None => {
+                    args.push(content[pos + 1..].to_string());
+                    pos = bytes.len();
+                }
+            }
+        } else {
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            args.push(content[start..pos].to_string());
+        }
+        spans.push(start..pos);
+    }
+
+    Some(Directive { keyword, args, spans, content_end: content.len() })
+}
+
+/// Quotes a value that would otherwise split into several arguments.
+fn quote(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '#') {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// ssh's `*`/`?` wildcard match, case-insensitive like host matching is.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    fn matches(p: &[u8], t: &[u8]) -> bool {
+        match (p.first(), t.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => matches(&p[1..], t) || (!t.is_empty() && matches(p, &t[1..])),
+            (Some(b'?'), Some(_)) => matches(&p[1..], &t[1..]),
+            (Some(a), Some(b)) if a.eq_ignore_ascii_case(b) => matches(&p[1..], &t[1..]),
+            _ => false,
+        }
+    }
+    matches(pattern.as_bytes(), text.as_bytes())
+}
+
+/// A pattern list matches when some pattern does and no `!pattern` does.
+fn pattern_list_matches<'a>(patterns: impl IntoIterator<Item = &'a str>, host: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns {
+        if let Some(negated) = pattern.strip_prefix('!') {
+            if wildcard_match(negated, host) {
+                return false;
+            }
+        } else if wildcard_match(pattern, host) {
+            matched = true;
+        }
+    }
+    matched
+}
+
+fn is_pattern(alias: &str) -> bool {
+    alias.contains(['*', '?', '!'])
+}
+
+/// Evaluates the `Match` criteria we can answer without running anything.
+/// `exec`, `canonical` and unknown criteria never match, so their options stay off.
+fn match_applies(args: &[String], host: &str) -> bool {
+    let local_user = std::env::var("USER").unwrap_or_default();
+    let mut args = args.iter();
+    while let Some(criterion) = args.next() {
+        let (negate, name) = match criterion.strip_prefix('!') {
+            Some(name) => (true, name.to_ascii_lowercase()),
+            None => (false, criterion.to_ascii_lowercase()),
+        };
+        let result = match name.as_str() {
+            "all" | "final" => true,
+            "host" | "originalhost" => args.next().is_some_and(|list| pattern_list_matches(list.split(','), host)),
+            "localuser" => args.next().is_some_and(|list| pattern_list_matches(list.split(','), &local_user)),
+            "user" | "localnetwork" | "tagged" | "exec" => {
+                args.next();
+                false
+            }
+            _ => false,
+        };
+        if result == negate {
+            return false;
+        }
+    }
+    true
+}
+
+/// An ssh_config file kept line by line, so anything we don't edit is written
+/// back exactly as it was read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SshConfigFile {
+    lines: Vec<String>,
+}
+
+impl SshConfigFile {
+    pub fn parse(text: &str) -> Self {
+        Self { lines: text.split_inclusive('\n').map(str::to_string).collect() }
+    }
+
+    /// Reads a file, a missing one is an empty config.
+    pub fn read(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn directives(&self) -> impl Iterator<Item = Directive> + '_ {
+        self.lines.iter().filter_map(|line| parse_line(line))
+    }
+
+    fn directive_at(&self, index: usize) -> Option<Directive> {
+        parse_line(&self.lines[index])
+    }
+
+    fn is_comment(&self, index: usize) -> bool {
+        self.lines[index].trim_start().starts_with('#')
+    }
+
+    fn is_blank(&self, index: usize) -> bool {
+        self.lines[index].trim().is_empty()
+    }
+
+    fn newline(&self) -> &'static str {
+        if self.lines.first().is_some_and(|l| l.ends_with("\r\n")) { "\r\n" } else { "\n" }
+    }
+
+    /// Concrete aliases declared by `Host` lines, wildcard patterns left out.
+    pub fn host_aliases(&self) -> Vec<String> {
+        self.directives()
+            .filter(|d| d.is("Host"))
+            .flat_map(|d| d.args)
+            .filter(|alias| !is_pattern(alias))
+            .collect()
+    }
+
+    pub fn has_host(&self, alias: &str) -> bool {
+        self.host_header(alias).is_some()
+    }
+
+    fn host_header(&self, alias: &str) -> Option<usize> {
+        (0..self.lines.len()).find(|&i| {
+            self.directive_at(i).is_some_and(|d| d.is("Host") && d.args.iter().any(|a| a == alias))
+        })
+    }
+
+    /// The line after the last line of the block that starts at `header`.
+    fn block_end(&self, header: usize) -> usize {
+        (header + 1..self.lines.len())
+            .find(|&i| self.directive_at(i).is_some_and(|d| d.is_block_start()))
+            .unwrap_or(self.lines.len())
+    }
+
+    /// Comment lines directly above a header belong to it.
+    fn leading_comments_start(&self, header: usize) -> usize {
+        let mut start = header;
+        while start > 0 && self.is_comment(start - 1) {
+            start -= 1;
+        }
+        start
+    }
+
+    fn splice(&mut self, index: usize, range: Range<usize>, replacement: &str) {
+        self.lines[index].replace_range(range, replacement);
+    }
+
+    fn ensure_newline(&mut self, index: usize) {
+        if !self.lines[index].ends_with('\n') {
+            let newline = self.newline();
+            self.lines[index].push_str(newline);
+        }
+    }
+
+    /// Where a new directive goes: after the block's last directive, indented like the others.
+    fn insertion_point(&self, header: usize) -> (usize, String) {
+        let end = self.block_end(header);
+        let body: Vec<usize> = (header + 1..end).filter(|&i| self.directive_at(i).is_some()).collect();
+        let indent = body.first()
+            .map(|&i| {
+                let line = &self.lines[i];
+                line[..line.len() - line.trim_start().len()].to_string()
+            })
+            .unwrap_or_else(|| "    ".to_string());
+        (body.last().map(|i| i + 1).unwrap_or(header + 1), indent)
+    }
+
+    fn insert_directive(&mut self, index: usize, indent: &str, keyword: &str, value: &str) {
+        self.ensure_newline(index - 1);
+        let line = format!("{}{} {}{}", indent, keyword, value, self.newline());
+        self.lines.insert(index, line);
+    }
+
+    fn block_lines(&self, header: usize, keyword: &str) -> Vec<usize> {
+        (header + 1..self.block_end(header))
+            .filter(|&i| self.directive_at(i).is_some_and(|d| d.is(keyword)))
+            .collect()
+    }
+
+    fn set_value(&mut self, index: usize, value: &str) {
+        let Some(directive) = self.directive_at(index) else { return };
+        if directive.value() == value {
+            return;
+        }
+        match (directive.spans.first(), directive.spans.last()) {
+            (Some(first), Some(last)) => self.splice(index, first.start..last.end, value),
+            _ => self.splice(index, directive.content_end..directive.content_end, &format!(" {}", value)),
+        }
+    }
+
+    /// Sets a single-valued option of a `Host` block. `None` removes it.
+    pub fn set_option(&mut self, alias: &str, keyword: &str, value: Option<&str>) -> Result<()> {
+        let header = self.host_header(alias).ok_or_else(|| anyhow!("Host {} not found", alias))?;
+        let existing = self.block_lines(header, keyword);
+        match (value, existing.first()) {
+            (Some(value), Some(&index)) => self.set_value(index, &quote(value)),
+            (Some(value), None) => {
+                let (index, indent) = self.insertion_point(header);
+                self.insert_directive(index, &indent, keyword, &quote(value));
+            }
+            (None, _) => {
+                for index in existing.into_iter().rev() {
+                    self.lines.remove(index);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes a repeatable option of a `Host` block (e.g. `LocalForward`) hold exactly `values`.
+    /// Values are written as-is, since they're usually several arguments.
+    pub fn set_list(&mut self, alias: &str, keyword: &str, values: &[String]) -> Result<()> {
+        let header = self.host_header(alias).ok_or_else(|| anyhow!("Host {} not found", alias))?;
+        let existing = self.block_lines(header, keyword);
+        for (&index, value) in existing.iter().zip(values) {
+            self.set_value(index, value);
+        }
+        for &index in existing.iter().skip(values.len()).rev() {
+            self.lines.remove(index);
+        }
+        if values.len() > existing.len() {
+            let (mut index, indent) = match existing.last() {
+                Some(&last) => {
+                    let (_, indent) = self.insertion_point(header);
+                    (last + 1, indent)
+                }
+                None => self.insertion_point(header),
+            };
+            for value in &values[existing.len()..] {
+                self.insert_directive(index, &indent, keyword, value);
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Renames one alias on its `Host` line.
+    pub fn rename_host(&mut self, alias: &str, new_alias: &str) -> Result<()> {
+        if alias == new_alias {
+            return Ok(());
+        }
+        if self.has_host(new_alias) {
+            bail!("Host {} already exists", new_alias);
+        }
+        let header = self.host_header(alias).ok_or_else(|| anyhow!("Host {} not found", alias))?;
+        let directive = self.directive_at(header).expect("header is a directive");
+        let position = directive.args.iter().position(|a| a == alias).expect("header declares alias");
+        self.splice(header, directive.spans[position].clone(), &quote(new_alias));
+        Ok(())
+    }
+
+    /// Removes an alias. A block that only declared this alias goes away with
+    /// its leading comments; a shared `Host a b` line just loses the alias.
+    pub fn remove_host(&mut self, alias: &str) -> Result<()> {
+        let header = self.host_header(alias).ok_or_else(|| anyhow!("Host {} not found", alias))?;
+        let directive = self.directive_at(header).expect("header is a directive");
+
+        if directive.args.len() > 1 {
+            let position = directive.args.iter().position(|a| a == alias).expect("header declares alias");
+            let span = directive.spans[position].clone();
+            // Take the whitespace in front of the alias along with it
+            let line = &self.lines[header];
+            let start = line[..span.start].trim_end().len();
+            self.splice(header, start..span.end, "");
+            return Ok(());
+        }
+
+        let start = self.leading_comments_start(header);
+        let end = self.block_end(header);
+        let end = if end < self.lines.len() { self.leading_comments_start(end) } else { end };
+        let start = if end == self.lines.len() {
+            // Last block: don't leave the blank lines that separated it behind
+            let mut start = start;
+            while start > 0 && self.is_blank(start - 1) {
+                start -= 1;
+            }
+            start
+        } else {
+            start
+        };
+        self.lines.drain(start..end);
+        Ok(())
+    }
+}
+
+impl fmt::Display for SshConfigFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            f.write_str(line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Files an `Include` argument names. Relative paths are relative to `base`
+/// (`~/.ssh` for the user config); globs expand in sorted order.
+pub fn include_paths(base: &Path, pattern: &str) -> Vec<PathBuf> {
+    let expanded = crate::shared::utils::expand_path(pattern);
+    let full = if Path::new(&expanded).is_absolute() { PathBuf::from(expanded) } else { base.join(expanded) };
+    match glob::glob(&full.to_string_lossy()) {
+        Ok(paths) => paths.filter_map(|p| p.ok()).filter(|p| p.is_file()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Options that apply to a host, gathered first-one-wins like ssh does.
+#[derive(Debug, Default)]
+pub struct HostOptions {
+    options: Vec<(String, String)>,
+}
+
+impl HostOptions {
+    fn push(&mut self, keyword: &str, value: String) {
+        let keyword = keyword.to_ascii_lowercase();
+        if MULTI_VALUED.contains(&keyword.as_str()) || !self.options.iter().any(|(k, _)| *k == keyword) {
+            self.options.push((keyword, value));
+        }
+    }
+
+    pub fn get(&self, keyword: &str) -> Option<&str> {
+        self.options.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(keyword))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all(&self, keyword: &str) -> Vec<&str> {
+        self.options.iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(keyword))
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+}
+
+/// Resolves the options for `host` from `path`, following `Include`s and evaluating `Match` blocks.
+pub fn query(path: &Path, host: &str) -> Result<HostOptions> {
+    let base = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let mut options = HostOptions::default();
+    collect_options(path, &base, host, &mut options, 0)?;
+    Ok(options)
+}
+
+fn collect_options(path: &Path, base: &Path, host: &str, options: &mut HostOptions, depth: usize) -> Result<()> {
+    if depth > MAX_INCLUDE_DEPTH {
+        bail!("Too many nested Includes in {}", path.display());
+    }
+    // An included file starts in the state of the block that included it, and
+    // whatever Host lines it has don't leak back out
+    let mut active = true;
+    for directive in SshConfigFile::read(path)?.directives() {
+        if directive.is("Host") {
+            active = pattern_list_matches(directive.args.iter().map(String::as_str), host);
+        } else if directive.is("Match") {
+            active = match_applies(&directive.args, host);
+        } else if !active {
+            continue;
+        } else if directive.is("Include") {
+            for pattern in &directive.args {
+                for included in include_paths(base, pattern) {
+                    collect_options(&included, base, host, options, depth + 1)?;
+                }
+            }
+        } else {
+            options.push(&directive.keyword, directive.value());
+        }
+    }
+    Ok(())
+}
+
+/// Every concrete alias reachable from `path`, with the file that declares it, in file order.
+pub fn host_sources(path: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let base = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let mut sources = Vec::new();
+    collect_sources(path, &base, &mut sources, 0)?;
+    Ok(sources)
+}
+
+fn collect_sources(path: &Path, base: &Path, sources: &mut Vec<(String, PathBuf)>, depth: usize) -> Result<()> {
+    if depth > MAX_INCLUDE_DEPTH {
+        bail!("Too many nested Includes in {}", path.display());
+    }
+    for directive in SshConfigFile::read(path)?.directives() {
+        if directive.is("Host") {
+            for alias in directive.args.iter().filter(|a| !is_pattern(a)) {
+                if !sources.iter().any(|(known, _)| known == alias) {
+                    sources.push((alias.clone(), path.to_path_buf()));
+                }
+            }
+        } else if directive.is("Include") {
+            for pattern in &directive.args {
+                for included in include_paths(base, pattern) {
+                    collect_sources(&included, base, sources, depth + 1)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "# Global settings\r\nInclude config.d/*\r\nServerAliveInterval 60\r\n\r\nHost web web-alias\r\n\tHostName=10.0.0.1   # prod box\r\n\tUser deploy\r\n\tLocalForward 8080 localhost:80\r\n\tIdentityFile \"~/.ssh/my key\"\r\n\r\n# Jump through the bastion\r\nHost db\r\n  HostName 10.0.0.2\r\n  ProxyJump bastion\r\n  SomeFutureOption yes\r\n\r\nMatch host db exec \"test -f /tmp/x\"\r\n  User nobody\r\n\r\nHost *\r\n  User fallback";
+
+    #[test]
+    fn test_untouched_config_round_trips() {
+        assert_eq!(SshConfigFile::parse(CONFIG).to_string(), CONFIG);
+        let file = SshConfigFile::parse(CONFIG);
+        assert_eq!(file.host_aliases(), vec!["web", "web-alias", "db"]);
+
+        let hostname = parse_line("\tHostName=10.0.0.1   # prod box\r\n").unwrap();
+        assert_eq!((hostname.keyword.as_str(), hostname.args.as_slice()), ("HostName", &["10.0.0.1".to_string()][..]));
+        let identity = parse_line("\tIdentityFile \"~/.ssh/my key\"").unwrap();
+        assert_eq!(identity.value(), "~/.ssh/my key");
+    }
+
+    #[test]
+    fn test_edits_touch_only_their_lines() -> Result<()> {
+        let mut file = SshConfigFile::parse(CONFIG);
+        file.set_option("web", "HostName", Some("10.0.0.9"))?;
+        file.set_option("web", "User", Some("deploy"))?;
+        file.set_option("db", "ProxyJump", None)?;
+        file.set_option("db", "Port", Some("2222"))?;
+        file.set_list("web", "LocalForward", &["8080 localhost:80".into(), "5432 db:5432".into()])?;
+        let expected = CONFIG
+            .replace("HostName=10.0.0.1   # prod box", "HostName=10.0.0.9   # prod box")
+            .replace("\tLocalForward 8080 localhost:80\r\n", "\tLocalForward 8080 localhost:80\r\n\tLocalForward 5432 db:5432\r\n")
+            .replace("  ProxyJump bastion\r\n  SomeFutureOption yes\r\n", "  SomeFutureOption yes\r\n  Port 2222\r\n");
+        assert_eq!(file.to_string(), expected);
+
+        file.rename_host("web-alias", "www")?;
+        assert!(file.to_string().contains("Host web www\r\n"));
+        assert!(file.rename_host("www", "db").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_host() -> Result<()> {
+        let mut file = SshConfigFile::parse(CONFIG);
+        file.remove_host("web-alias")?;
+        assert!(file.to_string().contains("Host web\r\n\tHostName"));
+
+        file.remove_host("db")?;
+        let text = file.to_string();
+        assert!(!text.contains("bastion") && !text.contains("SomeFutureOption"));
+        assert!(text.contains("IdentityFile \"~/.ssh/my key\"\r\n\r\nMatch host db"));
+
+        let mut last = SshConfigFile::parse("Host a\n  User x\n\nHost b\n  User y\n");
+        last.remove_host("b")?;
+        assert_eq!(last.to_string(), "Host a\n  User x\n");
+        assert!(last.remove_host("b").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_follows_include_and_match() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("switchboard-ssh-config-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("config.d"))?;
+        fs::write(dir.join("config"), CONFIG)?;
+        fs::write(dir.join("config.d").join("work"), "Host build\n  HostName ci.internal\n  Port 2200\n")?;
+
+        let path = dir.join("config");
+        let web = query(&path, "web")?;
+        assert_eq!(web.get("hostname"), Some("10.0.0.1"));
+        assert_eq!(web.get("User"), Some("deploy"));
+        assert_eq!(web.get("ServerAliveInterval"), Some("60"));
+        assert_eq!(web.get_all("LocalForward"), vec!["8080 localhost:80"]);
+
+        // Match with exec never applies, so the catch-all wins
+        let db = query(&path, "db")?;
+        assert_eq!((db.get("ProxyJump"), db.get("User")), (Some("bastion"), Some("fallback")));
+
+        let build = query(&path, "build")?;
+        assert_eq!((build.get("HostName"), build.get("Port")), (Some("ci.internal"), Some("2200")));
+
+        let sources = host_sources(&path)?;
+        let aliases: Vec<&str> = sources.iter().map(|(a, _)| a.as_str()).collect();
+        assert_eq!(aliases, vec!["build", "web", "web-alias", "db"]);
+        assert_eq!(sources[0].1, dir.join("config.d").join("work"));
+
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_criteria() {
+        assert!(match_applies(&["all".into()], "x"));
+        assert!(match_applies(&["host".into(), "*.prod,!db.prod".into()], "web.prod"));
+        assert!(!match_applies(&["host".into(), "*.prod,!db.prod".into()], "db.prod"));
+        assert!(match_applies(&["!host".into(), "db*".into()], "web"));
+        assert!(!match_applies(&["host".into(), "web".into(), "exec".into(), "true".into()], "web"));
+    }
+}
@@ -0,0 +1,280 @@
+use super::models::SshTarget;
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use serde::Serialize;
+use ssh2::{CheckResult, ErrorCode, HashType, HostKeyType, KnownHostFileKind, Session};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// LIBSSH2_ERROR_EAGAIN, returned instead of blocking when the session is non-blocking
+const LIBSSH2_ERROR_EAGAIN: i32 = -37;
+
+pub fn would_block(e: &ssh2::Error) -> bool {
+    e.code() == ErrorCode::Session(LIBSSH2_ERROR_EAGAIN)
+}
+
+/// How `known_hosts` names a host: bare for port 22, `[host]:port` otherwise.
+pub fn known_hosts_name(hostname: &str, port: u16) -> String {
+    if port == 22 {
+        hostname.to_string()
+    } else {
+        format!("[{}]:{}", hostname, port)
+    }
+}
+
+fn key_type_name(key_type: HostKeyType) -> Option<&'static str> {
+    match key_type {
+        HostKeyType::Rsa => Some("ssh-rsa"),
+        HostKeyType::Dss => Some("ssh-dss"),
+        HostKeyType::Ecdsa256 => Some("ecdsa-sha2-nistp256"),
+        HostKeyType::Ecdsa384 => Some("ecdsa-sha2-nistp384"),
+        HostKeyType::Ecdsa521 => Some("ecdsa-sha2-nistp521"),
+        HostKeyType::Ed25519 => Some("ssh-ed25519"),
+        HostKeyType::Unknown => None,
+    }
+}
+
+/// The server key's SHA256 fingerprint, formatted like `ssh-keygen -l` prints it.
+pub fn fingerprint(session: &Session) -> Option<String> {
+    session.host_key_hash(HashType::Sha256)
+        .map(|hash| format!("SHA256:{}", base64::engine::general_purpose::STANDARD_NO_PAD.encode(hash)))
+}
+
+/// TCP connect plus SSH handshake. Nothing is verified or authenticated yet.
+pub fn handshake(target: &SshTarget) -> Result<Session> {
+    if let Some(jump) = &target.proxy_jump {
+        return Err(anyhow!("{} is reached through ProxyJump {}, which native sessions don't support yet", target.alias, jump));
+    }
+    let address = (target.hostname.as_str(), target.port).to_socket_addrs()
+        .with_context(|| format!("Could not resolve {}", target.hostname))?
+        .next()
+        .ok_or_else(|| anyhow!("Could not resolve {}", target.hostname))?;
+    let tcp = TcpStream::connect_timeout(&address, target.connect_timeout)
+        .with_context(|| format!("Could not connect to {}:{}", target.hostname, target.port))?;
+
+    let mut session = Session::new()?;
+    session.set_tcp_stream(tcp);
+    session.set_timeout(target.connect_timeout.as_millis() as u32);
+    session.handshake()?;
+    Ok(session)
+}
+
+/// Why `verify_host_key` refused a server. Callers get it back with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostKeyError {
+    /// Not in `known_hosts` yet; the user may trust it with `trust_host_key`.
+    Unknown { alias: String, fingerprint: String },
+    /// Differs from the key in `known_hosts`, never trusted automatically.
+    Changed { alias: String, fingerprint: String, known_hosts: PathBuf },
+}
+
+impl fmt::Display for HostKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostKeyError::Unknown { alias, fingerprint } => write!(f, "Unknown host key for {} ({})", alias, fingerprint),
+            HostKeyError::Changed { alias, fingerprint, known_hosts } => write!(
+                f,
+                "HOST KEY CHANGED for {} ({}). Someone could be intercepting the connection; remove the old key from {} if the change is expected",
+                alias, fingerprint, known_hosts.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HostKeyError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SshErrorKind {
+    UnknownHostKey,
+    ChangedHostKey,
+    Other,
+}
+
+/// Error returned by the connect commands, so the UI can prompt on host key
+/// problems without parsing the message.
+#[derive(Debug, Clone, Serialize)]
+pub struct SshCommandError {
+    pub kind: SshErrorKind,
+    pub alias: Option<String>,
+    pub fingerprint: Option<String>,
+    pub message: String,
+}
+
+impl From<anyhow::Error> for SshCommandError {
+    fn from(e: anyhow::Error) -> Self {
+        let message = e.to_string();
+        match e.downcast::<HostKeyError>() {
+            Ok(HostKeyError::Unknown { alias, fingerprint }) => Self {
+                kind: SshErrorKind::UnknownHostKey,
+                alias: Some(alias),
+                fingerprint: Some(fingerprint),
+                message,
+            },
+            Ok(HostKeyError::Changed { alias, fingerprint, .. }) => Self {
+                kind: SshErrorKind::ChangedHostKey,
+                alias: Some(alias),
+                fingerprint: Some(fingerprint),
+                message,
+            },
+            Err(_) => Self { kind: SshErrorKind::Other, alias: None, fingerprint: None, message },
+        }
+    }
+}
+
+impl From<tauri::Error> for SshCommandError {
+    fn from(e: tauri::Error) -> Self {
+        anyhow::Error::from(e).into()
+    }
+}
+
+/// Checks the server's key against `known_hosts`. Unknown hosts are refused until the
+/// user trusts them with `trust_host_key`; a changed key is always refused.
+pub fn verify_host_key(session: &Session, known_hosts: &Path, target: &SshTarget) -> Result<()> {
+    let (key, _) = session.host_key().ok_or_else(|| anyhow!("Server sent no host key"))?;
+    let mut hosts = session.known_hosts()?;
+    if known_hosts.exists() {
+        hosts.read_file(known_hosts, KnownHostFileKind::OpenSSH)?;
+    }
+    let fingerprint = fingerprint(session).unwrap_or_default();
+    match hosts.check_port(&target.hostname, target.port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(HostKeyError::Unknown { alias: target.alias.clone(), fingerprint }.into()),
+        CheckResult::Mismatch => Err(HostKeyError::Changed {
+            alias: target.alias.clone(),
+            fingerprint,
+            known_hosts: known_hosts.to_path_buf(),
+        }.into()),
+        CheckResult::Failure => Err(anyhow!("Could not check the host key of {}", target.alias)),
+    }
+}
+
+pub fn is_host_key_changed(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref::<HostKeyError>(), Some(HostKeyError::Changed { .. }))
+}
+
+/// Tries the ssh-agent, then each identity file, then the password if one was given.
+pub fn authenticate(session: &Session, target: &SshTarget, password: Option<&str>) -> Result<()> {
+    let _ = session.userauth_agent(&target.user);
+    for identity in &target.identity_files {
+        if session.authenticated() {
+            break;
+        }
+        let _ = session.userauth_pubkey_file(&target.user, None, identity, None);
+    }
+    if let (false, Some(password)) = (session.authenticated(), password) {
+        let _ = session.userauth_password(&target.user, password);
+    }
+    if !session.authenticated() {
+        return Err(anyhow!("Authentication failed for {}@{}", target.user, target.alias));
+    }
+    Ok(())
+}
+
+/// A verified, authenticated session.
+pub fn connect(target: &SshTarget, known_hosts: &Path, password: Option<&str>) -> Result<Session> {
+    let session = handshake(target)?;
+    verify_host_key(&session, known_hosts, target)?;
+    authenticate(&session, target, password)?;
+    Ok(session)
+}
+
+/// Appends the server's key to `known_hosts` if its fingerprint is still `expected_fingerprint`,
+/// the one the user approved. Only for hosts that aren't known yet; a changed key has to be
+/// removed by hand.
+pub fn trust_host_key(target: &SshTarget, known_hosts: &Path, expected_fingerprint: &str) -> Result<String> {
+    let session = handshake(target)?;
+    match verify_host_key(&session, known_hosts, target) {
+        Ok(()) => return fingerprint(&session).ok_or_else(|| anyhow!("Server sent no host key")),
+        Err(e) if !matches!(e.downcast_ref::<HostKeyError>(), Some(HostKeyError::Unknown { .. })) => return Err(e),
+        Err(_) => {}
+    }
+
+    // The key could have changed since the user saw the prompt
+    let presented = fingerprint(&session).ok_or_else(|| anyhow!("Server sent no host key"))?;
+    if presented != expected_fingerprint {
+        return Err(HostKeyError::Changed {
+            alias: target.alias.clone(),
+            fingerprint: presented,
+            known_hosts: known_hosts.to_path_buf(),
+        }.into());
+    }
+
+    let (key, key_type) = session.host_key().ok_or_else(|| anyhow!("Server sent no host key"))?;
+    let key_type = key_type_name(key_type).ok_or_else(|| anyhow!("Unsupported host key type"))?;
+    let line = format!(
+        "{} {} {}\n",
+        known_hosts_name(&target.hostname, target.port),
+        key_type,
+        base64::engine::general_purpose::STANDARD.encode(key)
+    );
+
+    if let Some(parent) = known_hosts.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(known_hosts)?;
+    file.write_all(line.as_bytes())?;
+    Ok(presented)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_known_hosts_name() {
+        assert_eq!(known_hosts_name("github.com", 22), "github.com");
+        assert_eq!(known_hosts_name("10.0.0.5", 2222), "[10.0.0.5]:2222");
+    }
+
+    #[test]
+    fn test_connect_refused() {
+        // Grab a free port and release it, nothing listens there now
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let target = SshTarget {
+            alias: "nowhere".into(),
+            hostname: "127.0.0.1".into(),
+            port,
+            user: "nobody".into(),
+            identity_files: Vec::new(),
+            proxy_jump: None,
+            keepalive: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(2),
+        };
+        let err = handshake(&target).err().expect("connection should fail");
+        assert!(err.to_string().contains("Could not connect"));
+    }
+
+    #[test]
+    fn test_host_key_errors_downcast() {
+        let unknown: anyhow::Error = HostKeyError::Unknown { alias: "db".into(), fingerprint: "SHA256:abc".into() }.into();
+        assert_eq!(unknown.to_string(), "Unknown host key for db (SHA256:abc)");
+        assert!(!is_host_key_changed(&unknown));
+
+        let changed: anyhow::Error = HostKeyError::Changed {
+            alias: "db".into(),
+            fingerprint: "SHA256:abc".into(),
+            known_hosts: PathBuf::from("/home/me/.ssh/known_hosts"),
+        }.into();
+        assert!(is_host_key_changed(&changed));
+        assert!(!is_host_key_changed(&anyhow!("HOST KEY CHANGED, but only in the text")));
+    }
+
+    #[test]
+    fn test_command_error_keeps_host_key_details() {
+        let unknown: anyhow::Error = HostKeyError::Unknown { alias: "db".into(), fingerprint: "SHA256:abc".into() }.into();
+        let json = serde_json::to_value(SshCommandError::from(unknown.context("Connecting to db"))).unwrap();
+        assert_eq!(json["kind"], "unknown_host_key");
+        assert_eq!(json["alias"], "db");
+        assert_eq!(json["fingerprint"], "SHA256:abc");
+        assert_eq!(json["message"], "Connecting to db");
+
+        let other = SshCommandError::from(anyhow!("Unknown host key for db (SHA256:abc)"));
+        assert_eq!(other.kind, SshErrorKind::Other);
+        assert_eq!(other.fingerprint, None);
+    }
+}
@@ -0,0 +1,399 @@
+use super::models::{GenerateKeyInput, SshKeyInfo, SshKeyType};
+use crate::modules::vault::service::VaultService;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const DEFAULT_RSA_BITS: u32 = 4096;
+const MIN_RSA_BITS: u32 = 2048;
+const OPENSSH_KEY_MAGIC: &[u8] = b"openssh-key-v1\0";
+
+/// Vault entry holding the passphrase of a key, by fingerprint so it survives renames.
+fn passphrase_reference(fingerprint: &str) -> String {
+    format!("ssh-key:{}", fingerprint)
+}
+
+/// One line of `ssh-keygen -l` or `ssh-add -l`: `256 SHA256:abc comment words (ED25519)`.
+/// Returns (bits, fingerprint, comment, type).
+pub fn parse_fingerprint_line(line: &str) -> Option<(u32, String, Option<String>, String)> {
+    let line = line.trim();
+    let (rest, key_type) = line.rsplit_once(" (")?;
+    let key_type = key_type.strip_suffix(')')?.to_string();
+    let mut parts = rest.splitn(3, ' ');
+    let bits = parts.next()?.parse().ok()?;
+    let fingerprint = parts.next()?.to_string();
+    let comment = parts.next()
+        .map(str::trim)
+        .filter(|c| !c.is_empty() && *c != "no comment")
+        .map(str::to_string);
+    Some((bits, fingerprint, comment, key_type))
+}
+
+/// Whether a private key needs a passphrase, read from the key itself.
+pub fn is_encrypted(private_key: &str) -> bool {
+    if private_key.contains("ENCRYPTED") {
+        // PEM keys say so in their headers
+        return true;
+    }
+    let body: String = private_key.lines()
+        .filter(|l| !l.starts_with("-----"))
+        .collect();
+    let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(body.trim()) else {
+        return false;
+    };
+    // openssh-key-v1\0, then the cipher name as a length-prefixed string
+    let Some(rest) = bytes.strip_prefix(OPENSSH_KEY_MAGIC) else {
+        return false;
+    };
+    let Some(len) = rest.get(..4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize) else {
+        return false;
+    };
+    rest.get(4..4 + len).is_some_and(|cipher| cipher != b"none")
+}
+
+fn validate_key_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        bail!("Key names may only use letters, digits, '.', '_' and '-'");
+    }
+    Ok(())
+}
+
+fn run(mut command: Command) -> Result<Output> {
+    let program = command.get_program().to_string_lossy().to_string();
+    command.stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run {}", program))
+}
+
+/// Hands `secret` to an OpenSSH tool through an askpass helper instead of its arguments,
+/// which anyone can read in the process list. Returns the helper's directory, to remove
+/// once the command has run.
+fn use_askpass(command: &mut Command, secret: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("switchboard-askpass-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir)?;
+    let script = dir.join("askpass");
+    fs::write(&script, "#!/bin/sh\nprintf '%s\\n' \"$SWITCHBOARD_ASKPASS_SECRET\"\n")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o700))?;
+    }
+    // The secret goes through the environment, which only this user can read
+    command.env("SSH_ASKPASS", &script)
+        .env("SSH_ASKPASS_REQUIRE", "force")
+        .env("SWITCHBOARD_ASKPASS_SECRET", secret);
+    if std::env::var_os("DISPLAY").is_none() {
+        // Older OpenSSH only uses SSH_ASKPASS when DISPLAY is set
+        command.env("DISPLAY", ":0");
+    }
+    Ok(dir)
+}
+
+/// Runs a command set up by `use_askpass` and removes the helper afterwards.
+fn run_with_askpass(command: Command, askpass_dir: Option<PathBuf>) -> Result<Output> {
+    let output = run(command);
+    if let Some(dir) = askpass_dir {
+        let _ = fs::remove_dir_all(dir);
+    }
+    output
+}
+
+fn stderr_message(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if stderr.is_empty() { String::from_utf8_lossy(&output.stdout).trim().to_string() } else { stderr }
+}
+
+/// Key pairs in `~/.ssh` and the running ssh-agent, through `ssh-keygen` and `ssh-add`.
+pub struct KeyService {
+    ssh_dir: PathBuf,
+    agent_socket: Option<PathBuf>, // Overrides SSH_AUTH_SOCK
+    vault: Option<VaultService>,
+}
+
+impl Default for KeyService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyService {
+    pub fn new() -> Self {
+        let home = dirs::home_dir().expect("Could not find home directory");
+        Self {
+            ssh_dir: home.join(".ssh"),
+            agent_socket: None,
+            vault: Some(VaultService::new("switchboard-app")),
+        }
+    }
+
+    /// A service over another directory and agent, without the vault. Used by tests.
+    pub fn with_dir(ssh_dir: PathBuf, agent_socket: Option<PathBuf>) -> Self {
+        Self { ssh_dir, agent_socket, vault: None }
+    }
+
+    fn ssh_add(&self) -> Command {
+        let mut command = Command::new("ssh-add");
+        if let Some(socket) = &self.agent_socket {
+            command.env("SSH_AUTH_SOCK", socket);
+        }
+        command
+    }
+
+    fn saved_passphrase(&self, fingerprint: &str) -> Option<String> {
+        self.vault.as_ref()?.get_secret(&passphrase_reference(fingerprint)).ok()
+    }
+
+    /// Every private key in the ssh directory that has its `.pub` next to it.
+    pub fn list_keys(&self) -> Result<Vec<SshKeyInfo>> {
+        if !self.ssh_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let agent = self.agent_fingerprints().unwrap_or_default();
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&self.ssh_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "pub") {
+                let private = path.with_extension("");
+                if private.is_file() {
+                    if let Ok(mut key) = self.inspect(&private) {
+                        key.in_agent = agent.contains(&key.fingerprint);
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(keys)
+    }
+
+    /// Describes one private key, e.g. to validate an `IdentityFile` or a project's key path.
+    pub fn inspect(&self, private_key: &Path) -> Result<SshKeyInfo> {
+        let private_key = PathBuf::from(crate::shared::utils::expand_path(&private_key.to_string_lossy()));
+        let contents = fs::read_to_string(&private_key)
+            .with_context(|| format!("Could not read {}", private_key.display()))?;
+
+        // Reading the .pub avoids needing the passphrase
+        let public_key = PathBuf::from(format!("{}.pub", private_key.display()));
+        let target = if public_key.is_file() { &public_key } else { &private_key };
+        let mut command = Command::new("ssh-keygen");
+        command.arg("-l").arg("-f").arg(target);
+        let output = run(command)?;
+        if !output.status.success() {
+            bail!("{} is not a valid key: {}", private_key.display(), stderr_message(&output));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (bits, fingerprint, comment, key_type) = parse_fingerprint_line(&stdout)
+            .ok_or_else(|| anyhow!("Unexpected ssh-keygen output: {}", stdout.trim()))?;
+
+        Ok(SshKeyInfo {
+            name: private_key.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            path: private_key.to_string_lossy().to_string(),
+            key_type,
+            bits,
+            passphrase_saved: self.saved_passphrase(&fingerprint).is_some(),
+            fingerprint,
+            comment,
+            encrypted: is_encrypted(&contents),
+            in_agent: false,
+        })
+    }
+
+    pub fn generate(&self, input: GenerateKeyInput) -> Result<SshKeyInfo> {
+        validate_key_name(&input.name)?;
+        let path = self.ssh_dir.join(&input.name);
+        if path.exists() || Path::new(&format!("{}.pub", path.display())).exists() {
+            bail!("A key named {} already exists", input.name);
+        }
+        if !self.ssh_dir.exists() {
+            fs::create_dir_all(&self.ssh_dir)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&self.ssh_dir, fs::Permissions::from_mode(0o700))?;
+            }
+        }
+
+        let passphrase = input.passphrase.clone().unwrap_or_default();
+        let mut command = Command::new("ssh-keygen");
+        command.arg("-q").arg("-f").arg(&path);
+        match input.key_type {
+            SshKeyType::Ed25519 => {
+                command.args(["-t", "ed25519"]);
+            }
+            SshKeyType::Rsa => {
+                let bits = input.bits.unwrap_or(DEFAULT_RSA_BITS);
+                if bits < MIN_RSA_BITS {
+                    bail!("RSA keys need at least {} bits", MIN_RSA_BITS);
+                }
+                command.args(["-t", "rsa", "-b", &bits.to_string()]);
+            }
+        }
+        command.arg("-C").arg(input.comment.as_deref().unwrap_or(""));
+        let askpass_dir = if passphrase.is_empty() {
+            command.args(["-N", ""]);
+            None
+        } else {
+            // ssh-keygen asks twice and gets the same answer both times
+            Some(use_askpass(&mut command, &passphrase)?)
+        };
+        let output = run_with_askpass(command, askpass_dir)?;
+        if !output.status.success() {
+            bail!("ssh-keygen failed: {}", stderr_message(&output));
+        }
+
+        let mut key = self.inspect(&path)?;
+        if let (Some(vault), false) = (&self.vault, passphrase.is_empty()) {
+            vault.store_secret(&passphrase_reference(&key.fingerprint), &passphrase)?;
+            key.passphrase_saved = true;
+        }
+        Ok(key)
+    }
+
+    /// Contents of the `.pub` file, ready to paste into `authorized_keys` or a Git host.
+    pub fn public_key(&self, private_key: &Path) -> Result<String> {
+        let private_key = crate::shared::utils::expand_path(&private_key.to_string_lossy());
+        let public_key = format!("{}.pub", private_key);
+        let contents = fs::read_to_string(&public_key)
+            .with_context(|| format!("Could not read {}", public_key))?;
+        Ok(contents.trim().to_string())
+    }
+
+    /// Fingerprints of the keys loaded in the agent.
+    pub fn agent_fingerprints(&self) -> Result<Vec<String>> {
+        let mut command = self.ssh_add();
+        command.arg("-l");
+        let output = run(command)?;
+        match output.status.code() {
+            Some(0) => Ok(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(parse_fingerprint_line)
+                .map(|(_, fingerprint, _, _)| fingerprint)
+                .collect()),
+            // "The agent has no identities."
+            Some(1) => Ok(Vec::new()),
+            _ => Err(anyhow!("Could not reach the ssh-agent: {}", stderr_message(&output))),
+        }
+    }
+
+    /// Loads a key into the agent. Encrypted keys use the given passphrase, or the
+    /// one saved in the vault, handed to ssh-add through an askpass helper.
+    pub fn add_to_agent(&self, private_key: &Path, passphrase: Option<String>) -> Result<()> {
+        let key = self.inspect(private_key)?;
+        let mut command = self.ssh_add();
+        command.arg(&key.path);
+
+        let askpass_dir = if key.encrypted {
+            let passphrase = passphrase
+                .or_else(|| self.saved_passphrase(&key.fingerprint))
+                .ok_or_else(|| anyhow!("{} is encrypted and no passphrase is saved for it", key.name))?;
+            Some(use_askpass(&mut command, &passphrase)?)
+        } else {
+            None
+        };
+
+        let output = run_with_askpass(command, askpass_dir)?;
+        if !output.status.success() {
+            bail!("ssh-add failed: {}", stderr_message(&output));
+        }
+        Ok(())
+    }
+
+    pub fn remove_from_agent(&self, private_key: &Path) -> Result<()> {
+        let key = self.inspect(private_key)?;
+        let mut command = self.ssh_add();
+        command.arg("-d").arg(&key.path);
+        let output = run(command)?;
+        if !output.status.success() {
+            bail!("ssh-add failed: {}", stderr_message(&output));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fingerprint_line() {
+        let (bits, fingerprint, comment, key_type) = parse_fingerprint_line("256 SHA256:frVtg+pulMrL9ESR52L7akztxNCLUnY3O6yfceMZeB0 me@host (work) (ED25519)\n").unwrap();
+        assert_eq!((bits, key_type.as_str()), (256, "ED25519"));
+        assert_eq!(fingerprint, "SHA256:frVtg+pulMrL9ESR52L7akztxNCLUnY3O6yfceMZeB0");
+        assert_eq!(comment.as_deref(), Some("me@host (work)"));
+
+        let (_, _, comment, _) = parse_fingerprint_line("2048 SHA256:Vit no comment (RSA)").unwrap();
+        assert_eq!(comment, None);
+        assert!(parse_fingerprint_line("The agent has no identities.").is_none());
+        assert!(validate_key_name("../id_rsa").is_err());
+    }
+
+    /// Needs OpenSSH 8.4 or later (ssh-keygen, ssh-add and ssh-agent) on PATH; run with
+    /// `cargo test -- --ignored`.
+    #[cfg(unix)]
+    #[test]
+    #[ignore]
+    fn test_generate_inspect_and_agent() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("switchboard-keys-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir)?;
+        let socket = dir.join("agent.sock");
+        let mut agent = Command::new("ssh-agent").arg("-D").arg("-a").arg(&socket)
+            .stdout(Stdio::null())
+            .spawn()?;
+        for _ in 0..50 {
+            if socket.exists() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        let service = KeyService::with_dir(dir.join("ssh"), Some(socket));
+        let plain = service.generate(GenerateKeyInput {
+            name: "id_test".into(),
+            key_type: SshKeyType::Ed25519,
+            bits: None,
+            comment: Some("dev@switchboard".into()),
+            passphrase: None,
+        })?;
+        assert_eq!((plain.key_type.as_str(), plain.bits, plain.encrypted), ("ED25519", 256, false));
+        assert_eq!(plain.comment.as_deref(), Some("dev@switchboard"));
+        assert!(service.public_key(Path::new(&plain.path))?.starts_with("ssh-ed25519 "));
+
+        let locked = service.generate(GenerateKeyInput {
+            name: "id_locked".into(),
+            key_type: SshKeyType::Rsa,
+            bits: Some(2048),
+            comment: None,
+            passphrase: Some("correct horse".into()),
+        })?;
+        assert!(locked.encrypted);
+        let duplicate = GenerateKeyInput {
+            name: "id_test".into(),
+            key_type: SshKeyType::Ed25519,
+            bits: None,
+            comment: None,
+            passphrase: None,
+        };
+        assert!(service.generate(duplicate).is_err());
+
+        // No vault here, so the passphrase has to be passed in
+        assert!(service.add_to_agent(Path::new(&locked.path), None).is_err());
+        service.add_to_agent(Path::new(&locked.path), Some("correct horse".into()))?;
+        service.add_to_agent(Path::new(&plain.path), None)?;
+        let keys = service.list_keys()?;
+        assert_eq!(keys.iter().map(|k| (k.name.as_str(), k.in_agent)).collect::<Vec<_>>(), vec![("id_locked", true), ("id_test", true)]);
+
+        service.remove_from_agent(Path::new(&plain.path))?;
+        assert_eq!(service.agent_fingerprints()?, vec![locked.fingerprint.clone()]);
+
+        let _ = agent.kill();
+        let _ = agent.wait();
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
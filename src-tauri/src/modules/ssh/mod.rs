@@ -1,4 +1,10 @@
 pub mod models;
+pub mod repository;
+pub mod config_file;
 pub mod service;
+pub mod keys;
+pub mod connection;
+pub mod session;
+pub mod tunnels;
+pub mod sftp;
 pub mod commands;
-
@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SshHostModel {
@@ -7,4 +10,214 @@ pub struct SshHostModel {
     pub user: Option<String>,
     pub port: Option<u16>,
     pub identity_file: Option<String>,
+    #[serde(default)]
+    pub proxy_jump: Option<String>,
+    #[serde(default)]
+    pub local_forwards: Vec<String>, // e.g. "8080 localhost:80"
+}
+
+/// Everything needed to open a connection to a host, resolved from `~/.ssh/config`.
+#[derive(Debug, Clone)]
+pub struct SshTarget {
+    pub alias: String,
+    pub hostname: String,
+    pub port: u16,
+    pub user: String,
+    pub identity_files: Vec<PathBuf>,
+    pub proxy_jump: Option<String>,
+    pub keepalive: Duration,
+    pub connect_timeout: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SshSessionStatus {
+    Connected,
+    Reconnecting,
+    Closed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshSessionInfo {
+    pub session_id: String,
+    pub host: String, // Alias the session was opened with
+    pub status: SshSessionStatus,
+    pub cols: u16,
+    pub rows: u16,
+    pub connected_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TunnelKind {
+    Local,   // -L, a local port reaching target_host:target_port from the server
+    Remote,  // -R, a port on the server reaching target_host:target_port from here
+    Dynamic, // -D, a local SOCKS proxy
+}
+
+impl std::str::FromStr for TunnelKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(Self::Local),
+            "remote" => Ok(Self::Remote),
+            "dynamic" => Ok(Self::Dynamic),
+            _ => Err(anyhow::anyhow!("Unknown tunnel kind: {}", s)),
+        }
+    }
+}
+
+/// A port forward saved per project (`ssh_tunnels` table), run with the system `ssh`.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SshTunnel {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub host: String, // Alias from ~/.ssh/config
+    pub kind: String, // 'local', 'remote', 'dynamic'
+    pub bind_address: Option<String>,
+    pub bind_port: i64,
+    pub target_host: Option<String>,
+    pub target_port: Option<i64>,
+    pub connection_id: Option<String>,
+    pub auto_reconnect: bool,
+    #[sqlx(default)]
+    pub created_at: String,
+    #[sqlx(default)]
+    pub updated_at: String,
+}
+
+impl SshTunnel {
+    pub fn tunnel_kind(&self) -> TunnelKind {
+        self.kind.parse().unwrap_or(TunnelKind::Local)
+    }
+}
+
+/// Editable fields of an `SshTunnel`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshTunnelInput {
+    pub name: String,
+    pub host: String,
+    #[serde(default = "default_tunnel_kind")]
+    pub kind: String,
+    pub bind_address: Option<String>,
+    pub bind_port: u16,
+    pub target_host: Option<String>,
+    pub target_port: Option<u16>,
+    pub connection_id: Option<String>,
+    #[serde(default = "default_auto_reconnect")]
+    pub auto_reconnect: bool,
+}
+
+fn default_tunnel_kind() -> String {
+    "local".to_string()
+}
+
+fn default_auto_reconnect() -> bool {
+    true
+}
+
+impl SshTunnelInput {
+    pub fn local(name: &str, host: &str, bind_port: u16, target_host: &str, target_port: u16) -> Self {
+        Self {
+            name: name.to_string(),
+            host: host.to_string(),
+            kind: default_tunnel_kind(),
+            bind_address: None,
+            bind_port,
+            target_host: Some(target_host.to_string()),
+            target_port: Some(target_port),
+            connection_id: None,
+            auto_reconnect: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TunnelStatus {
+    Stopped,
+    Starting,
+    Up,
+    Reconnecting,
+    Failed, // Exited and auto-reconnect is off
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TunnelState {
+    pub tunnel_id: String,
+    pub status: TunnelStatus,
+    pub message: Option<String>, // Last thing ssh printed before exiting
+    pub pid: Option<u32>,
+    pub attempts: u32, // Reconnects since the tunnel was last stable
+}
+
+/// A key pair in `~/.ssh`, as `ssh-keygen -l` describes it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshKeyInfo {
+    pub name: String, // File name of the private key, e.g. "id_ed25519"
+    pub path: String,
+    pub key_type: String, // "ED25519", "RSA", "ECDSA", ...
+    pub bits: u32,
+    pub fingerprint: String, // SHA256:...
+    pub comment: Option<String>,
+    pub encrypted: bool,
+    pub passphrase_saved: bool, // Passphrase is in the vault
+    pub in_agent: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SshKeyType {
+    Ed25519,
+    Rsa,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateKeyInput {
+    pub name: String,
+    pub key_type: SshKeyType,
+    pub bits: Option<u32>, // RSA only, defaults to 4096
+    pub comment: Option<String>,
+    pub passphrase: Option<String>, // Kept in the vault so the key can be loaded into the agent later
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SftpEntryKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+/// A remote file or directory, from `readdir` or `stat`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpEntry {
+    pub name: String,
+    pub path: String,
+    pub kind: SftpEntryKind,
+    pub size: Option<u64>,
+    pub modified: Option<u64>, // Unix seconds
+    pub permissions: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferDirection {
+    Download,
+    Upload,
+}
+
+/// Payload of `sftp_progress` events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpProgress {
+    pub transfer_id: String,
+    pub host: String,
+    pub direction: TransferDirection,
+    pub remote_path: String,
+    pub transferred: u64,
+    pub total: Option<u64>,
+    pub done: bool,
 }
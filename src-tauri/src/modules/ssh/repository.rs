@@ -0,0 +1,108 @@
+use super::models::{SshTunnel, SshTunnelInput, TunnelKind};
+use anyhow::{anyhow, Result};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+const SELECT_TUNNEL: &str = "SELECT id, project_id, name, host, kind, bind_address, bind_port, target_host, target_port, connection_id, auto_reconnect, created_at, updated_at FROM ssh_tunnels";
+
+fn validate(input: &SshTunnelInput) -> Result<()> {
+    if input.name.trim().is_empty() {
+        return Err(anyhow!("Tunnel name is required"));
+    }
+    if input.host.trim().is_empty() || input.host.starts_with('-') {
+        return Err(anyhow!("Tunnel needs a host from ~/.ssh/config"));
+    }
+    if input.bind_port == 0 {
+        return Err(anyhow!("Bind port must be between 1 and 65535"));
+    }
+    match input.kind.parse::<TunnelKind>()? {
+        TunnelKind::Dynamic => Ok(()),
+        TunnelKind::Local | TunnelKind::Remote => match (&input.target_host, input.target_port) {
+            (Some(host), Some(port)) if !host.trim().is_empty() && port != 0 => Ok(()),
+            _ => Err(anyhow!("Local and remote forwards need a target host and port")),
+        },
+    }
+}
+
+pub struct TunnelRepository {
+    pool: SqlitePool,
+}
+
+impl TunnelRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_tunnel(&self, project_id: String, input: SshTunnelInput) -> Result<SshTunnel> {
+        validate(&input)?;
+        let id = Uuid::new_v4().to_string();
+
+        sqlx::query("INSERT INTO ssh_tunnels (id, project_id, name, host, kind, bind_address, bind_port, target_host, target_port, connection_id, auto_reconnect) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(&id)
+            .bind(&project_id)
+            .bind(&input.name)
+            .bind(&input.host)
+            .bind(&input.kind)
+            .bind(&input.bind_address)
+            .bind(i64::from(input.bind_port))
+            .bind(&input.target_host)
+            .bind(input.target_port.map(i64::from))
+            .bind(&input.connection_id)
+            .bind(input.auto_reconnect)
+            .execute(&self.pool)
+            .await?;
+
+        self.get_tunnel(&id).await?.ok_or_else(|| anyhow!("Failed to retrieve created tunnel"))
+    }
+
+    pub async fn get_tunnel(&self, id: &str) -> Result<Option<SshTunnel>> {
+        let tunnel = sqlx::query_as::<_, SshTunnel>(&format!("{} WHERE id = ?", SELECT_TUNNEL))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(tunnel)
+    }
+
+    pub async fn get_tunnels(&self, project_id: &str) -> Result<Vec<SshTunnel>> {
+        let tunnels = sqlx::query_as::<_, SshTunnel>(&format!("{} WHERE project_id = ? ORDER BY created_at ASC", SELECT_TUNNEL))
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(tunnels)
+    }
+
+    /// Tunnels a database connection depends on.
+    pub async fn get_connection_tunnels(&self, connection_id: &str) -> Result<Vec<SshTunnel>> {
+        let tunnels = sqlx::query_as::<_, SshTunnel>(&format!("{} WHERE connection_id = ? ORDER BY created_at ASC", SELECT_TUNNEL))
+            .bind(connection_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(tunnels)
+    }
+
+    pub async fn update_tunnel(&self, id: &str, input: SshTunnelInput) -> Result<()> {
+        validate(&input)?;
+        sqlx::query("UPDATE ssh_tunnels SET name = ?, host = ?, kind = ?, bind_address = ?, bind_port = ?, target_host = ?, target_port = ?, connection_id = ?, auto_reconnect = ? WHERE id = ?")
+            .bind(input.name)
+            .bind(input.host)
+            .bind(input.kind)
+            .bind(input.bind_address)
+            .bind(i64::from(input.bind_port))
+            .bind(input.target_host)
+            .bind(input.target_port.map(i64::from))
+            .bind(input.connection_id)
+            .bind(input.auto_reconnect)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_tunnel(&self, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM ssh_tunnels WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -1,24 +1,37 @@
-use super::models::SshHostModel;
+use super::config_file::{self, SshConfigFile};
+use super::keys::KeyService;
+use super::models::{SshHostModel, SshTarget};
 use anyhow::{Context, Result};
-use ssh2_config::{ParseRule, SshConfig};
-use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(30);
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+// Tried in this order when a host has no IdentityFile, like ssh does
+const DEFAULT_IDENTITIES: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+#[derive(Clone)]
 pub struct SshService {
     config_path: PathBuf,
+    // Outside ~/.ssh, where an Include glob could read old copies back in
+    backup_dir: PathBuf,
 }
 
 impl SshService {
-    pub fn new() -> Self {
-        // Default to ~/.ssh/config
+    /// Edits ~/.ssh/config, keeping backups in `backup_dir`.
+    pub fn new(backup_dir: PathBuf) -> Self {
         let home = dirs::home_dir().expect("Could not find home directory");
         let config_path = home.join(".ssh").join("config");
-        Self { config_path }
+        Self::with_path(config_path, backup_dir)
     }
 
-    pub fn with_path(path: PathBuf) -> Self {
-        Self { config_path: path }
+    pub fn with_path(path: PathBuf, backup_dir: PathBuf) -> Self {
+        Self {
+            config_path: path,
+            backup_dir,
+        }
     }
 
     pub fn list_hosts(&self) -> Result<Vec<SshHostModel>> {
@@ -26,71 +39,73 @@ impl SshService {
             return Ok(vec![]);
         }
 
+        // Every concrete alias, including those from Included files, with ssh's
+        // first-one-wins resolution for the details
+        config_file::host_sources(&self.config_path)?
+            .into_iter()
+            .map(|(alias, _)| self.get_host(&alias))
+            .collect()
+    }
 
+    pub fn get_host(&self, alias: &str) -> Result<SshHostModel> {
+        let options = config_file::query(&self.config_path, alias)?;
+        Ok(SshHostModel {
+            host: alias.to_string(),
+            hostname: options.get("HostName").unwrap_or_default().to_string(),
+            user: options.get("User").map(str::to_string),
+            port: options.get("Port").and_then(|p| p.parse().ok()),
+            identity_file: options.get("IdentityFile").map(str::to_string),
+            proxy_jump: options.get("ProxyJump").map(str::to_string),
+            local_forwards: options.get_all("LocalForward").into_iter().map(str::to_string).collect(),
+        })
+    }
 
-        
-        // Parse the config to use for querying details
-        // Note: We need to re-open or clone for the parser since we will scan manually first?
-        // Actually, let's load logic:
-        // 1. Parse full config for querying (SshConfig)
-        // 2. Read lines to find Host definitions.
-        
-        // Let's re-open for parsing content
-        let mut reader_for_parse = BufReader::new(File::open(&self.config_path)?);
-        // Assuming ParseRule strict or default. If ParseRule::default() doesn't exist, try ParseRule::STRICT.
-        // Docs commonly suggest ParseRule::STRICT or similar.
-        // If unknown, we can try to rely on defaults or standard.
-        // Based on search "ParseRule::STRICT" exists.
-        let config = SshConfig::default().parse(&mut reader_for_parse, ParseRule::STRICT)?;
-        
-        // Now scan for "Host " lines to get keys
-        let reader_for_scan = BufReader::new(File::open(&self.config_path)?);
-        let mut hosts = Vec::new();
-        let mut seen_hosts = std::collections::HashSet::new();
-
-        for line in reader_for_scan.lines() {
-            let line = line?;
-            let trimmed = line.trim();
-            if trimmed.starts_with("Host ") {
-                let parts: Vec<&str> = trimmed.split_whitespace().collect();
-                // parts[0] is "Host"
-                for alias in &parts[1..] {
-                    if *alias == "*" { continue; } // Skip wildcard
-                    if seen_hosts.contains(&alias.to_string()) { continue; } // Dedup
-                    
-                    seen_hosts.insert(alias.to_string());
-                    
-                    // Query details
-                    let params = config.query(alias);
-                    
-                    let model = SshHostModel {
-                        host: alias.to_string(),
-                        hostname: params.host_name.unwrap_or_default(),
-                        user: params.user,
-                        port: params.port,
-                        // Fix: identity_file is Option<Vec<PathBuf>>, access inner vec then first item
-                        identity_file: params.identity_file.as_ref()
-                            .and_then(|files| files.first())
-                            .map(|p| p.to_string_lossy().to_string()),
-                    };
-                    hosts.push(model);
-                }
-            }
-        }
+    /// Connection parameters for an alias, with ssh's defaults filled in.
+    /// Unknown aliases resolve to themselves, like `ssh some-hostname` would.
+    pub fn resolve_host(&self, alias: &str) -> Result<SshTarget> {
+        let options = config_file::query(&self.config_path, alias)?;
+        let seconds = |keyword: &str| options.get(keyword).and_then(|v| v.parse::<u64>().ok()).map(Duration::from_secs);
+
+        let ssh_dir = self.config_path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let identity_files = match options.get_all("IdentityFile") {
+            files if !files.is_empty() => files.into_iter()
+                .map(|f| PathBuf::from(crate::shared::utils::expand_path(f)))
+                .collect(),
+            _ => DEFAULT_IDENTITIES.iter()
+                .map(|name| ssh_dir.join(name))
+                .filter(|path| path.exists())
+                .collect(),
+        };
 
-        Ok(hosts)
+        Ok(SshTarget {
+            alias: alias.to_string(),
+            hostname: options.get("HostName").unwrap_or(alias).to_string(),
+            port: options.get("Port").and_then(|p| p.parse().ok()).unwrap_or(22),
+            user: options.get("User").map(str::to_string)
+                .or_else(|| std::env::var("USER").ok())
+                .unwrap_or_else(|| "root".to_string()),
+            identity_files,
+            proxy_jump: options.get("ProxyJump").filter(|j| !j.eq_ignore_ascii_case("none")).map(str::to_string),
+            keepalive: seconds("ServerAliveInterval").filter(|d| !d.is_zero()).unwrap_or(DEFAULT_KEEPALIVE),
+            connect_timeout: seconds("ConnectTimeout").unwrap_or(DEFAULT_CONNECT_TIMEOUT),
+        })
+    }
+
+    /// The `known_hosts` file next to the config.
+    pub fn known_hosts_path(&self) -> PathBuf {
+        self.config_path.with_file_name("known_hosts")
     }
 
     pub fn add_host(&self, host: SshHostModel) -> Result<()> {
+        check_identity_file(&host)?;
+
         // 1. Backup
         if self.config_path.exists() {
-            let backup_path = self.config_path.with_extension("bak");
-            fs::copy(&self.config_path, &backup_path).context("Failed to create SSH config backup")?;
+            self.backup(&self.config_path)?;
         }
 
         // 2. Append
         let mut file = fs::OpenOptions::new()
-            .write(true)
             .append(true)
             .create(true)
             .open(&self.config_path)
@@ -107,14 +122,105 @@ impl SshService {
         if let Some(identity_file) = &host.identity_file {
             writeln!(file, "    IdentityFile {}", identity_file)?;
         }
+        if let Some(proxy_jump) = &host.proxy_jump {
+            writeln!(file, "    ProxyJump {}", proxy_jump)?;
+        }
+        for forward in &host.local_forwards {
+            writeln!(file, "    LocalForward {}", forward)?;
+        }
         
         Ok(())
     }
+
+    /// Rewrites the `Host` block that declares `alias`, in whichever file declares it.
+    /// Only fields that differ from the current values are touched, so comments,
+    /// ordering and directives we don't model stay as they are.
+    pub fn update_host(&self, alias: &str, host: SshHostModel) -> Result<()> {
+        let path = self.host_file(alias)?;
+        let current = self.get_host(alias)?;
+        let mut config = SshConfigFile::read(&path)?;
+        // Only a new key is checked, so hosts whose key has moved can still be edited
+        if current.identity_file != host.identity_file {
+            check_identity_file(&host)?;
+        }
+
+        if current.hostname != host.hostname {
+            config.set_option(alias, "HostName", Some(host.hostname.as_str()).filter(|h| !h.is_empty()))?;
+        }
+        if current.user != host.user {
+            config.set_option(alias, "User", host.user.as_deref())?;
+        }
+        if current.port != host.port {
+            config.set_option(alias, "Port", host.port.map(|p| p.to_string()).as_deref())?;
+        }
+        if current.identity_file != host.identity_file {
+            config.set_option(alias, "IdentityFile", host.identity_file.as_deref())?;
+        }
+        if current.proxy_jump != host.proxy_jump {
+            config.set_option(alias, "ProxyJump", host.proxy_jump.as_deref())?;
+        }
+        if current.local_forwards != host.local_forwards {
+            config.set_list(alias, "LocalForward", &host.local_forwards)?;
+        }
+        if host.host != alias {
+            if self.list_hosts()?.iter().any(|h| h.host == host.host) {
+                anyhow::bail!("Host {} already exists", host.host);
+            }
+            config.rename_host(alias, &host.host)?;
+        }
+
+        self.write_config(&path, &config)
+    }
+
+    pub fn delete_host(&self, alias: &str) -> Result<()> {
+        let path = self.host_file(alias)?;
+        let mut config = SshConfigFile::read(&path)?;
+        config.remove_host(alias)?;
+        self.write_config(&path, &config)
+    }
+
+    fn host_file(&self, alias: &str) -> Result<PathBuf> {
+        config_file::host_sources(&self.config_path)?
+            .into_iter()
+            .find(|(known, _)| known == alias)
+            .map(|(_, path)| path)
+            .ok_or_else(|| anyhow::anyhow!("Host {} not found in {}", alias, self.config_path.display()))
+    }
+
+    /// Copies a config file into the backup dir, named after its path below `~/.ssh`
+    /// (`conf.d/work.conf` becomes `conf.d__work.conf.bak`).
+    fn backup(&self, path: &Path) -> Result<PathBuf> {
+        let base = self.config_path.parent().unwrap_or(Path::new(""));
+        let name = path.strip_prefix(base).unwrap_or(path)
+            .iter()
+            .map(|part| part.to_string_lossy())
+            .filter(|part| part != "/")
+            .collect::<Vec<_>>()
+            .join("__");
+        let backup_path = self.backup_dir.join(format!("{}.bak", name));
+        fs::create_dir_all(&self.backup_dir).context("Failed to create SSH config backup")?;
+        fs::copy(path, &backup_path).context("Failed to create SSH config backup")?;
+        Ok(backup_path)
+    }
+
+    fn write_config(&self, path: &Path, config: &SshConfigFile) -> Result<()> {
+        self.backup(path)?;
+        fs::write(path, config.to_string()).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Fails unless the host's `IdentityFile` is a readable key.
+fn check_identity_file(host: &SshHostModel) -> Result<()> {
+    if let Some(identity_file) = &host.identity_file {
+        KeyService::new().inspect(Path::new(identity_file))?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs::File;
     use std::io::Write;
 
     #[test]
@@ -131,7 +237,7 @@ mod tests {
             writeln!(file, "    Port 2222")?;
         }
 
-        let service = SshService::with_path(config_path.clone());
+        let service = SshService::with_path(config_path.clone(), temp_dir.join("test_ssh_config_backups"));
 
         // Test List
         let hosts = service.list_hosts()?;
@@ -148,7 +254,14 @@ mod tests {
             user: Some("dev".to_string()),
             port: None,
             identity_file: Some("/path/to/key.pem".to_string()),
+            proxy_jump: None,
+            local_forwards: Vec::new(),
         };
+        // A missing key is refused before the config is touched
+        assert!(service.add_host(new_host.clone()).is_err());
+        assert!(!service.list_hosts()?.iter().any(|h| h.host == "new-server"));
+
+        let new_host = SshHostModel { identity_file: None, ..new_host };
         service.add_host(new_host.clone())?;
 
         // Verify content and parse again
@@ -157,4 +270,69 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_resolve_host_fills_defaults() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("switchboard-ssh-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir)?;
+        let config_path = dir.join("config");
+        fs::write(&config_path, "Host staging\n    HostName 10.1.2.3\n    User deploy\n    Port 2222\n    ServerAliveInterval 10\n    IdentityFile /keys/staging\n")?;
+        fs::write(dir.join("id_ed25519"), "")?;
+
+        let service = SshService::with_path(config_path, dir.join("backups"));
+        let staging = service.resolve_host("staging")?;
+        assert_eq!((staging.hostname.as_str(), staging.port, staging.user.as_str()), ("10.1.2.3", 2222, "deploy"));
+        assert_eq!(staging.identity_files, vec![PathBuf::from("/keys/staging")]);
+        assert_eq!(staging.keepalive, Duration::from_secs(10));
+
+        // Not in the config: connect to the name itself with the default key
+        let other = service.resolve_host("example.internal")?;
+        assert_eq!((other.hostname.as_str(), other.port), ("example.internal", 22));
+        assert_eq!(other.identity_files, vec![dir.join("id_ed25519")]);
+        assert_eq!(other.keepalive, DEFAULT_KEEPALIVE);
+        assert_eq!(service.known_hosts_path(), dir.join("known_hosts"));
+
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_and_delete_host() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("switchboard-ssh-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("conf.d"))?;
+        let main = "Include conf.d/*.conf\n\n# Production\nHost prod\n    HostName 10.0.0.1 # primary\n    User admin\n    Compression yes\n\nHost *\n    ServerAliveInterval 20\n";
+        let included = "Host lab\n  HostName lab.local\n  LocalForward 9000 localhost:9000\n";
+        fs::write(dir.join("config"), main)?;
+        fs::write(dir.join("conf.d").join("lab.conf"), included)?;
+        let service = SshService::with_path(dir.join("config"), dir.join("backups"));
+
+        // Saving a host back unchanged leaves the file byte-identical
+        let prod = service.get_host("prod")?;
+        service.update_host("prod", prod.clone())?;
+        assert_eq!(fs::read_to_string(dir.join("config"))?, main);
+
+        service.update_host("prod", SshHostModel { port: Some(2222), proxy_jump: Some("bastion".into()), ..prod })?;
+        assert_eq!(
+            fs::read_to_string(dir.join("config"))?,
+            main.replace("    Compression yes\n", "    Compression yes\n    Port 2222\n    ProxyJump bastion\n")
+        );
+
+        // Hosts from an Include are edited where they live
+        let lab = service.get_host("lab")?;
+        assert_eq!(lab.local_forwards, vec!["9000 localhost:9000"]);
+        service.update_host("lab", SshHostModel { host: "lab2".into(), local_forwards: Vec::new(), ..lab })?;
+        assert_eq!(fs::read_to_string(dir.join("conf.d").join("lab.conf"))?, "Host lab2\n  HostName lab.local\n");
+        // Backups stay out of the Include glob
+        assert_eq!(fs::read_dir(dir.join("conf.d"))?.count(), 1);
+        assert_eq!(fs::read_to_string(dir.join("backups").join("conf.d__lab.conf.bak"))?, included);
+        assert!(service.update_host("lab2", SshHostModel { host: "prod".into(), ..service.get_host("lab2")? }).is_err());
+
+        service.delete_host("prod")?;
+        assert_eq!(fs::read_to_string(dir.join("config"))?, "Include conf.d/*.conf\n\nHost *\n    ServerAliveInterval 20\n");
+        let hosts: Vec<String> = service.list_hosts()?.into_iter().map(|h| h.host).collect();
+        assert_eq!(hosts, vec!["lab2"]);
+
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
 }
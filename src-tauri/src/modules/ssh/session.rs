@@ -0,0 +1,418 @@
+use super::connection::{self, would_block};
+use super::models::{SshSessionInfo, SshSessionStatus, SshTarget};
+use super::service::SshService;
+use crate::shared::events::EventSink;
+use crate::shared::pty_stream::{stream_pty, StreamConfig};
+use crate::shared::utils::now_millis;
+use anyhow::{anyhow, Result};
+use ssh2::{Channel, Session};
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+// The session is non-blocking, so the I/O loop polls; short enough to feel instant when typing
+const IDLE_POLL: Duration = Duration::from_millis(10);
+const READ_BUFFER_BYTES: usize = 8 * 1024;
+
+/// 1s, 2s, 4s... capped at 30s.
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    RECONNECT_BASE_DELAY.saturating_mul(2u32.saturating_pow(attempt)).min(RECONNECT_MAX_DELAY)
+}
+
+enum Command {
+    Write(Vec<u8>),
+    Resize(u16, u16),
+    Close,
+}
+
+struct SessionHandle {
+    commands: Sender<Command>,
+    info: SshSessionInfo,
+}
+
+type SessionMap = Arc<Mutex<HashMap<String, SessionHandle>>>;
+
+/// Managed state for interactive SSH sessions to hosts from `~/.ssh/config`.
+/// Each session runs on its own thread, which owns the libssh2 session and channel.
+#[derive(Clone)]
+pub struct SshSessionService {
+    sessions: SessionMap,
+    ssh: SshService,
+}
+
+impl SshSessionService {
+    pub fn new(ssh: SshService) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            ssh,
+        }
+    }
+
+    /// Opens a shell on `host`. Blocks until connected, so host key and auth errors
+    /// come back from here; later drops are retried in the background.
+    pub fn connect<S: EventSink + Clone>(&self, sink: S, host: &str, cols: u16, rows: u16, password: Option<String>) -> Result<String> {
+        let target = self.ssh.resolve_host(host)?;
+        let known_hosts = self.ssh.known_hosts_path();
+        let (session, channel) = open_shell(&target, &known_hosts, password.as_deref(), (cols, rows))?;
+
+        let session_id = Uuid::new_v4().to_string();
+        let (commands_tx, commands_rx) = mpsc::channel();
+        let info = SshSessionInfo {
+            session_id: session_id.clone(),
+            host: host.to_string(),
+            status: SshSessionStatus::Connected,
+            cols,
+            rows,
+            connected_at: now_millis(),
+        };
+        self.sessions.lock().unwrap().insert(session_id.clone(), SessionHandle {
+            commands: commands_tx,
+            info,
+        });
+
+        let runner = SessionRunner {
+            session_id: session_id.clone(),
+            target,
+            known_hosts,
+            password,
+            size: (cols, rows),
+            commands: commands_rx,
+            sessions: self.sessions.clone(),
+            sink,
+        };
+        thread::spawn(move || runner.run(session, channel));
+
+        Ok(session_id)
+    }
+
+    fn send(&self, session_id: &str, command: Command) -> Result<()> {
+        let sessions = self.sessions.lock().unwrap();
+        let handle = sessions.get(session_id).ok_or_else(|| anyhow!("Session not found"))?;
+        handle.commands.send(command).map_err(|_| anyhow!("Session has ended"))
+    }
+
+    pub fn write(&self, session_id: &str, data: &str) -> Result<()> {
+        self.send(session_id, Command::Write(data.as_bytes().to_vec()))
+    }
+
+    pub fn resize(&self, session_id: &str, cols: u16, rows: u16) -> Result<()> {
+        self.send(session_id, Command::Resize(cols, rows))?;
+        if let Some(handle) = self.sessions.lock().unwrap().get_mut(session_id) {
+            handle.info.cols = cols;
+            handle.info.rows = rows;
+        }
+        Ok(())
+    }
+
+    /// Closes the channel. `ssh_exit` follows once the session thread is done.
+    pub fn disconnect(&self, session_id: &str) -> Result<()> {
+        self.send(session_id, Command::Close)
+    }
+
+    pub fn list(&self) -> Vec<SshSessionInfo> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut list: Vec<SshSessionInfo> = sessions.values().map(|h| h.info.clone()).collect();
+        list.sort_by_key(|info| info.connected_at);
+        list
+    }
+
+    /// Adds the host's key to `known_hosts`, after the user has checked its fingerprint.
+    pub fn trust_host(&self, host: &str, fingerprint: &str) -> Result<String> {
+        let target = self.ssh.resolve_host(host)?;
+        connection::trust_host_key(&target, &self.ssh.known_hosts_path(), fingerprint)
+    }
+}
+
+fn open_shell(target: &SshTarget, known_hosts: &std::path::Path, password: Option<&str>, size: (u16, u16)) -> Result<(Session, Channel)> {
+    let session = connection::connect(target, known_hosts, password)?;
+    session.set_keepalive(false, target.keepalive.as_secs().max(1) as u32);
+    let mut channel = session.channel_session()?;
+    channel.request_pty("xterm-256color", None, Some((size.0 as u32, size.1 as u32, 0, 0)))?;
+    channel.shell()?;
+    Ok((session, channel))
+}
+
+/// Feeds chunks from the I/O loop to the shared PTY streamer.
+struct ChunkReader {
+    chunks: Receiver<Vec<u8>>,
+    pending: Vec<u8>,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending.is_empty() {
+            match self.chunks.recv() {
+                Ok(chunk) => self.pending = chunk,
+                Err(_) => return Ok(0), // Session over
+            }
+        }
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+enum Outcome {
+    Exited(Option<i32>),
+    Closed,
+    Lost(String),
+}
+
+struct SessionRunner<S: EventSink + Clone> {
+    session_id: String,
+    target: SshTarget,
+    known_hosts: PathBuf,
+    password: Option<String>,
+    size: (u16, u16),
+    commands: Receiver<Command>,
+    sessions: SessionMap,
+    sink: S,
+}
+
+impl<S: EventSink + Clone> SessionRunner<S> {
+    fn run(mut self, session: Session, channel: Channel) {
+        let (chunks_tx, chunks_rx) = mpsc::sync_channel(64);
+        let data_sink = self.sink.clone();
+        let session_id = self.session_id.clone();
+        let output = stream_pty(ChunkReader { chunks: chunks_rx, pending: Vec::new() }, StreamConfig::default(), move |data| {
+            data_sink.emit_event("ssh_data", serde_json::json!({
+                "session_id": session_id,
+                "data": data
+            }));
+        });
+
+        let mut current = Some((session, channel));
+        let mut exit_status = None;
+        let mut reason = None;
+        while let Some((session, mut channel)) = current.take() {
+            match self.pump(&session, &mut channel, &chunks_tx) {
+                Outcome::Exited(code) => exit_status = code,
+                Outcome::Closed => {
+                    session.set_blocking(true);
+                    let _ = channel.close();
+                    let _ = session.disconnect(None, "Session closed", None);
+                }
+                Outcome::Lost(error) => {
+                    self.set_status(SshSessionStatus::Reconnecting, Some(&error));
+                    current = self.reconnect();
+                    match current {
+                        Some(_) => self.set_status(SshSessionStatus::Connected, None),
+                        None => reason = Some(error),
+                    }
+                }
+            }
+        }
+
+        drop(chunks_tx);
+        output.wait(Duration::from_millis(500));
+        self.sessions.lock().unwrap().remove(&self.session_id);
+        self.sink.emit_event("ssh_exit", serde_json::json!({
+            "session_id": self.session_id,
+            "exit_status": exit_status,
+            "reason": reason
+        }));
+    }
+
+    /// Shuttles data until the shell exits, the user closes it or the connection drops.
+    fn pump(&mut self, session: &Session, channel: &mut Channel, output: &SyncSender<Vec<u8>>) -> Outcome {
+        session.set_blocking(false);
+        let mut keepalive_due = Instant::now() + self.target.keepalive;
+        let mut buffer = [0u8; READ_BUFFER_BYTES];
+        loop {
+            let mut busy = false;
+            loop {
+                match self.commands.try_recv() {
+                    Ok(Command::Write(data)) => {
+                        if let Err(e) = write_all(channel, &data) {
+                            return Outcome::Lost(e.to_string());
+                        }
+                        busy = true;
+                    }
+                    Ok(Command::Resize(cols, rows)) => {
+                        self.size = (cols, rows);
+                        let _ = retry(|| channel.request_pty_size(cols as u32, rows as u32, None, None));
+                    }
+                    Ok(Command::Close) | Err(TryRecvError::Disconnected) => return Outcome::Closed,
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+
+            match channel.read(&mut buffer) {
+                Ok(0) if channel.eof() => return Outcome::Exited(channel.exit_status().ok()),
+                Ok(0) => {}
+                Ok(n) => {
+                    // Blocks while the streamer is behind, which is the backpressure we want
+                    if output.send(buffer[..n].to_vec()).is_err() {
+                        return Outcome::Closed;
+                    }
+                    busy = true;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Outcome::Lost(e.to_string()),
+            }
+
+            if Instant::now() >= keepalive_due {
+                match session.keepalive_send() {
+                    Ok(seconds) => keepalive_due = Instant::now() + Duration::from_secs(seconds.max(1) as u64),
+                    Err(e) if would_block(&e) => {}
+                    Err(e) => return Outcome::Lost(e.to_string()),
+                }
+            }
+
+            if !busy {
+                thread::sleep(IDLE_POLL);
+            }
+        }
+    }
+
+    /// Opens a fresh shell with backoff. Gives up after a few attempts or when the user closes
+    /// the session meanwhile; input typed while disconnected is dropped.
+    fn reconnect(&mut self) -> Option<(Session, Channel)> {
+        for attempt in 0..MAX_RECONNECT_ATTEMPTS {
+            let deadline = Instant::now() + reconnect_delay(attempt);
+            while let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+                match self.commands.recv_timeout(wait) {
+                    Ok(Command::Close) | Err(RecvTimeoutError::Disconnected) => return None,
+                    Ok(Command::Resize(cols, rows)) => self.size = (cols, rows),
+                    Ok(Command::Write(_)) => {}
+                    Err(RecvTimeoutError::Timeout) => break,
+                }
+            }
+            match open_shell(&self.target, &self.known_hosts, self.password.as_deref(), self.size) {
+                Ok(shell) => return Some(shell),
+                // A changed host key won't fix itself by retrying
+                Err(e) if connection::is_host_key_changed(&e) => {
+                    self.set_status(SshSessionStatus::Reconnecting, Some(&e.to_string()));
+                    return None;
+                }
+                Err(e) => self.set_status(SshSessionStatus::Reconnecting, Some(&e.to_string())),
+            }
+        }
+        None
+    }
+
+    fn set_status(&self, status: SshSessionStatus, message: Option<&str>) {
+        if let Some(handle) = self.sessions.lock().unwrap().get_mut(&self.session_id) {
+            handle.info.status = status;
+        }
+        self.sink.emit_event("ssh_status", serde_json::json!({
+            "session_id": self.session_id,
+            "status": status,
+            "message": message
+        }));
+    }
+}
+
+/// Retries a libssh2 call until it stops returning EAGAIN.
+fn retry<T>(mut f: impl FnMut() -> std::result::Result<T, ssh2::Error>) -> std::result::Result<T, ssh2::Error> {
+    loop {
+        match f() {
+            Err(e) if would_block(&e) => thread::sleep(Duration::from_millis(1)),
+            result => return result,
+        }
+    }
+}
+
+fn write_all(channel: &mut Channel, mut data: &[u8]) -> std::io::Result<()> {
+    while !data.is_empty() {
+        match channel.write(data) {
+            Ok(n) => data = &data[n..],
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(1)),
+            Err(e) => return Err(e),
+        }
+    }
+    loop {
+        match channel.flush() {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(1)),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_delay_backs_off() {
+        assert_eq!(reconnect_delay(0), Duration::from_secs(1));
+        assert_eq!(reconnect_delay(3), Duration::from_secs(8));
+        assert_eq!(reconnect_delay(10), RECONNECT_MAX_DELAY);
+    }
+
+    /// Needs a reachable sshd, e.g. `docker run -d -p 2222:2222 -e PASSWORD_ACCESS=true
+    /// -e USER_PASSWORD=secret -e USER_NAME=dev lscr.io/linuxserver/openssh-server`, then
+    /// `SWITCHBOARD_TEST_SSH=dev:secret@127.0.0.1:2222 cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_session_against_local_sshd() -> Result<()> {
+        let spec = std::env::var("SWITCHBOARD_TEST_SSH")?;
+        let (credentials, address) = spec.split_once('@').ok_or_else(|| anyhow!("expected user:password@host:port"))?;
+        let (user, password) = credentials.split_once(':').ok_or_else(|| anyhow!("expected user:password"))?;
+        let (hostname, port) = address.split_once(':').ok_or_else(|| anyhow!("expected host:port"))?;
+
+        let dir = std::env::temp_dir().join(format!("switchboard-ssh-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("config"), format!("Host test-sshd\n    HostName {}\n    Port {}\n    User {}\n", hostname, port, user))?;
+
+        #[derive(Default)]
+        struct RecordingSink {
+            output: Mutex<String>,
+            exits: Mutex<Vec<serde_json::Value>>,
+        }
+
+        impl EventSink for RecordingSink {
+            fn emit_event(&self, event: &str, payload: serde_json::Value) {
+                match event {
+                    "ssh_data" => self.output.lock().unwrap().push_str(payload["data"].as_str().unwrap_or_default()),
+                    "ssh_exit" => self.exits.lock().unwrap().push(payload),
+                    _ => {}
+                }
+            }
+        }
+
+        let service = SshSessionService::new(SshService::with_path(dir.join("config"), dir.join("backups")));
+        let sink = Arc::new(RecordingSink::default());
+
+        // Unknown until trusted
+        let err = service.connect(sink.clone(), "test-sshd", 80, 24, Some(password.into())).expect_err("unknown host key");
+        let Some(connection::HostKeyError::Unknown { fingerprint, .. }) = err.downcast_ref() else {
+            panic!("expected an unknown host key, got {}", err);
+        };
+        // Only the fingerprint the user saw gets trusted
+        assert!(service.trust_host("test-sshd", "SHA256:somethingelse").is_err());
+        assert!(!dir.join("known_hosts").exists());
+        assert_eq!(&service.trust_host("test-sshd", fingerprint)?, fingerprint);
+
+        let session_id = service.connect(sink.clone(), "test-sshd", 80, 24, Some(password.into()))?;
+        service.resize(&session_id, 100, 30)?;
+        service.write(&session_id, "stty size; echo remote-$((40 + 2))\n")?;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !sink.output.lock().unwrap().contains("remote-42") && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        let output = sink.output.lock().unwrap().clone();
+        assert!(output.contains("30 100"));
+        assert!(output.contains("remote-42"));
+
+        service.write(&session_id, "exit 7\n")?;
+        while sink.exits.lock().unwrap().is_empty() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(sink.exits.lock().unwrap()[0]["exit_status"], 7);
+        assert!(service.list().is_empty());
+
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+use super::connection;
+use super::models::{SftpEntry, SftpEntryKind, SftpProgress, TransferDirection};
+use super::service::SshService;
+use crate::shared::events::EventSink;
+use anyhow::{anyhow, bail, Context, Result};
+use ssh2::{ErrorCode, FileStat, RenameFlags, Session, Sftp};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TRANSFER_BUFFER_BYTES: usize = 64 * 1024;
+// Often enough for a smooth progress bar without flooding the webview
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_DIR_MODE: i32 = 0o755;
+
+// SSH_FX_* status codes from the SFTP protocol
+const FX_NO_SUCH_FILE: i32 = 2;
+const FX_PERMISSION_DENIED: i32 = 3;
+const FX_FAILURE: i32 = 4;
+const FX_FILE_ALREADY_EXISTS: i32 = 11;
+
+/// A remote path bookmarked as an `sftp` project link: `sftp://<alias>/<path>`, where
+/// the alias is a host from `~/.ssh/config` and an empty path means the login directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SftpUrl {
+    pub host: String,
+    pub path: String,
+}
+
+impl SftpUrl {
+    pub fn parse(url: &str) -> Result<Self> {
+        let rest = url.trim().strip_prefix("sftp://")
+            .ok_or_else(|| anyhow!("SFTP links look like sftp://<host>/<path>"))?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, ""),
+        };
+        if host.is_empty() || host.starts_with('-') || host.contains(char::is_whitespace) {
+            bail!("SFTP links need a host from ~/.ssh/config");
+        }
+        // "/~/logs" is relative to the login directory
+        let path = match path.strip_prefix("/~") {
+            Some(home) => format!("~{}", home),
+            None => path.to_string(),
+        };
+        Ok(Self { host: host.to_string(), path })
+    }
+}
+
+impl std::fmt::Display for SftpUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path.strip_prefix('~') {
+            Some(home) => write!(f, "sftp://{}/~{}", self.host, home),
+            None => write!(f, "sftp://{}{}", self.host, self.path),
+        }
+    }
+}
+
+fn entry_kind(stat: &FileStat) -> SftpEntryKind {
+    let file_type = stat.file_type();
+    if file_type.is_symlink() {
+        SftpEntryKind::Symlink
+    } else if file_type.is_dir() {
+        SftpEntryKind::Dir
+    } else if file_type.is_file() {
+        SftpEntryKind::File
+    } else {
+        SftpEntryKind::Other
+    }
+}
+
+fn to_entry(path: &Path, stat: &FileStat) -> SftpEntry {
+    SftpEntry {
+        name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "/".into()),
+        path: path.to_string_lossy().to_string(),
+        kind: entry_kind(stat),
+        size: stat.size,
+        modified: stat.mtime,
+        permissions: stat.perm.map(|p| p & 0o7777),
+    }
+}
+
+/// Whether the error came from the connection rather than from the SFTP server,
+/// i.e. whether reconnecting could help.
+fn is_connection_error(e: &ssh2::Error) -> bool {
+    matches!(e.code(), ErrorCode::Session(_))
+}
+
+/// `failure_hint` explains a bare SSH_FX_FAILURE, which servers answer for all sorts of things.
+fn describe(e: ssh2::Error, path: &str, failure_hint: Option<&str>) -> anyhow::Error {
+    match e.code() {
+        ErrorCode::SFTP(FX_NO_SUCH_FILE) => anyhow!("{}: no such file or directory", path),
+        ErrorCode::SFTP(FX_PERMISSION_DENIED) => anyhow!("{}: permission denied", path),
+        ErrorCode::SFTP(FX_FILE_ALREADY_EXISTS) => anyhow!("{}: already exists", path),
+        ErrorCode::SFTP(FX_FAILURE) => match failure_hint {
+            Some(hint) => anyhow!("{}: operation failed ({})", path, hint),
+            None => anyhow!("{}: operation failed", path),
+        },
+        _ => anyhow!("{}: {}", path, e),
+    }
+}
+
+/// Copies `reader` into `writer`, calling `on_progress` with the running total at most
+/// every `PROGRESS_INTERVAL`. Returns the number of bytes copied.
+pub fn copy_with_progress(reader: &mut impl Read, writer: &mut impl Write, mut on_progress: impl FnMut(u64)) -> io::Result<u64> {
+    let mut buffer = vec![0u8; TRANSFER_BUFFER_BYTES];
+    let mut transferred = 0u64;
+    let mut last_report = Instant::now();
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buffer[..n])?;
+        transferred += n as u64;
+        if last_report.elapsed() >= PROGRESS_INTERVAL {
+            on_progress(transferred);
+            last_report = Instant::now();
+        }
+    }
+    writer.flush()?;
+    Ok(transferred)
+}
+
+struct SftpConnection {
+    // The Sftp handle borrows the session's connection, keep it alive alongside
+    _session: Session,
+    sftp: Sftp,
+}
+
+/// Managed state for SFTP on hosts from `~/.ssh/config`. Browsing reuses one connection
+/// per host; transfers get their own so a large download doesn't block the listing.
+#[derive(Clone)]
+pub struct SftpService {
+    connections: Arc<Mutex<HashMap<String, Arc<Mutex<SftpConnection>>>>>,
+    ssh: SshService,
+}
+
+impl SftpService {
+    pub fn new(ssh: SshService) -> Self {
+        Self {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            ssh,
+        }
+    }
+
+    fn open(&self, host: &str, password: Option<&str>) -> Result<SftpConnection> {
+        let target = self.ssh.resolve_host(host)?;
+        let session = connection::connect(&target, &self.ssh.known_hosts_path(), password)?;
+        let sftp = session.sftp().with_context(|| format!("{} does not offer the SFTP subsystem", host))?;
+        Ok(SftpConnection { _session: session, sftp })
+    }
+
+    fn cached(&self, host: &str, password: Option<&str>) -> Result<Arc<Mutex<SftpConnection>>> {
+        if let Some(connection) = self.connections.lock().unwrap().get(host) {
+            return Ok(connection.clone());
+        }
+        // Connect outside the map lock, other hosts shouldn't wait on this one
+        let connection = Arc::new(Mutex::new(self.open(host, password)?));
+        self.connections.lock().unwrap().insert(host.to_string(), connection.clone());
+        Ok(connection)
+    }
+
+    /// Runs `f` on the host's cached connection, reconnecting once if it has dropped.
+    fn with_sftp<T>(&self, host: &str, password: Option<&str>, path: &str, f: impl Fn(&Sftp) -> Result<T, ssh2::Error>) -> Result<T> {
+        self.with_sftp_hinted(host, password, path, None, f)
+    }
+
+    fn with_sftp_hinted<T>(&self, host: &str, password: Option<&str>, path: &str, failure_hint: Option<&str>, f: impl Fn(&Sftp) -> Result<T, ssh2::Error>) -> Result<T> {
+        let connection = self.cached(host, password)?;
+        let result = f(&connection.lock().unwrap().sftp);
+        match result {
+            Err(e) if is_connection_error(&e) => {
+                self.connections.lock().unwrap().remove(host);
+                let connection = self.cached(host, password)?;
+                let result = f(&connection.lock().unwrap().sftp);
+                result.map_err(|e| describe(e, path, failure_hint))
+            }
+            result => result.map_err(|e| describe(e, path, failure_hint)),
+        }
+    }
+
+    /// Resolves `~`, `~/...` and an empty path against the login directory.
+    fn rename_path(sftp: &Sftp, from: &Path, to: &Path, overwrite: bool) -> Result<(), ssh2::Error> {
+        let mut flags = RenameFlags::ATOMIC | RenameFlags::NATIVE;
+        if overwrite {
+            flags |= RenameFlags::OVERWRITE;
+        }
+        match sftp.rename(from, to, Some(flags)) {
+            // SFTP v3 servers like OpenSSH ignore the flags and never replace
+            Err(_) if overwrite && sftp.lstat(to).is_ok_and(|stat| !stat.file_type().is_dir()) => {
+                sftp.unlink(to)?;
+                sftp.rename(from, to, Some(flags))
+            }
+            result => result,
+        }
+    }
+
+    fn resolve(sftp: &Sftp, path: &str) -> Result<PathBuf, ssh2::Error> {
+        match path {
+            "" | "~" => sftp.realpath(Path::new(".")),
+            _ => match path.strip_prefix("~/") {
+                Some(rest) => Ok(sftp.realpath(Path::new("."))?.join(rest)),
+                None => Ok(PathBuf::from(path)),
+            },
+        }
+    }
+
+    /// Directory contents, directories first, then by name.
+    pub fn list_dir(&self, host: &str, path: &str, password: Option<&str>) -> Result<Vec<SftpEntry>> {
+        let mut entries: Vec<SftpEntry> = self.with_sftp(host, password, path, |sftp| {
+            let dir = Self::resolve(sftp, path)?;
+            sftp.readdir(&dir)
+        })?
+            .iter()
+            .map(|(path, stat)| to_entry(path, stat))
+            .filter(|e| e.name != "." && e.name != "..")
+            .collect();
+        entries.sort_by(|a, b| (a.kind != SftpEntryKind::Dir, &a.name).cmp(&(b.kind != SftpEntryKind::Dir, &b.name)));
+        Ok(entries)
+    }
+
+    /// Follows symlinks; the returned path is absolute.
+    pub fn stat(&self, host: &str, path: &str, password: Option<&str>) -> Result<SftpEntry> {
+        let (resolved, stat) = self.with_sftp(host, password, path, |sftp| {
+            let resolved = Self::resolve(sftp, path)?;
+            let stat = sftp.stat(&resolved)?;
+            Ok((resolved, stat))
+        })?;
+        Ok(to_entry(&resolved, &stat))
+    }
+
+    /// Moves `from` to `to`. An existing `to` is only replaced when `overwrite` is set.
+    pub fn rename(&self, host: &str, from: &str, to: &str, overwrite: bool, password: Option<&str>) -> Result<()> {
+        self.with_sftp(host, password, from, |sftp| {
+            let from = Self::resolve(sftp, from)?;
+            let to = Self::resolve(sftp, to)?;
+            Self::rename_path(sftp, &from, &to, overwrite)
+        })
+    }
+
+    /// Removes a file, a symlink or an empty directory.
+    pub fn delete(&self, host: &str, path: &str, password: Option<&str>) -> Result<()> {
+        // Most servers answer a plain failure for rmdir on a directory with files in it
+        self.with_sftp_hinted(host, password, path, Some("directories must be empty to delete"), |sftp| {
+            let resolved = Self::resolve(sftp, path)?;
+            if sftp.lstat(&resolved)?.file_type().is_dir() {
+                sftp.rmdir(&resolved)
+            } else {
+                sftp.unlink(&resolved)
+            }
+        })
+    }
+
+    pub fn mkdir(&self, host: &str, path: &str, password: Option<&str>) -> Result<()> {
+        self.with_sftp(host, password, path, |sftp| {
+            let resolved = Self::resolve(sftp, path)?;
+            sftp.mkdir(&resolved, DEFAULT_DIR_MODE)
+        })
+    }
+
+    /// Drops the cached browsing connection for a host.
+    pub fn disconnect(&self, host: &str) {
+        self.connections.lock().unwrap().remove(host);
+    }
+
+    /// Downloads into `local_path`, through a `.part` file so an interrupted download
+    /// never leaves a truncated file under the real name.
+    pub fn download<S: EventSink>(&self, sink: &S, transfer_id: &str, host: &str, remote_path: &str, local_path: &Path, password: Option<&str>) -> Result<u64> {
+        let connection = self.open(host, password)?;
+        let resolved = Self::resolve(&connection.sftp, remote_path).map_err(|e| describe(e, remote_path, None))?;
+        let remote = resolved.to_string_lossy().to_string();
+        let mut file = connection.sftp.open(&resolved).map_err(|e| describe(e, &remote, None))?;
+        let total = file.stat().ok().and_then(|s| s.size);
+
+        let progress = |transferred, done| SftpProgress {
+            transfer_id: transfer_id.to_string(),
+            host: host.to_string(),
+            direction: TransferDirection::Download,
+            remote_path: remote.clone(),
+            transferred,
+            total,
+            done,
+        };
+        let emit = |p: SftpProgress| sink.emit_event("sftp_progress", serde_json::to_value(p).unwrap_or_default());
+
+        let partial = PathBuf::from(format!("{}.part", local_path.display()));
+        let copied = fs::File::create(&partial)
+            .with_context(|| format!("Could not create {}", partial.display()))
+            .and_then(|mut out| {
+                emit(progress(0, false));
+                Ok(copy_with_progress(&mut file, &mut out, |n| emit(progress(n, false)))?)
+            });
+        let copied = match copied {
+            Ok(n) => n,
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                return Err(e.context(format!("Download of {} failed", remote)));
+            }
+        };
+        fs::rename(&partial, local_path)?;
+        emit(progress(copied, true));
+        Ok(copied)
+    }
+
+    /// Uploads `local_path`, replacing `remote_path` if it exists.
+    pub fn upload<S: EventSink>(&self, sink: &S, transfer_id: &str, host: &str, local_path: &Path, remote_path: &str, password: Option<&str>) -> Result<u64> {
+        let mut file = fs::File::open(local_path)
+            .with_context(|| format!("Could not open {}", local_path.display()))?;
+        let total = file.metadata().ok().map(|m| m.len());
+
+        let connection = self.open(host, password)?;
+        let resolved = Self::resolve(&connection.sftp, remote_path).map_err(|e| describe(e, remote_path, None))?;
+        let remote = resolved.to_string_lossy().to_string();
+
+        let progress = |transferred, done| SftpProgress {
+            transfer_id: transfer_id.to_string(),
+            host: host.to_string(),
+            direction: TransferDirection::Upload,
+            remote_path: remote.clone(),
+            transferred,
+            total,
+            done,
+        };
+        let emit = |p: SftpProgress| sink.emit_event("sftp_progress", serde_json::to_value(p).unwrap_or_default());
+
+        // The target is only replaced once the whole file is there
+        let sftp = &connection.sftp;
+        let partial = PathBuf::from(format!("{}.part", resolved.display()));
+        let copied = sftp.create(&partial)
+            .map_err(|e| describe(e, &remote, None))
+            .and_then(|mut out| {
+                emit(progress(0, false));
+                copy_with_progress(&mut file, &mut out, |n| emit(progress(n, false)))
+                    .with_context(|| format!("Upload to {} failed", remote))
+            })
+            .and_then(|copied| {
+                Self::rename_path(sftp, &partial, &resolved, true).map_err(|e| describe(e, &remote, None))?;
+                Ok(copied)
+            });
+        let copied = match copied {
+            Ok(n) => n,
+            Err(e) => {
+                let _ = sftp.unlink(&partial);
+                return Err(e);
+            }
+        };
+        emit(progress(copied, true));
+        Ok(copied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sftp_url_round_trip() -> Result<()> {
+        let url = SftpUrl::parse("sftp://prod-web/var/log/nginx")?;
+        assert_eq!(url, SftpUrl { host: "prod-web".into(), path: "/var/log/nginx".into() });
+        assert_eq!(url.to_string(), "sftp://prod-web/var/log/nginx");
+
+        let home = SftpUrl::parse("sftp://staging/~/app/config")?;
+        assert_eq!(home.path, "~/app/config");
+        assert_eq!(home.to_string(), "sftp://staging/~/app/config");
+        assert_eq!(SftpUrl::parse("sftp://staging")?.path, "");
+
+        assert!(SftpUrl::parse("https://staging/x").is_err());
+        assert!(SftpUrl::parse("sftp:///etc").is_err());
+        assert!(SftpUrl::parse("sftp://-oProxyCommand=x/etc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_with_progress() -> Result<()> {
+        // Reads that trickle in slower than the progress interval
+        struct Slow(io::Cursor<Vec<u8>>);
+        impl Read for Slow {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                std::thread::sleep(PROGRESS_INTERVAL);
+                let n = buf.len().min(TRANSFER_BUFFER_BYTES / 2);
+                self.0.read(&mut buf[..n])
+            }
+        }
+
+        let data: Vec<u8> = (0..TRANSFER_BUFFER_BYTES * 2).map(|i| i as u8).collect();
+        let mut out = Vec::new();
+        let mut reports = Vec::new();
+        let copied = copy_with_progress(&mut Slow(io::Cursor::new(data.clone())), &mut out, |n| reports.push(n))?;
+
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(out, data);
+        assert!(reports.len() >= 3);
+        assert!(reports.windows(2).all(|w| w[0] < w[1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_failure_hint_only_where_given() {
+        let failure = || ssh2::Error::new(ErrorCode::SFTP(FX_FAILURE), "failure");
+        assert_eq!(describe(failure(), "/srv/app", None).to_string(), "/srv/app: operation failed");
+        assert_eq!(
+            describe(failure(), "/srv/app", Some("directories must be empty to delete")).to_string(),
+            "/srv/app: operation failed (directories must be empty to delete)"
+        );
+        let missing = ssh2::Error::new(ErrorCode::SFTP(FX_NO_SUCH_FILE), "no such file");
+        assert_eq!(describe(missing, "/srv/app", Some("ignored")).to_string(), "/srv/app: no such file or directory");
+    }
+}
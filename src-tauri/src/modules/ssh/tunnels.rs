@@ -0,0 +1,563 @@
+use super::models::{SshTunnel, TunnelKind, TunnelState, TunnelStatus};
+use super::repository::TunnelRepository;
+use crate::shared::events::EventSink;
+use anyhow::{anyhow, Context, Result};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// A tunnel that stays up this long resets the backoff
+const STABLE_AFTER: Duration = Duration::from_secs(30);
+// Remote forwards can't be probed from here; ssh exits on a refused forward well before this
+const REMOTE_UP_AFTER: Duration = Duration::from_secs(3);
+const PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+struct RunningTunnel {
+    tunnel: SshTunnel,
+    child: Option<Child>,
+    last_error: Arc<Mutex<Option<String>>>, // Last stderr line of the current run
+    status: TunnelStatus,
+    message: Option<String>,
+    started: Instant,
+    attempts: u32,
+    next_start: Option<Instant>,
+    run: u64, // Changes with every spawn, so late results of an older one are dropped
+}
+
+impl RunningTunnel {
+    /// Running, about to run or waiting to reconnect, so starting again would add a second ssh.
+    fn busy(&self) -> bool {
+        self.child.is_some() || self.next_start.is_some() || self.status == TunnelStatus::Starting
+    }
+
+    fn state(&self) -> TunnelState {
+        TunnelState {
+            tunnel_id: self.tunnel.id.clone(),
+            status: self.status,
+            message: self.message.clone(),
+            pid: self.child.as_ref().map(|c| c.id()),
+            attempts: self.attempts,
+        }
+    }
+}
+
+fn with_brackets(host: &str) -> String {
+    if host.contains(':') { format!("[{}]", host) } else { host.to_string() }
+}
+
+/// The `-L`/`-R`/`-D` argument for a tunnel.
+pub fn forward_spec(tunnel: &SshTunnel) -> Result<String> {
+    let bind = match tunnel.bind_address.as_deref().filter(|b| !b.is_empty()) {
+        Some(address) => format!("{}:{}", with_brackets(address), tunnel.bind_port),
+        None => tunnel.bind_port.to_string(),
+    };
+    match tunnel.tunnel_kind() {
+        TunnelKind::Dynamic => Ok(bind),
+        TunnelKind::Local | TunnelKind::Remote => {
+            let host = tunnel.target_host.as_deref().ok_or_else(|| anyhow!("Tunnel {} has no target host", tunnel.name))?;
+            let port = tunnel.target_port.ok_or_else(|| anyhow!("Tunnel {} has no target port", tunnel.name))?;
+            Ok(format!("{}:{}:{}", bind, with_brackets(host), port))
+        }
+    }
+}
+
+/// Arguments for a forward-only `ssh` that never prompts and exits when the
+/// forward can't be set up, so a failure shows up as an exit instead of a hang.
+pub fn ssh_args(tunnel: &SshTunnel) -> Result<Vec<String>> {
+    let flag = match tunnel.tunnel_kind() {
+        TunnelKind::Local => "-L",
+        TunnelKind::Remote => "-R",
+        TunnelKind::Dynamic => "-D",
+    };
+    let mut args: Vec<String> = ["-N", "-T", "-o", "BatchMode=yes", "-o", "ExitOnForwardFailure=yes", "-o", "ServerAliveInterval=15", "-o", "ServerAliveCountMax=3"]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+    args.push(flag.to_string());
+    args.push(forward_spec(tunnel)?);
+    args.push("--".to_string());
+    args.push(tunnel.host.clone());
+    Ok(args)
+}
+
+/// Whether something accepts connections on the local end of a tunnel.
+fn local_end_open(tunnel: &SshTunnel) -> bool {
+    let address = match tunnel.bind_address.as_deref() {
+        None | Some("") | Some("*") | Some("0.0.0.0") | Some("localhost") => "127.0.0.1",
+        Some("::") => "::1",
+        Some(address) => address,
+    };
+    (address, tunnel.bind_port as u16).to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .is_some_and(|address| TcpStream::connect_timeout(&address, PROBE_TIMEOUT).is_ok())
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))).min(MAX_BACKOFF)
+}
+
+/// Runs saved tunnels as `ssh -N` children, watches them and reconnects them
+/// with backoff. Status changes go out as `tunnel_status` events.
+#[derive(Clone)]
+pub struct TunnelManager {
+    pool: SqlitePool,
+    sink: Arc<dyn EventSink>,
+    program: String,
+    running: Arc<Mutex<HashMap<String, RunningTunnel>>>, // Keyed by tunnel id
+    runs: Arc<AtomicU64>,
+}
+
+impl TunnelManager {
+    pub fn new(pool: SqlitePool, sink: Arc<dyn EventSink>) -> Self {
+        Self {
+            pool,
+            sink,
+            program: "ssh".to_string(),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            runs: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Uses another ssh binary, e.g. a stub in tests.
+    pub fn with_program(mut self, program: impl Into<String>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.tick_blocking().await;
+        }
+    }
+
+    /// Spawning ssh and probing ports block, so ticks stay off the async workers.
+    async fn tick_blocking(&self) {
+        let manager = self.clone();
+        let _ = tokio::task::spawn_blocking(move || manager.tick()).await;
+    }
+
+    /// Starts a tunnel, or returns its state if it's already running or waiting to reconnect.
+    pub async fn start(&self, tunnel_id: &str) -> Result<TunnelState> {
+        if let Some(entry) = self.running.lock().unwrap().get(tunnel_id) {
+            if entry.busy() {
+                return Ok(entry.state());
+            }
+        }
+        // Read the definition fresh, it may have been edited since the last run
+        let tunnel = TunnelRepository::new(self.pool.clone())
+            .get_tunnel(tunnel_id)
+            .await?
+            .context("Tunnel not found")?;
+
+        // Claim the tunnel before spawning, so a start racing this one waits on it
+        let run = {
+            let mut running = self.running.lock().unwrap();
+            if let Some(entry) = running.get(tunnel_id).filter(|e| e.busy()) {
+                return Ok(entry.state());
+            }
+            let run = self.runs.fetch_add(1, Ordering::Relaxed);
+            running.insert(tunnel_id.to_string(), RunningTunnel {
+                tunnel: tunnel.clone(),
+                child: None,
+                last_error: Arc::new(Mutex::new(None)),
+                status: TunnelStatus::Starting,
+                message: None,
+                started: Instant::now(),
+                attempts: 0,
+                next_start: None,
+                run,
+            });
+            run
+        };
+
+        let manager = self.clone();
+        let id = tunnel_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            let launched = manager.launch(&id, &tunnel, run);
+            if launched.is_err() {
+                let mut running = manager.running.lock().unwrap();
+                if running.get(&id).is_some_and(|e| e.run == run) {
+                    running.remove(&id);
+                }
+            }
+            // None if the tunnel was stopped meanwhile
+            launched.map(|state| state.unwrap_or_else(|| manager.state(&id)))
+        })
+        .await?
+    }
+
+    pub fn stop(&self, tunnel_id: &str) -> Result<()> {
+        let entry = self.running.lock().unwrap().remove(tunnel_id);
+        if let Some(mut entry) = entry {
+            if let Some(mut child) = entry.child.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            entry.status = TunnelStatus::Stopped;
+            entry.message = None;
+            self.emit(&entry);
+        }
+        Ok(())
+    }
+
+    /// Kills every tunnel, used when the app exits.
+    pub fn stop_all(&self) {
+        let ids: Vec<String> = self.running.lock().unwrap().keys().cloned().collect();
+        for id in ids {
+            let _ = self.stop(&id);
+        }
+    }
+
+    pub fn is_running(&self, tunnel_id: &str) -> bool {
+        self.running.lock().unwrap().contains_key(tunnel_id)
+    }
+
+    pub fn state(&self, tunnel_id: &str) -> TunnelState {
+        self.running.lock().unwrap()
+            .get(tunnel_id)
+            .map(RunningTunnel::state)
+            .unwrap_or_else(|| TunnelState {
+                tunnel_id: tunnel_id.to_string(),
+                status: TunnelStatus::Stopped,
+                message: None,
+                pid: None,
+                attempts: 0,
+            })
+    }
+
+    /// State of every saved tunnel of a project, stopped ones included.
+    pub async fn project_states(&self, project_id: &str) -> Result<Vec<TunnelState>> {
+        let tunnels = TunnelRepository::new(self.pool.clone()).get_tunnels(project_id).await?;
+        Ok(tunnels.iter().map(|t| self.state(&t.id)).collect())
+    }
+
+    /// Brings up the tunnels a database connection depends on and waits until
+    /// they accept connections, so the connect that follows goes through them.
+    pub async fn start_for_connection(&self, connection_id: &str, timeout: Duration) -> Result<Vec<TunnelState>> {
+        let tunnels = TunnelRepository::new(self.pool.clone()).get_connection_tunnels(connection_id).await?;
+        for tunnel in &tunnels {
+            self.start(&tunnel.id).await?;
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.tick_blocking().await;
+            let states: Vec<TunnelState> = tunnels.iter().map(|t| self.state(&t.id)).collect();
+            if states.iter().all(|s| s.status == TunnelStatus::Up) {
+                return Ok(states);
+            }
+            let stuck = tunnels.iter().zip(&states)
+                .find(|(_, s)| s.status != TunnelStatus::Up && (s.status == TunnelStatus::Failed || Instant::now() >= deadline));
+            if let Some((tunnel, state)) = stuck {
+                return Err(anyhow!(
+                    "Tunnel {} is not up{}",
+                    tunnel.name,
+                    state.message.as_deref().map(|m| format!(": {}", m)).unwrap_or_default()
+                ));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    fn spawn(&self, tunnel: &SshTunnel) -> Result<(Child, Arc<Mutex<Option<String>>>)> {
+        // Something else on the port would pass for the forward being up
+        if tunnel.tunnel_kind() != TunnelKind::Remote && local_end_open(tunnel) {
+            return Err(anyhow!("Port {} is already in use", tunnel.bind_port));
+        }
+        let mut child = Command::new(&self.program)
+            .args(ssh_args(tunnel)?)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.program))?;
+
+        let last_error = Arc::new(Mutex::new(None));
+        if let Some(stderr) = child.stderr.take() {
+            let last_error = last_error.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(|l| l.ok()) {
+                    let line = line.trim();
+                    if !line.is_empty() {
+                        *last_error.lock().unwrap() = Some(line.to_string());
+                    }
+                }
+            });
+        }
+        Ok((child, last_error))
+    }
+
+    fn emit(&self, entry: &RunningTunnel) {
+        let mut payload = serde_json::json!(entry.state());
+        payload["project_id"] = serde_json::json!(entry.tunnel.project_id);
+        self.sink.emit_event("tunnel_status", payload);
+    }
+
+    /// Spawns ssh for a claimed run and puts the child in place, or kills it if the
+    /// tunnel was stopped or started again meanwhile.
+    fn launch(&self, tunnel_id: &str, tunnel: &SshTunnel, run: u64) -> Result<Option<TunnelState>> {
+        let (mut child, last_error) = self.spawn(tunnel)?;
+        let mut running = self.running.lock().unwrap();
+        let Some(entry) = running.get_mut(tunnel_id).filter(|e| e.run == run) else {
+            drop(running);
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        };
+        entry.child = Some(child);
+        entry.last_error = last_error;
+        entry.started = Instant::now();
+        entry.status = TunnelStatus::Starting;
+        self.emit(entry);
+        Ok(Some(entry.state()))
+    }
+
+    /// One pass of the watch loop: notice exits, schedule reconnects, respawn when due,
+    /// and mark starting tunnels up once their forward is in place. Decides under the
+    /// lock, then spawns and probes without holding it.
+    pub fn tick(&self) {
+        let now = Instant::now();
+        let mut due = Vec::new();
+        let mut starting = Vec::new();
+        let mut exited = Vec::new();
+        {
+            let mut running = self.running.lock().unwrap();
+            for (id, entry) in running.iter_mut() {
+                if let Some(at) = entry.next_start {
+                    if at <= now {
+                        entry.next_start = None;
+                        entry.status = TunnelStatus::Starting;
+                        entry.run = self.runs.fetch_add(1, Ordering::Relaxed);
+                        due.push((id.clone(), entry.tunnel.clone(), entry.run));
+                    }
+                    continue;
+                }
+
+                let Some(child) = entry.child.as_mut() else {
+                    continue;
+                };
+
+                match child.try_wait() {
+                    Ok(None) => match entry.status {
+                        TunnelStatus::Starting => starting.push((id.clone(), entry.tunnel.clone(), entry.started, entry.run)),
+                        TunnelStatus::Up if now.duration_since(entry.started) >= STABLE_AFTER => entry.attempts = 0,
+                        _ => {}
+                    },
+                    result => {
+                        let exit = result.map(|status| status.map(|s| s.to_string()).unwrap_or_default()).unwrap_or_else(|e| e.to_string());
+                        entry.child = None;
+                        if entry.tunnel.auto_reconnect {
+                            entry.attempts += 1;
+                            entry.next_start = Some(now + backoff(entry.attempts));
+                            entry.status = TunnelStatus::Reconnecting;
+                        } else {
+                            entry.status = TunnelStatus::Failed;
+                        }
+                        exited.push((id.clone(), exit, entry.run));
+                    }
+                }
+            }
+        }
+
+        for (id, tunnel, run) in due {
+            if let Err(e) = self.launch(&id, &tunnel, run) {
+                let mut running = self.running.lock().unwrap();
+                if let Some(entry) = running.get_mut(&id).filter(|e| e.run == run) {
+                    entry.message = Some(e.to_string());
+                    if entry.tunnel.auto_reconnect {
+                        entry.attempts += 1;
+                        entry.next_start = Some(now + backoff(entry.attempts));
+                        entry.status = TunnelStatus::Reconnecting;
+                    } else {
+                        entry.status = TunnelStatus::Failed;
+                    }
+                    self.emit(entry);
+                }
+            }
+        }
+
+        for (id, tunnel, started, run) in starting {
+            let up = match tunnel.tunnel_kind() {
+                TunnelKind::Local | TunnelKind::Dynamic => local_end_open(&tunnel),
+                TunnelKind::Remote => now.duration_since(started) >= REMOTE_UP_AFTER,
+            };
+            if up {
+                let mut running = self.running.lock().unwrap();
+                if let Some(entry) = running.get_mut(&id).filter(|e| e.run == run && e.child.is_some() && e.status == TunnelStatus::Starting) {
+                    entry.status = TunnelStatus::Up;
+                    entry.message = None;
+                    self.emit(entry);
+                }
+            }
+        }
+
+        if !exited.is_empty() {
+            // Give the stderr readers a moment to catch ssh's last words
+            std::thread::sleep(Duration::from_millis(20));
+            let mut running = self.running.lock().unwrap();
+            for (id, exit, run) in exited {
+                if let Some(entry) = running.get_mut(&id).filter(|e| e.run == run) {
+                    entry.message = Some(entry.last_error.lock().unwrap().clone()
+                        .unwrap_or_else(|| format!("ssh {}", exit)));
+                    self.emit(entry);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::databases::repository::DatabaseRepository;
+    use crate::modules::projects::repository::ProjectRepository;
+    use crate::modules::ssh::models::SshTunnelInput;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<serde_json::Value>>,
+    }
+
+    impl EventSink for RecordingSink {
+        fn emit_event(&self, _event: &str, payload: serde_json::Value) {
+            self.events.lock().unwrap().push(payload);
+        }
+    }
+
+    fn tunnel(kind: &str, bind_address: Option<&str>, target_host: Option<&str>) -> SshTunnel {
+        SshTunnel {
+            id: "t".into(),
+            project_id: "p".into(),
+            name: "db".into(),
+            host: "staging".into(),
+            kind: kind.into(),
+            bind_address: bind_address.map(str::to_string),
+            bind_port: 15432,
+            target_host: target_host.map(str::to_string),
+            target_port: target_host.map(|_| 5432),
+            connection_id: None,
+            auto_reconnect: true,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_ssh_args() -> Result<()> {
+        let args = ssh_args(&tunnel("local", None, Some("db.internal")))?;
+        assert_eq!(&args[args.len() - 4..], ["-L", "15432:db.internal:5432", "--", "staging"]);
+        assert!(args.contains(&"ExitOnForwardFailure=yes".to_string()));
+
+        assert_eq!(forward_spec(&tunnel("remote", Some("::1"), Some("fe80::2")))?, "[::1]:15432:[fe80::2]:5432");
+        assert_eq!(forward_spec(&tunnel("dynamic", Some("127.0.0.1"), None))?, "127.0.0.1:15432");
+        assert!(forward_spec(&tunnel("local", None, None)).is_err());
+        assert_eq!(backoff(1), BASE_BACKOFF);
+        assert_eq!(backoff(10), MAX_BACKOFF);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_tunnel_lifecycle() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let pool = SqlitePoolOptions::new().connect("sqlite::memory:").await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+
+        // Stand-ins for ssh: one that holds the forward open, one that can't bind
+        let dir = std::env::temp_dir().join(format!("switchboard-tunnels-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        let ok_ssh = dir.join("ok-ssh");
+        let bad_ssh = dir.join("bad-ssh");
+        let counting_ssh = dir.join("counting-ssh");
+        std::fs::write(&ok_ssh, "#!/bin/sh\nexec sleep 30\n")?;
+        std::fs::write(&bad_ssh, "#!/bin/sh\necho 'bind [127.0.0.1]:15432: Address already in use' >&2\nexit 255\n")?;
+        std::fs::write(&counting_ssh, "#!/bin/sh\necho $$ >> \"$(dirname \"$0\")/spawned\"\nexec sleep 30\n")?;
+        for script in [&ok_ssh, &bad_ssh, &counting_ssh] {
+            std::fs::set_permissions(script, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        let project = ProjectRepository::new(pool.clone())
+            .create_project("Test Project".into(), dir.to_string_lossy().to_string(), None)
+            .await?;
+        let connection = DatabaseRepository::new(pool.clone())
+            .create_connection(project.id.clone(), "staging db".into(), "postgres".into(), "{}".into(), None)
+            .await?;
+
+        // Whatever listens on the bind port counts as the forward being up; the
+        // stub can't listen, so the test opens the port once the tunnel started
+        let port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let repository = TunnelRepository::new(pool.clone());
+        let mut input = SshTunnelInput::local("db", "staging", port, "localhost", 5432);
+        input.connection_id = Some(connection.id.clone());
+        let linked = repository.create_tunnel(project.id.clone(), input).await?;
+        assert!(repository.create_tunnel(project.id.clone(), SshTunnelInput::local("bad", "-oProxyCommand=x", 1, "h", 1)).await.is_err());
+
+        let sink = Arc::new(RecordingSink::default());
+        let manager = TunnelManager::new(pool.clone(), sink.clone()).with_program(ok_ssh.to_string_lossy());
+        assert_eq!(manager.start(&linked.id).await?.status, TunnelStatus::Starting);
+        let listener = std::net::TcpListener::bind(("127.0.0.1", port))?;
+        let states = manager.start_for_connection(&connection.id, Duration::from_secs(5)).await?;
+        assert_eq!(states[0].status, TunnelStatus::Up);
+        assert!(states[0].pid.is_some());
+        manager.stop(&linked.id)?;
+        assert_eq!(manager.project_states(&project.id).await?[0].status, TunnelStatus::Stopped);
+
+        // The port is taken now
+        let err = manager.start(&linked.id).await.expect_err("port is in use");
+        assert!(err.to_string().contains("already in use"));
+        drop(listener);
+
+        // A forward that can't bind reconnects with the error, or fails when reconnect is off
+        let manager = manager.with_program(bad_ssh.to_string_lossy());
+        manager.start(&linked.id).await?;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while manager.state(&linked.id).status != TunnelStatus::Reconnecting && Instant::now() < deadline {
+            manager.tick();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let state = manager.state(&linked.id);
+        assert_eq!((state.status, state.attempts), (TunnelStatus::Reconnecting, 1));
+        assert!(state.message.unwrap_or_default().contains("Address already in use"));
+        manager.stop(&linked.id)?;
+
+        let mut input = SshTunnelInput::local("db", "staging", port, "localhost", 5432);
+        input.auto_reconnect = false;
+        input.connection_id = Some(connection.id.clone());
+        repository.update_tunnel(&linked.id, input).await?;
+        let err = manager.start_for_connection(&connection.id, Duration::from_secs(5)).await.expect_err("tunnel should fail");
+        assert!(err.to_string().contains("Address already in use"));
+
+        let statuses: Vec<String> = sink.events.lock().unwrap().iter().map(|e| e["status"].as_str().unwrap_or_default().to_string()).collect();
+        assert!(statuses.starts_with(&["starting".into(), "up".into(), "stopped".into()]));
+        assert_eq!(statuses.last().map(String::as_str), Some("failed"));
+
+        // Starts racing each other share one ssh
+        let spawned = || std::fs::read_to_string(dir.join("spawned")).map(|s| s.lines().count()).unwrap_or(0);
+        let manager = manager.with_program(counting_ssh.to_string_lossy());
+        let (first, second) = tokio::join!(manager.start(&linked.id), manager.start(&linked.id));
+        assert_eq!((first?.status, second?.status), (TunnelStatus::Starting, TunnelStatus::Starting));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while spawned() == 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(spawned(), 1);
+        manager.stop(&linked.id)?;
+
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
@@ -1,48 +1,173 @@
 use tauri::{command, AppHandle, State};
-use sqlx::SqlitePool;
-use super::models::TerminalSessions;
+use super::models::{ShellProfile, ShellProfileInput, TerminalAttachment, TerminalInfo};
+use super::recording::{Recording, RecordingInfo, RecordingService, RecordingTarget};
+use super::repository::TerminalRepository;
 use super::service::TerminalService;
-use crate::modules::projects::repository::ProjectRepository;
+use crate::modules::processes::models::ProcessState;
+use crate::modules::processes::service::ProcessService;
+use sqlx::SqlitePool;
 
 #[command]
 pub async fn spawn_shell(
     app_handle: AppHandle,
     state: State<'_, TerminalService>,
     project_id: String,
+    profile_id: Option<String>,
     initial_command: Option<String>,
 ) -> Result<String, String> {
-    state.spawn_shell(app_handle, project_id, initial_command)
+    state.spawn_shell(app_handle, project_id, profile_id, initial_command)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[command]
 pub fn write_to_shell(
-    sessions: State<'_, TerminalSessions>,
-    pool: State<'_, SqlitePool>,
+    state: State<'_, TerminalService>,
     session_id: String,
     data: String
 ) -> Result<(), String> {
-    // We don't strictly need ProjectRepo for writing, but Service constructor needs it currently.
-    // We could refactor to split concerns or just create repo cheap.
-    let repo = ProjectRepository::new(pool.inner().clone());
-    let service = TerminalService::new(sessions.inner().clone(), repo);
-    
-    service.write_to_shell(&session_id, &data).map_err(|e| e.to_string())
+    state.write_to_shell(&session_id, &data).map_err(|e| e.to_string())
 }
 
 #[command]
 pub fn resize_shell(
-    sessions: State<'_, TerminalSessions>,
-    pool: State<'_, SqlitePool>,
+    state: State<'_, TerminalService>,
     session_id: String,
     cols: u16,
     rows: u16
 ) -> Result<(), String> {
-    let repo = ProjectRepository::new(pool.inner().clone());
-    let service = TerminalService::new(sessions.inner().clone(), repo);
-    
-    service.resize_shell(&session_id, cols, rows).map_err(|e| e.to_string())
+    state.resize_shell(&session_id, cols, rows).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn close_shell(
+    state: State<'_, TerminalService>,
+    session_id: String
+) -> Result<(), String> {
+    state.close_shell(&session_id).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn list_shells(
+    state: State<'_, TerminalService>,
+    project_id: String
+) -> Result<Vec<TerminalInfo>, String> {
+    Ok(state.list_shells(&project_id))
+}
+
+#[command]
+pub fn attach_shell(
+    state: State<'_, TerminalService>,
+    session_id: String
+) -> Result<TerminalAttachment, String> {
+    state.attach_shell(&session_id).map_err(|e| e.to_string())
+}
+
+// Shell profiles
+
+#[command]
+pub async fn get_shell_profiles(
+    pool: State<'_, SqlitePool>,
+    project_id: String
+) -> Result<Vec<ShellProfile>, String> {
+    let repo = TerminalRepository::new(pool.inner().clone());
+    repo.get_profiles(&project_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn create_shell_profile(
+    pool: State<'_, SqlitePool>,
+    project_id: String,
+    profile: ShellProfileInput
+) -> Result<ShellProfile, String> {
+    let repo = TerminalRepository::new(pool.inner().clone());
+    repo.create_profile(project_id, profile)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn update_shell_profile(
+    pool: State<'_, SqlitePool>,
+    id: String,
+    profile: ShellProfileInput
+) -> Result<(), String> {
+    let repo = TerminalRepository::new(pool.inner().clone());
+    repo.update_profile(&id, profile)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn delete_shell_profile(
+    pool: State<'_, SqlitePool>,
+    id: String
+) -> Result<(), String> {
+    let repo = TerminalRepository::new(pool.inner().clone());
+    repo.delete_profile(&id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn detect_shells() -> Result<Vec<ShellProfileInput>, String> {
+    Ok(super::shells::detect_shells())
+}
+
+// Recordings, of a terminal session or a managed process
+
+fn recording_target(terminals: &TerminalService, processes: &ProcessState, session_id: &str) -> Result<RecordingTarget, String> {
+    terminals.recording_target(session_id)
+        .or_else(|_| ProcessService::new(processes.clone()).recording_target(session_id))
+        .map_err(|_| "Session not found".to_string())
+}
+
+#[command]
+pub fn start_recording(
+    recordings: State<'_, RecordingService>,
+    terminals: State<'_, TerminalService>,
+    processes: State<'_, ProcessState>,
+    session_id: String
+) -> Result<RecordingInfo, String> {
+    let target = recording_target(&terminals, &processes, &session_id)?;
+    recordings.start(target).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn stop_recording(
+    recordings: State<'_, RecordingService>,
+    terminals: State<'_, TerminalService>,
+    processes: State<'_, ProcessState>,
+    session_id: String
+) -> Result<RecordingInfo, String> {
+    let target = recording_target(&terminals, &processes, &session_id)?;
+    recordings.stop(&target.slot).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn list_recordings(
+    recordings: State<'_, RecordingService>,
+    project_id: Option<String>
+) -> Result<Vec<RecordingInfo>, String> {
+    recordings.list(project_id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn get_recording(
+    recordings: State<'_, RecordingService>,
+    id: String
+) -> Result<Recording, String> {
+    recordings.load(&id).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn delete_recording(
+    recordings: State<'_, RecordingService>,
+    id: String
+) -> Result<(), String> {
+    recordings.delete(&id).map_err(|e| e.to_string())
 }
 
 #[command]
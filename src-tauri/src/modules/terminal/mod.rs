@@ -1,3 +1,6 @@
 pub mod models;
+pub mod repository;
+pub mod shells;
+pub mod recording;
 pub mod service;
 pub mod commands;
@@ -1,15 +1,128 @@
-use portable_pty::PtyPair;
+use super::recording::RecordingSlot;
+use crate::shared::history::OutputHistory;
+use crate::shared::pty_stream::PtyWriter;
+use portable_pty::{ChildKiller, MasterPty};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-// We need to store the PtyPair to keep the session alive and write to it.
-// PtyPair contains (master, slave). We mostly need master to write to input and resize.
-// Reading happens in a background thread that holds a clone/ref to master reader.
+pub const SCROLLBACK_LIMIT: usize = 256 * 1024; // Enough to redraw a full screen many times over
+
+// We keep the master side to resize the PTY; the slave is dropped after spawn so the
+// reader sees EOF once the shell exits. Reading happens in a background thread.
 
 pub struct TerminalSession {
-    pub pty_pair: PtyPair,
-    // We might want to store extra metadata like process ID or project ID here
+    pub master: Box<dyn MasterPty + Send>,
+    // Taken once at spawn; written to after the sessions lock is released.
+    pub writer: PtyWriter,
+    pub killer: Box<dyn ChildKiller + Send + Sync>,
+    pub scrollback: Arc<Mutex<OutputHistory>>,
+    pub recording: RecordingSlot,
+    pub info: TerminalInfo,
+}
+
+/// What the UI needs to find a shell again after a webview reload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalInfo {
+    pub session_id: String,
     pub project_id: String,
+    pub program: String,
+    pub pid: Option<u32>,
+    pub cols: u16,
+    pub rows: u16,
+    pub created_at: u64,
+}
+
+/// A running shell plus its recent output, for redrawing a reattached terminal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalAttachment {
+    pub info: TerminalInfo,
+    pub scrollback: String,
+    /// Stream offset the scrollback ends at; `terminal_data` chunks with `end` at or
+    /// below it are already included.
+    pub end: u64,
 }
 
 // Thread-safe container for sessions
-pub type TerminalSessions = Arc<Mutex<std::collections::HashMap<String, TerminalSession>>>;
+pub type TerminalSessions = Arc<Mutex<HashMap<String, TerminalSession>>>;
+
+/// A saved way of starting a shell for a project.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ShellProfile {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub program: String,
+    pub args: String, // JSON array
+    pub env: String, // JSON object
+    pub startup_script: Option<String>,
+    pub working_subdir: Option<String>,
+    pub is_default: bool,
+    #[sqlx(default)]
+    pub created_at: String,
+    #[sqlx(default)]
+    pub updated_at: String,
+}
+
+/// Editable fields of a `ShellProfile`. Also what `/etc/shells` detection suggests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShellProfileInput {
+    pub name: String,
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    pub startup_script: Option<String>,
+    pub working_subdir: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+impl ShellProfileInput {
+    pub fn new(name: String, program: String) -> Self {
+        Self {
+            name,
+            program,
+            args: Vec::new(),
+            env: HashMap::new(),
+            startup_script: None,
+            working_subdir: None,
+            is_default: false,
+        }
+    }
+}
+
+/// Everything needed to start one shell, resolved from a profile or the system default.
+#[derive(Debug, Clone, Default)]
+pub struct ShellSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub startup_script: Option<String>,
+    pub working_subdir: Option<String>,
+}
+
+impl ShellSpec {
+    pub fn new(program: String) -> Self {
+        Self {
+            program,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&ShellProfile> for ShellSpec {
+    fn from(profile: &ShellProfile) -> Self {
+        Self {
+            program: profile.program.clone(),
+            args: serde_json::from_str(&profile.args).unwrap_or_default(),
+            env: serde_json::from_str::<HashMap<String, String>>(&profile.env)
+                .map(|env| env.into_iter().collect())
+                .unwrap_or_default(),
+            startup_script: profile.startup_script.clone(),
+            working_subdir: profile.working_subdir.clone(),
+        }
+    }
+}
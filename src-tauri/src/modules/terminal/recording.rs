@@ -0,0 +1,308 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+// Recordings of processes that don't belong to a project
+const NO_PROJECT_DIR: &str = "_global";
+
+/// Where a session's output goes while it is being recorded. Lives on the session so the
+/// PTY reader can write to it without going through the service.
+pub type RecordingSlot = Arc<Mutex<Option<AsciicastRecorder>>>;
+
+/// A session that can be recorded, terminal or managed process.
+pub struct RecordingTarget {
+    pub slot: RecordingSlot,
+    pub project_id: Option<String>,
+    pub title: String,
+    pub width: u16,
+    pub height: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    pub id: String,
+    pub project_id: Option<String>,
+    pub title: Option<String>,
+    pub width: u16,
+    pub height: u16,
+    pub timestamp: u64, // Unix seconds, as in the asciicast header
+    pub duration: f64,
+    pub size_bytes: u64,
+}
+
+/// One line of an asciicast file after the header: `[time, code, data]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingEvent {
+    pub time: f64,
+    pub code: String, // "o" output, "r" resize ("COLSxROWS")
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub info: RecordingInfo,
+    pub events: Vec<RecordingEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    width: u16,
+    height: u16,
+    #[serde(default)]
+    timestamp: u64,
+    title: Option<String>,
+}
+
+/// Writes an asciicast v2 file as the session runs.
+pub struct AsciicastRecorder {
+    file: BufWriter<File>,
+    started: Instant,
+    info: RecordingInfo,
+}
+
+impl AsciicastRecorder {
+    pub fn create(path: &Path, info: RecordingInfo) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        let header = serde_json::json!({
+            "version": 2,
+            "width": info.width,
+            "height": info.height,
+            "timestamp": info.timestamp,
+            "title": info.title,
+            "env": { "TERM": "xterm-256color" }
+        });
+        writeln!(file, "{}", header)?;
+        file.flush()?;
+        Ok(Self {
+            file,
+            started: Instant::now(),
+            info,
+        })
+    }
+
+    pub fn output(&mut self, data: &str) -> Result<()> {
+        self.event("o", data)
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
+        self.event("r", &format!("{}x{}", cols, rows))
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> Result<()> {
+        let time = (self.started.elapsed().as_secs_f64() * 1_000_000.0).round() / 1_000_000.0;
+        writeln!(self.file, "{}", serde_json::to_string(&(time, code, data))?)?;
+        // Batches from the PTY stream are already coalesced, so flushing each is cheap
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<RecordingInfo> {
+        self.file.flush()?;
+        let mut info = self.info;
+        info.duration = self.started.elapsed().as_secs_f64();
+        info.size_bytes = self.file.get_ref().metadata()?.len();
+        Ok(info)
+    }
+}
+
+/// Appends PTY output to the slot's recording, if there is one. A recording that can't be
+/// written is dropped rather than interrupting the session.
+pub fn record_output(slot: &RecordingSlot, data: &str) {
+    let mut slot = slot.lock().unwrap();
+    if let Some(Err(e)) = slot.as_mut().map(|recorder| recorder.output(data)) {
+        println!("ERROR: Failed to write recording, stopping it: {}", e);
+        *slot = None;
+    }
+}
+
+pub fn record_resize(slot: &RecordingSlot, cols: u16, rows: u16) {
+    let mut slot = slot.lock().unwrap();
+    if let Some(Err(e)) = slot.as_mut().map(|recorder| recorder.resize(cols, rows)) {
+        println!("ERROR: Failed to write recording, stopping it: {}", e);
+        *slot = None;
+    }
+}
+
+/// Managed state for asciicast recordings, stored as `<dir>/<project id>/<id>.cast`.
+pub struct RecordingService {
+    dir: PathBuf,
+}
+
+impl RecordingService {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Project ids become directory names, so anything but a UUID is rejected.
+    fn project_dir(&self, project_id: Option<&str>) -> Result<PathBuf> {
+        let Some(project_id) = project_id else {
+            return Ok(self.dir.join(NO_PROJECT_DIR));
+        };
+        Uuid::parse_str(project_id).map_err(|_| anyhow!("Invalid project id"))?;
+        Ok(self.dir.join(project_id))
+    }
+
+    pub fn start(&self, target: RecordingTarget) -> Result<RecordingInfo> {
+        let mut slot = target.slot.lock().unwrap();
+        if slot.is_some() {
+            return Err(anyhow!("Session is already being recorded"));
+        }
+        let info = RecordingInfo {
+            id: Uuid::new_v4().to_string(),
+            project_id: target.project_id,
+            title: Some(target.title),
+            width: target.width,
+            height: target.height,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            duration: 0.0,
+            size_bytes: 0,
+        };
+        let path = self.project_dir(info.project_id.as_deref())?.join(format!("{}.cast", info.id));
+        *slot = Some(AsciicastRecorder::create(&path, info.clone())?);
+        Ok(info)
+    }
+
+    pub fn stop(&self, slot: &RecordingSlot) -> Result<RecordingInfo> {
+        let recorder = slot.lock().unwrap().take().ok_or_else(|| anyhow!("Session is not being recorded"))?;
+        recorder.finish()
+    }
+
+    /// Recordings of a project, newest first. Without a project, those of unowned processes.
+    pub fn list(&self, project_id: Option<&str>) -> Result<Vec<RecordingInfo>> {
+        let dir = self.project_dir(project_id)?;
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Ok(Vec::new());
+        };
+        let mut recordings: Vec<RecordingInfo> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "cast"))
+            .filter_map(|path| read_info(&path, project_id).ok())
+            .collect();
+        recordings.sort_by_key(|r| std::cmp::Reverse(r.timestamp));
+        Ok(recordings)
+    }
+
+    pub fn load(&self, id: &str) -> Result<Recording> {
+        let (path, project_id) = self.find(id)?;
+        let info = read_info(&path, project_id.as_deref())?;
+        let events = BufReader::new(File::open(&path)?)
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.ok())
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| parse_event(&line))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Recording { info, events })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        let (path, _) = self.find(id)?;
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    fn find(&self, id: &str) -> Result<(PathBuf, Option<String>)> {
+        // Ids become file names, don't let one climb out of the recordings directory
+        Uuid::parse_str(id).map_err(|_| anyhow!("Invalid recording id"))?;
+        for entry in fs::read_dir(&self.dir)?.filter_map(|e| e.ok()) {
+            let path = entry.path().join(format!("{}.cast", id));
+            if path.exists() {
+                let dir_name = entry.file_name().to_string_lossy().to_string();
+                let project_id = (dir_name != NO_PROJECT_DIR).then_some(dir_name);
+                return Ok((path, project_id));
+            }
+        }
+        Err(anyhow!("Recording not found"))
+    }
+}
+
+fn parse_event(line: &str) -> Result<RecordingEvent> {
+    let (time, code, data): (f64, String, String) = serde_json::from_str(line)?;
+    Ok(RecordingEvent { time, code, data })
+}
+
+/// Header plus duration, taken from the time of the last event.
+fn read_info(path: &Path, project_id: Option<&str>) -> Result<RecordingInfo> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header: Header = serde_json::from_str(&lines.next().ok_or_else(|| anyhow!("Empty recording"))??)?;
+    let duration = lines
+        .map_while(|line| line.ok())
+        .filter(|line| !line.trim().is_empty())
+        .last()
+        .and_then(|line| parse_event(&line).ok())
+        .map(|event| event.time)
+        .unwrap_or(0.0);
+    Ok(RecordingInfo {
+        id: path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+        project_id: project_id.map(|p| p.to_string()),
+        title: header.title,
+        width: header.width,
+        height: header.height,
+        timestamp: header.timestamp,
+        duration,
+        size_bytes: fs::metadata(path)?.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_list_load_delete() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("switchboard-recordings-{}", Uuid::new_v4()));
+        let service = RecordingService::new(dir.clone());
+        let slot: RecordingSlot = Arc::new(Mutex::new(None));
+        let project_id = Uuid::new_v4().to_string();
+        let target = || RecordingTarget {
+            slot: slot.clone(),
+            project_id: Some(project_id.clone()),
+            title: "npm run dev".into(),
+            width: 80,
+            height: 24,
+        };
+
+        let info = service.start(target())?;
+        assert!(service.start(target()).is_err());
+        record_output(&slot, "hello \"quoted\"\r\n");
+        record_resize(&slot, 120, 40);
+        record_output(&slot, "\x1b[32mdone\x1b[0m");
+        service.stop(&slot)?;
+        assert!(service.stop(&slot).is_err());
+
+        // Output after stopping is not recorded
+        record_output(&slot, "ignored");
+
+        let listed = service.list(Some(&project_id))?;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, info.id);
+        assert_eq!(listed[0].title.as_deref(), Some("npm run dev"));
+        assert!(service.list(None)?.is_empty());
+
+        let recording = service.load(&info.id)?;
+        assert_eq!((recording.info.width, recording.info.height), (80, 24));
+        let events: Vec<(&str, &str)> = recording.events.iter().map(|e| (e.code.as_str(), e.data.as_str())).collect();
+        assert_eq!(events, vec![("o", "hello \"quoted\"\r\n"), ("r", "120x40"), ("o", "\x1b[32mdone\x1b[0m")]);
+        assert!(recording.events.windows(2).all(|w| w[0].time <= w[1].time));
+
+        // The header is plain asciicast v2
+        let content = fs::read_to_string(dir.join(&project_id).join(format!("{}.cast", info.id)))?;
+        let header: serde_json::Value = serde_json::from_str(content.lines().next().unwrap())?;
+        assert_eq!(header["version"], 2);
+
+        assert!(service.load("../../etc/passwd").is_err());
+        assert!(service.list(Some("../..")).is_err());
+        service.delete(&info.id)?;
+        assert!(service.list(Some(&project_id))?.is_empty());
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
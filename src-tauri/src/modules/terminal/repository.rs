@@ -0,0 +1,168 @@
+use super::models::{ShellProfile, ShellProfileInput};
+use anyhow::{anyhow, Result};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+const SELECT_PROFILE: &str = "SELECT id, project_id, name, program, args, env, startup_script, working_subdir, is_default, created_at, updated_at FROM terminal_profiles";
+
+fn validate(input: &ShellProfileInput) -> Result<()> {
+    if input.name.trim().is_empty() {
+        return Err(anyhow!("Profile name is required"));
+    }
+    if input.program.trim().is_empty() {
+        return Err(anyhow!("Shell program is required"));
+    }
+    if let Some(subdir) = &input.working_subdir {
+        let path = std::path::Path::new(subdir);
+        if path.is_absolute() || path.components().any(|c| c == std::path::Component::ParentDir) {
+            return Err(anyhow!("Working directory must stay inside the project"));
+        }
+    }
+    Ok(())
+}
+
+pub struct TerminalRepository {
+    pool: SqlitePool,
+}
+
+impl TerminalRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_profile(&self, project_id: String, input: ShellProfileInput) -> Result<ShellProfile> {
+        validate(&input)?;
+        let id = Uuid::new_v4().to_string();
+
+        let mut tx = self.pool.begin().await?;
+        if input.is_default {
+            sqlx::query("UPDATE terminal_profiles SET is_default = 0 WHERE project_id = ?")
+                .bind(&project_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("INSERT INTO terminal_profiles (id, project_id, name, program, args, env, startup_script, working_subdir, is_default) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(&id)
+            .bind(&project_id)
+            .bind(&input.name)
+            .bind(&input.program)
+            .bind(serde_json::to_string(&input.args)?)
+            .bind(serde_json::to_string(&input.env)?)
+            .bind(&input.startup_script)
+            .bind(&input.working_subdir)
+            .bind(input.is_default)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.get_profile(&id).await?.ok_or_else(|| anyhow!("Failed to retrieve created shell profile"))
+    }
+
+    pub async fn get_profile(&self, id: &str) -> Result<Option<ShellProfile>> {
+        let profile = sqlx::query_as::<_, ShellProfile>(&format!("{} WHERE id = ?", SELECT_PROFILE))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(profile)
+    }
+
+    pub async fn get_profiles(&self, project_id: &str) -> Result<Vec<ShellProfile>> {
+        let profiles = sqlx::query_as::<_, ShellProfile>(&format!("{} WHERE project_id = ? ORDER BY created_at ASC", SELECT_PROFILE))
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(profiles)
+    }
+
+    pub async fn get_default_profile(&self, project_id: &str) -> Result<Option<ShellProfile>> {
+        let profile = sqlx::query_as::<_, ShellProfile>(&format!("{} WHERE project_id = ? AND is_default = 1", SELECT_PROFILE))
+            .bind(project_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(profile)
+    }
+
+    pub async fn update_profile(&self, id: &str, input: ShellProfileInput) -> Result<()> {
+        validate(&input)?;
+        let profile = self.get_profile(id).await?.ok_or_else(|| anyhow!("Shell profile not found"))?;
+
+        // Only one default per project
+        let mut tx = self.pool.begin().await?;
+        if input.is_default {
+            sqlx::query("UPDATE terminal_profiles SET is_default = 0 WHERE project_id = ? AND id != ?")
+                .bind(&profile.project_id)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("UPDATE terminal_profiles SET name = ?, program = ?, args = ?, env = ?, startup_script = ?, working_subdir = ?, is_default = ? WHERE id = ?")
+            .bind(input.name)
+            .bind(input.program)
+            .bind(serde_json::to_string(&input.args)?)
+            .bind(serde_json::to_string(&input.env)?)
+            .bind(input.startup_script)
+            .bind(input.working_subdir)
+            .bind(input.is_default)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete_profile(&self, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM terminal_profiles WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::projects::repository::ProjectRepository;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_profile_crud_keeps_one_default() -> Result<()> {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await?;
+
+        let project = ProjectRepository::new(pool.clone())
+            .create_project("Test Project".into(), "/tmp/test".into(), None)
+            .await?;
+        let repo = TerminalRepository::new(pool);
+
+        let mut bash = ShellProfileInput::new("bash".into(), "/bin/bash".into());
+        bash.args = vec!["-l".into()];
+        bash.is_default = true;
+        let bash = repo.create_profile(project.id.clone(), bash).await?;
+        assert_eq!(bash.args, r#"["-l"]"#);
+
+        let mut node = ShellProfileInput::new("node".into(), "/bin/sh".into());
+        node.working_subdir = Some("web".into());
+        node.is_default = true;
+        let node = repo.create_profile(project.id.clone(), node).await?;
+        assert_eq!(repo.get_default_profile(&project.id).await?.map(|p| p.id), Some(node.id.clone()));
+
+        let mut escape = ShellProfileInput::new("escape".into(), "/bin/sh".into());
+        escape.working_subdir = Some("../elsewhere".into());
+        assert!(repo.create_profile(project.id.clone(), escape).await.is_err());
+
+        let mut bash_input = ShellProfileInput::new("bash".into(), "/bin/bash".into());
+        bash_input.is_default = true;
+        repo.update_profile(&bash.id, bash_input).await?;
+        assert_eq!(repo.get_default_profile(&project.id).await?.map(|p| p.id), Some(bash.id.clone()));
+        assert_eq!(repo.get_profiles(&project.id).await?.len(), 2);
+
+        repo.delete_profile(&node.id).await?;
+        assert!(repo.get_profile(&node.id).await?.is_none());
+        Ok(())
+    }
+}
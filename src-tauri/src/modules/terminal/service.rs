@@ -1,28 +1,69 @@
-use super::models::{TerminalSession, TerminalSessions};
+use super::models::{ShellSpec, TerminalAttachment, TerminalInfo, TerminalSession, TerminalSessions, SCROLLBACK_LIMIT};
+use super::recording::{record_output, record_resize, RecordingSlot, RecordingTarget};
+use super::repository::TerminalRepository;
+use super::shells::default_shell;
+use crate::shared::history::OutputHistory;
 use crate::modules::projects::repository::ProjectRepository;
-use anyhow::{Context, Result};
+use crate::shared::events::EventSink;
+use crate::shared::pty_stream::{stream_pty, PtyWriter, StreamConfig};
+use crate::shared::utils::now_millis;
+use anyhow::{anyhow, Context, Result};
 use portable_pty::{native_pty_system, CommandBuilder, PtySize};
-use std::io::{Read, Write};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use tauri::{AppHandle, Emitter};
+use std::time::Duration;
 use uuid::Uuid;
 
+// How long the exit event waits for the reader to drain the last output. Background
+// jobs can keep the PTY open after the shell itself is gone.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Managed state for built-in project shells. Owns the session map, so every
+/// command sees the same sessions no matter which webview calls it.
 pub struct TerminalService {
     sessions: TerminalSessions,
     project_repo: ProjectRepository,
+    terminal_repo: TerminalRepository,
 }
 
 impl TerminalService {
-    pub fn new(sessions: TerminalSessions, repo: ProjectRepository) -> Self {
+    pub fn new(pool: SqlitePool) -> Self {
         Self {
-            sessions,
-            project_repo: repo,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            project_repo: ProjectRepository::new(pool.clone()),
+            terminal_repo: TerminalRepository::new(pool),
         }
     }
 
+    /// Opens a shell in the project directory: the given profile, else the project's
+    /// default profile, else the user's login shell.
+    pub async fn spawn_shell<S: EventSink + Clone>(
+        &self,
+        sink: S,
+        project_id: String,
+        profile_id: Option<String>,
+        initial_command: Option<String>,
+    ) -> Result<String> {
+        let profile = match profile_id {
+            Some(id) => Some(self.terminal_repo.get_profile(&id).await?
+                .filter(|p| p.project_id == project_id)
+                .ok_or_else(|| anyhow!("Shell profile not found"))?),
+            None => self.terminal_repo.get_default_profile(&project_id).await?,
+        };
+        let spec = profile.as_ref().map(ShellSpec::from).unwrap_or_else(default_shell);
+        self.spawn(sink, project_id, spec, initial_command).await
+    }
 
-
-    pub async fn spawn_shell(&self, app_handle: AppHandle, project_id: String, initial_command: Option<String>) -> Result<String> {
+    pub async fn spawn<S: EventSink + Clone>(
+        &self,
+        sink: S,
+        project_id: String,
+        spec: ShellSpec,
+        initial_command: Option<String>,
+    ) -> Result<String> {
         let project = self.project_repo.get_project(&project_id).await
             .context("Failed to fetch project")?
             .context("Project not found")?;
@@ -38,122 +79,403 @@ impl TerminalService {
             pixel_height: 0,
         })?;
 
-        // Configure Command
-        // Default to shell env var or /bin/zsh or cmd.exe
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
-        println!("DEBUG: Using shell: {}", shell);
-        
-        let mut cmd = CommandBuilder::new(shell);
-        cmd.args(["-l"]);
+        let mut cmd = CommandBuilder::new(&spec.program);
+        cmd.args(&spec.args);
         cmd.env("TERM", "xterm-256color");
-        
+
         // Cwd
         // Expand ~ if present
         let expanded_path = crate::shared::utils::expand_path(&project.path);
-        println!("DEBUG: Spawning shell in path: {}", expanded_path);
-        
+
         // Validation: Verify path exists, if not create it (auto-heal)
         let path_obj = std::path::Path::new(&expanded_path);
         if !path_obj.exists() {
-             println!("DEBUG: Path missing, auto-creating: {}", expanded_path);
-             if let Err(e) = std::fs::create_dir_all(&path_obj) {
-                println!("ERROR: Failed to create path: {}", e);
-                return Err(anyhow::anyhow!("Failed to create project path: {}", e));
-             }
+            println!("DEBUG: Path missing, auto-creating: {}", expanded_path);
+            std::fs::create_dir_all(path_obj)
+                .map_err(|e| anyhow!("Failed to create project path: {}", e))?;
         }
 
-        cmd.cwd(expanded_path);
+        match &spec.working_subdir {
+            Some(subdir) => {
+                let cwd = path_obj.join(subdir);
+                if !cwd.is_dir() {
+                    return Err(anyhow!("Working directory {} does not exist", cwd.display()));
+                }
+                cmd.cwd(cwd);
+            }
+            None => cmd.cwd(expanded_path),
+        }
 
-        // Env Vars
+        // Env Vars, profile overrides win over the project's
         for env in envs {
             cmd.env(env.key, env.value);
         }
+        for (key, value) in &spec.env {
+            cmd.env(key, value);
+        }
 
-        // Spawn
-        println!("DEBUG: Spawning command: {:?}", cmd);
-        let _child = pair.slave.spawn_command(cmd)?;
+        let mut child = pair.slave.spawn_command(cmd)?;
+        drop(pair.slave);
+        let killer = child.clone_killer();
+        let pid = child.process_id();
 
-        // If we have an initial command (like ssh), write it immediately
+        let mut writer = pair.master.take_writer()?;
+
+        // Startup script first, then an initial command (like ssh), both typed in as input
+        if let Some(script) = spec.startup_script.as_deref().filter(|s| !s.trim().is_empty()) {
+            writer.write_all(format!("{}\r", script.trim_end().replace('\n', "\r")).as_bytes())?;
+        }
         if let Some(cmd_str) = initial_command {
-            let mut writer = pair.master.take_writer()?;
-            let cmd_with_newline = format!("{}\r", cmd_str); // Add return
-            writer.write_all(cmd_with_newline.as_bytes())?;
+            writer.write_all(format!("{}\r", cmd_str).as_bytes())?;
         }
-        
+
+        let writer: PtyWriter = Arc::new(Mutex::new(writer));
+
         // Generate Session ID
         let session_id = Uuid::new_v4().to_string();
+        let scrollback = Arc::new(Mutex::new(OutputHistory::new(SCROLLBACK_LIMIT, None)));
 
         // Start Reader Thread
-        let mut reader = pair.master.try_clone_reader()?;
-        let app_handle_clone = app_handle.clone();
+        let reader = pair.master.try_clone_reader()?;
         let session_id_clone = session_id.clone();
+        let scrollback_clone = scrollback.clone();
+        let recording: RecordingSlot = Arc::new(Mutex::new(None));
+        let recording_clone = recording.clone();
+        let reader_sink = sink.clone();
 
-        thread::spawn(move || {
-            let mut buffer = [0u8; 1024];
-            loop {
-                match reader.read(&mut buffer) {
-                    Ok(n) if n > 0 => {
-                        // println!("DEBUG: Read {} bytes from PTY for session {}", n, session_id_clone);
-                        let data = String::from_utf8_lossy(&buffer[..n]).to_string();
-                        let _ = app_handle_clone.emit("terminal_data", Payload {
-                            session_id: session_id_clone.clone(),
-                            data,
-                        });
-                    }
-                    Ok(_) => {
-                         println!("DEBUG: PTY Reader EOF for session {}", session_id_clone);
-                         break;
-                    } 
-                    Err(e) => {
-                         println!("DEBUG: PTY Reader Error for session {}: {}", session_id_clone, e);
-                         break;
-                    }
-                }
-            }
-            // Cleanup or emit exit event
+        let output = stream_pty(reader, StreamConfig::default(), move |data| {
+            // `end` lets a reattaching view skip chunks its scrollback already holds
+            let end = {
+                let mut scrollback = scrollback_clone.lock().unwrap();
+                scrollback.push(data.as_bytes());
+                scrollback.total()
+            };
+            record_output(&recording_clone, data);
+            reader_sink.emit_event("terminal_data", serde_json::json!({
+                "session_id": session_id_clone,
+                "data": data,
+                "end": end
+            }));
         });
 
         // Store Session
-        let session = TerminalSession {
-            pty_pair: pair,
+        let info = TerminalInfo {
+            session_id: session_id.clone(),
             project_id: project_id.clone(),
+            program: spec.program,
+            pid,
+            cols: 80,
+            rows: 24,
+            created_at: now_millis(),
+        };
+        let session = TerminalSession {
+            master: pair.master,
+            writer,
+            killer,
+            scrollback,
+            recording,
+            info,
         };
 
         self.sessions.lock().unwrap().insert(session_id.clone(), session);
 
+        // Reaper Thread: the session goes away with its shell
+        let sessions = self.sessions.clone();
+        let session_id_clone = session_id.clone();
+        thread::spawn(move || {
+            let exit_code = child.wait().ok().map(|status| status.exit_code());
+            output.wait(DRAIN_TIMEOUT);
+            sessions.lock().unwrap().remove(&session_id_clone);
+            sink.emit_event("terminal_exit", serde_json::json!({
+                "session_id": session_id_clone,
+                "project_id": project_id,
+                "exit_code": exit_code
+            }));
+        });
+
         Ok(session_id)
     }
 
-    pub fn write_to_shell(&self, session_id: &str, data: &str) -> Result<()> {
-        println!("DEBUG: Writing {} bytes to session {}", data.len(), session_id);
+    /// Hangs up the shell. `terminal_exit` follows once it is gone.
+    pub fn close_shell(&self, session_id: &str) -> Result<()> {
         let mut sessions = self.sessions.lock().unwrap();
-        if let Some(session) = sessions.get_mut(session_id) {
-            // MasterPty doesn't implement Write directly in newer versions of portable-pty
-            // We need to clone a writer from it.
-            let mut writer = session.pty_pair.master.take_writer()?;
-            writer.write_all(data.as_bytes())?;
-        } else {
-             println!("ERROR: Session {} not found", session_id);
-        }
+        let session = sessions.get_mut(session_id).ok_or_else(|| anyhow!("Session not found"))?;
+        session.killer.kill()?;
+        Ok(())
+    }
+
+    pub fn list_shells(&self, project_id: &str) -> Vec<TerminalInfo> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut shells: Vec<TerminalInfo> = sessions.values()
+            .filter(|s| s.info.project_id == project_id)
+            .map(|s| s.info.clone())
+            .collect();
+        shells.sort_by_key(|s| s.created_at);
+        shells
+    }
+
+    /// Everything needed to pick a running shell back up in a fresh terminal view.
+    pub fn attach_shell(&self, session_id: &str) -> Result<TerminalAttachment> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(session_id).ok_or_else(|| anyhow!("Session not found"))?;
+        let scrollback = session.scrollback.lock().unwrap().read(None, SCROLLBACK_LIMIT)?;
+        Ok(TerminalAttachment {
+            info: session.info.clone(),
+            scrollback: scrollback.data,
+            end: scrollback.end,
+        })
+    }
+
+    pub fn write_to_shell(&self, session_id: &str, data: &str) -> Result<()> {
+        // The write can block on a full PTY buffer, so it must not hold the sessions lock
+        let writer = self.sessions.lock().unwrap()
+            .get(session_id)
+            .map(|s| s.writer.clone())
+            .ok_or_else(|| anyhow!("Session not found"))?;
+        let mut writer = writer.lock().unwrap();
+        writer.write_all(data.as_bytes())?;
+        writer.flush()?;
         Ok(())
     }
 
     pub fn resize_shell(&self, session_id: &str, cols: u16, rows: u16) -> Result<()> {
         let mut sessions = self.sessions.lock().unwrap();
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.pty_pair.master.resize(PtySize {
-                rows,
-                cols,
-                pixel_width: 0,
-                pixel_height: 0,
-            })?;
-        }
+        let session = sessions.get_mut(session_id).ok_or_else(|| anyhow!("Session not found"))?;
+        session.master.resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        session.info.cols = cols;
+        session.info.rows = rows;
+        record_resize(&session.recording, cols, rows);
         Ok(())
     }
+
+    pub fn recording_target(&self, session_id: &str) -> Result<RecordingTarget> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(session_id).ok_or_else(|| anyhow!("Session not found"))?;
+        Ok(RecordingTarget {
+            slot: session.recording.clone(),
+            project_id: Some(session.info.project_id.clone()),
+            title: session.info.program.clone(),
+            width: session.info.cols,
+            height: session.info.rows,
+        })
+    }
 }
 
-#[derive(Clone, serde::Serialize)]
-struct Payload {
-    session_id: String,
-    data: String,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::time::{Duration, Instant};
+
+    #[derive(Default)]
+    struct RecordingSink {
+        output: Mutex<String>,
+        chunks: Mutex<Vec<(u64, String)>>,
+        exits: Mutex<Vec<serde_json::Value>>,
+    }
+
+    impl EventSink for RecordingSink {
+        fn emit_event(&self, event: &str, payload: serde_json::Value) {
+            match event {
+                "terminal_data" => {
+                    let data = payload["data"].as_str().unwrap_or_default();
+                    self.output.lock().unwrap().push_str(data);
+                    self.chunks.lock().unwrap().push((payload["end"].as_u64().unwrap_or_default(), data.to_string()));
+                }
+                "terminal_exit" => self.exits.lock().unwrap().push(payload),
+                _ => {}
+            }
+        }
+    }
+
+    async fn service_with_project() -> Result<(TerminalService, String)> {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await?;
+
+        let repo = ProjectRepository::new(pool.clone());
+        let project = repo.create_project("Shell".into(), std::env::temp_dir().to_string_lossy().to_string(), None).await?;
+        repo.set_env_var(&project.id, "SWITCHBOARD_GREETING".into(), "hello from env".into()).await?;
+        Ok((TerminalService::new(pool), project.id))
+    }
+
+    fn wait_for_exit(sink: &RecordingSink) -> Option<serde_json::Value> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Some(exit) = sink.exits.lock().unwrap().first() {
+                return Some(exit.clone());
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        None
+    }
+
+    fn wait_for_output(sink: &RecordingSink, needle: &str) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if sink.output.lock().unwrap().contains(needle) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shell_round_trip() -> Result<()> {
+        let (service, project_id) = service_with_project().await?;
+        let sink = Arc::new(RecordingSink::default());
+
+        let session_id = service.spawn(sink.clone(), project_id, ShellSpec::new("/bin/sh".into()), None).await?;
+
+        // take_writer() only works once per PTY, so a second write used to fail
+        service.write_to_shell(&session_id, "echo \"$SWITCHBOARD_GREETING\"\n")?;
+        assert!(wait_for_output(&sink, "hello from env\r\n"));
+        service.write_to_shell(&session_id, "echo second-$((20 + 22))\n")?;
+        assert!(wait_for_output(&sink, "second-42"));
+
+        service.resize_shell(&session_id, 120, 40)?;
+        assert!(service.write_to_shell("missing", "ls\n").is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_initial_command_runs() -> Result<()> {
+        let (service, project_id) = service_with_project().await?;
+        let sink = Arc::new(RecordingSink::default());
+
+        service.spawn(sink.clone(), project_id, ShellSpec::new("/bin/sh".into()), Some("echo init-$((6 * 7))".into())).await?;
+        assert!(wait_for_output(&sink, "init-42"));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exit_event_removes_session() -> Result<()> {
+        let (service, project_id) = service_with_project().await?;
+        let sink = Arc::new(RecordingSink::default());
+
+        let session_id = service.spawn(sink.clone(), project_id.clone(), ShellSpec::new("/bin/sh".into()), None).await?;
+        let shells = service.list_shells(&project_id);
+        assert_eq!(shells.len(), 1);
+        assert_eq!(shells[0].session_id, session_id);
+        assert!(service.list_shells("other").is_empty());
+
+        service.write_to_shell(&session_id, "printf 'last-%s\\n' words; exit 3\n")?;
+        let exit = wait_for_exit(&sink).expect("terminal_exit");
+        assert_eq!(exit["session_id"], session_id.as_str());
+        assert_eq!(exit["exit_code"], 3);
+        // Output written right before exiting still arrives first
+        assert!(sink.output.lock().unwrap().contains("last-words"));
+        assert!(service.list_shells(&project_id).is_empty());
+        assert!(service.attach_shell(&session_id).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_attach_replays_scrollback_then_close() -> Result<()> {
+        let (service, project_id) = service_with_project().await?;
+        let sink = Arc::new(RecordingSink::default());
+
+        let session_id = service.spawn(sink.clone(), project_id, ShellSpec::new("/bin/sh".into()), Some("echo before-$((1 + 1))".into())).await?;
+        assert!(wait_for_output(&sink, "before-2"));
+        service.resize_shell(&session_id, 100, 30)?;
+
+        let attachment = service.attach_shell(&session_id)?;
+        assert!(attachment.scrollback.contains("before-2"));
+        assert_eq!((attachment.info.cols, attachment.info.rows), (100, 30));
+
+        service.close_shell(&session_id)?;
+        assert!(wait_for_exit(&sink).is_some());
+        assert!(service.close_shell(&session_id).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_attach_mid_stream_loses_and_repeats_nothing() -> Result<()> {
+        let (service, project_id) = service_with_project().await?;
+        let sink = Arc::new(RecordingSink::default());
+
+        let command = "for i in $(seq 1 300); do echo line-$i; sleep 0.002; done; echo done-$((1 + 1))";
+        let session_id = service.spawn(sink.clone(), project_id, ShellSpec::new("/bin/sh".into()), Some(command.into())).await?;
+        assert!(wait_for_output(&sink, "line-50"));
+
+        // What a view attaching now would show: the scrollback, then every later chunk
+        let attachment = service.attach_shell(&session_id)?;
+        assert!(wait_for_output(&sink, "done-2"));
+        let later: String = sink.chunks.lock().unwrap().iter()
+            .filter(|(end, _)| *end > attachment.end)
+            .map(|(_, data)| data.as_str())
+            .collect();
+        assert_eq!(attachment.scrollback + &later, *sink.output.lock().unwrap());
+
+        service.close_shell(&session_id)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_with_profile() -> Result<()> {
+        use crate::modules::terminal::models::ShellProfileInput;
+
+        let (service, project_id) = service_with_project().await?;
+        let sink = Arc::new(RecordingSink::default());
+        let subdir = format!("switchboard-profile-{}", Uuid::new_v4());
+        std::fs::create_dir_all(std::env::temp_dir().join(&subdir))?;
+
+        let mut input = ShellProfileInput::new("sh".into(), "/bin/sh".into());
+        input.env.insert("SWITCHBOARD_GREETING".into(), "from-profile".into());
+        input.startup_script = Some("echo \"greeting-$SWITCHBOARD_GREETING\"\npwd".into());
+        input.working_subdir = Some(subdir.clone());
+        input.is_default = true;
+        service.terminal_repo.create_profile(project_id.clone(), input).await?;
+
+        // No profile id given, so the project default is used
+        service.spawn_shell(sink.clone(), project_id.clone(), None, None).await?;
+        assert!(wait_for_output(&sink, "greeting-from-profile"));
+        assert!(wait_for_output(&sink, &format!("/{}\r\n", subdir)));
+
+        assert!(service.spawn_shell(sink, project_id, Some("missing".into()), None).await.is_err());
+        let _ = std::fs::remove_dir_all(std::env::temp_dir().join(&subdir));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_records_shell_session() -> Result<()> {
+        use crate::modules::terminal::recording::RecordingService;
+
+        let (service, project_id) = service_with_project().await?;
+        let sink = Arc::new(RecordingSink::default());
+        let dir = std::env::temp_dir().join(format!("switchboard-recordings-{}", Uuid::new_v4()));
+        let recordings = RecordingService::new(dir.clone());
+
+        let session_id = service.spawn(sink.clone(), project_id.clone(), ShellSpec::new("/bin/sh".into()), None).await?;
+        let info = recordings.start(service.recording_target(&session_id)?)?;
+        service.write_to_shell(&session_id, "echo rec-$((40 + 2))\n")?;
+        assert!(wait_for_output(&sink, "rec-42"));
+        service.resize_shell(&session_id, 100, 30)?;
+        recordings.stop(&service.recording_target(&session_id)?.slot)?;
+
+        let recording = recordings.load(&info.id)?;
+        assert_eq!(recording.info.project_id, Some(project_id));
+        assert!(recording.events.iter().any(|e| e.code == "o" && e.data.contains("rec-42")));
+        assert_eq!(recording.events.last().map(|e| e.data.as_str()), Some("100x30"));
+
+        service.close_shell(&session_id)?;
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
 }
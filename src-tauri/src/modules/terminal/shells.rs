@@ -0,0 +1,81 @@
+use super::models::{ShellProfileInput, ShellSpec};
+use std::path::Path;
+
+const ETC_SHELLS: &str = "/etc/shells";
+
+// Not interactive shells, even though some systems list them
+const NOT_SHELLS: &[&str] = &["nologin", "false", "tmux", "screen", "git-shell"];
+
+/// Shell paths from an `/etc/shells` file, skipping comments and non-shells.
+pub fn parse_etc_shells(content: &str) -> Vec<String> {
+    content.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| line.starts_with('/'))
+        .filter(|line| !NOT_SHELLS.contains(&shell_name(line)))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn shell_name(program: &str) -> &str {
+    program.rsplit('/').next().unwrap_or(program)
+}
+
+/// Installed shells as ready-to-save profiles, one per shell name.
+/// `/usr/bin/bash` and `/bin/bash` are usually the same binary, the first listed wins.
+pub fn detect_shells() -> Vec<ShellProfileInput> {
+    let listed = std::fs::read_to_string(ETC_SHELLS).map(|c| parse_etc_shells(&c)).unwrap_or_default();
+    let mut profiles: Vec<ShellProfileInput> = Vec::new();
+    for program in listed.into_iter().filter(|p| Path::new(p).exists()) {
+        let name = shell_name(&program).to_string();
+        if profiles.iter().any(|p| p.name == name) {
+            continue;
+        }
+        let mut profile = ShellProfileInput::new(name, program);
+        profile.args = login_args(&profile.name);
+        profiles.push(profile);
+    }
+    profiles
+}
+
+/// `-l` is understood by the common shells but not by everything listed in `/etc/shells`.
+fn login_args(name: &str) -> Vec<String> {
+    match name {
+        "sh" | "bash" | "zsh" | "ksh" | "mksh" | "fish" | "dash" => vec!["-l".to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// Used when a project has no default profile: `$SHELL` if it exists,
+/// otherwise the first of zsh, bash or sh in `/etc/shells`.
+pub fn default_shell() -> ShellSpec {
+    let program = std::env::var("SHELL").ok()
+        .filter(|shell| Path::new(shell).exists())
+        .or_else(|| {
+            let detected = detect_shells();
+            ["zsh", "bash", "sh"].iter()
+                .find_map(|name| detected.iter().find(|p| p.name == *name))
+                .map(|p| p.program.clone())
+        })
+        .unwrap_or_else(|| "/bin/sh".to_string());
+    let mut spec = ShellSpec::new(program);
+    spec.args = login_args(shell_name(&spec.program));
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_etc_shells() {
+        let content = "# /etc/shells: valid login shells\n/bin/sh\n/usr/bin/zsh  # installed by apt\n\n/usr/sbin/nologin\n/usr/bin/tmux\nbogus\n";
+        assert_eq!(parse_etc_shells(content), vec!["/bin/sh", "/usr/bin/zsh"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_default_shell_exists() {
+        let shell = default_shell();
+        assert!(Path::new(&shell.program).exists());
+    }
+}
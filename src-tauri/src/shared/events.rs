@@ -0,0 +1,26 @@
+use tauri::{AppHandle, Emitter, Runtime, Window};
+
+/// Destination for backend -> frontend events.
+/// Services stream PTY output through this instead of a concrete `Window`/`AppHandle`,
+/// which keeps them usable from background threads and from tests without a webview.
+pub trait EventSink: Send + Sync + 'static {
+    fn emit_event(&self, event: &str, payload: serde_json::Value);
+}
+
+impl<R: Runtime> EventSink for Window<R> {
+    fn emit_event(&self, event: &str, payload: serde_json::Value) {
+        let _ = self.emit(event, payload);
+    }
+}
+
+impl<R: Runtime> EventSink for AppHandle<R> {
+    fn emit_event(&self, event: &str, payload: serde_json::Value) {
+        let _ = self.emit(event, payload);
+    }
+}
+
+impl<T: EventSink + ?Sized> EventSink for std::sync::Arc<T> {
+    fn emit_event(&self, event: &str, payload: serde_json::Value) {
+        (**self).emit_event(event, payload)
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 1024 * 1024; // 1 MiB per stream
+
+/// A slice of PTY output. Offsets are absolute byte positions in the full
+/// output stream, so the UI can page backwards by asking for `start - limit`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryChunk {
+    pub data: String,
+    pub start: u64,
+    pub end: u64,
+    pub total: u64,
+}
+
+/// Byte-capped ring buffer of PTY output, shared by managed processes and shells.
+/// Bytes evicted from memory are appended to `spill_path` (if set), so the file always
+/// holds exactly the stream range `[0, buffer_start)` and the buffer holds the rest.
+pub struct OutputHistory {
+    buffer: VecDeque<u8>,
+    capacity: usize,
+    buffer_start: u64,
+    spill_path: Option<PathBuf>,
+    spill: Option<File>,
+}
+
+impl OutputHistory {
+    pub fn new(capacity: usize, spill_path: Option<PathBuf>) -> Self {
+        Self {
+            buffer: VecDeque::new(),
+            capacity: capacity.max(1),
+            buffer_start: 0,
+            spill_path,
+            spill: None,
+        }
+    }
+
+    /// Total number of bytes ever written.
+    pub fn total(&self) -> u64 {
+        self.buffer_start + self.buffer.len() as u64
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend(data);
+        self.evict();
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        if self.buffer.len() <= self.capacity {
+            return;
+        }
+        let excess = self.buffer.len() - self.capacity;
+        let evicted: Vec<u8> = self.buffer.drain(..excess).collect();
+        self.buffer_start += excess as u64;
+
+        if let Err(e) = self.spill_bytes(&evicted) {
+            // Losing old scrollback is acceptable, losing the stream is not.
+            println!("ERROR: Failed to spill output history: {}", e);
+            self.spill_path = None;
+            self.spill = None;
+        }
+    }
+
+    fn spill_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        let Some(path) = &self.spill_path else {
+            return Ok(());
+        };
+        if self.spill.is_none() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            self.spill = Some(OpenOptions::new().create(true).append(true).open(path)?);
+        }
+        if let Some(file) = self.spill.as_mut() {
+            file.write_all(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Reads up to `limit` bytes starting at `offset`.
+    /// Without an offset the tail of the stream is returned.
+    pub fn read(&self, offset: Option<u64>, limit: usize) -> Result<HistoryChunk> {
+        let total = self.total();
+        // Anything before the spill file was dropped (no log dir configured or spill failed)
+        let earliest = if self.spill_path.is_some() { 0 } else { self.buffer_start };
+
+        let start = offset.unwrap_or_else(|| total.saturating_sub(limit as u64));
+        let start = start.clamp(earliest, total);
+        let end = start.saturating_add(limit as u64).min(total);
+
+        let mut bytes = Vec::with_capacity((end - start) as usize);
+
+        // Part of the range that lives in the spill file
+        if start < self.buffer_start {
+            if let Some(path) = &self.spill_path {
+                let file_end = end.min(self.buffer_start);
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(start))?;
+                file.take(file_end - start).read_to_end(&mut bytes)?;
+            }
+        }
+
+        // Part of the range that is still in memory
+        if end > self.buffer_start {
+            let from = start.max(self.buffer_start) - self.buffer_start;
+            let to = end - self.buffer_start;
+            bytes.extend(self.buffer.range(from as usize..to as usize));
+        }
+
+        // Offsets are byte based, don't hand the UI half a character at either edge.
+        let lead = bytes.iter().take_while(|b| is_continuation(**b)).count().min(3);
+        let start = start + lead as u64;
+        bytes.drain(..lead);
+        let tail = incomplete_tail_len(&bytes);
+        let end = if end < total { end - tail as u64 } else { end };
+        if end < total {
+            bytes.truncate(bytes.len() - tail);
+        }
+
+        Ok(HistoryChunk {
+            data: String::from_utf8_lossy(&bytes).to_string(),
+            start,
+            end,
+            total,
+        })
+    }
+
+    /// Removes the spill file, used when its owner is dropped.
+    pub fn discard(&mut self) {
+        self.spill = None;
+        if let Some(path) = self.spill_path.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+/// Number of trailing bytes that form the start of a multi-byte character cut off by the range end.
+fn incomplete_tail_len(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(4) {
+        let byte = bytes[bytes.len() - back];
+        if is_continuation(byte) {
+            continue;
+        }
+        let width = match byte {
+            b if b >= 0xF0 => 4,
+            b if b >= 0xE0 => 3,
+            b if b >= 0xC0 => 2,
+            _ => 1,
+        };
+        return if width > back { back } else { 0 };
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_buffer_spills_and_pages_backwards() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("switchboard-history-{}.log", uuid::Uuid::new_v4()));
+        let mut history = OutputHistory::new(8, Some(path.clone()));
+
+        history.push(b"0123456789");
+        history.push(b"abcdef");
+        assert_eq!(history.total(), 16);
+
+        // Tail comes from memory
+        let tail = history.read(None, 8)?;
+        assert_eq!(tail.data, "89abcdef");
+        assert_eq!(tail.start, 8);
+
+        // Paging backwards reads the spilled part from disk
+        let older = history.read(Some(tail.start - 8), 8)?;
+        assert_eq!(older.data, "01234567");
+
+        // A range that straddles the file and the buffer
+        let middle = history.read(Some(4), 8)?;
+        assert_eq!(middle.data, "456789ab");
+
+        history.discard();
+        assert!(!path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_without_spill_old_output_is_dropped() -> Result<()> {
+        let mut history = OutputHistory::new(4, None);
+        history.push("héllo".as_bytes());
+
+        let chunk = history.read(Some(0), 100)?;
+        assert_eq!(chunk.start, 3);
+        assert_eq!(chunk.data, "llo");
+        assert_eq!(chunk.total, 6);
+
+        // A limit that ends mid-character stops before it
+        let mut history = OutputHistory::new(16, None);
+        history.push("aé".as_bytes());
+        let chunk = history.read(Some(0), 2)?;
+        assert_eq!(chunk.data, "a");
+        assert_eq!(chunk.end, 1);
+        Ok(())
+    }
+}
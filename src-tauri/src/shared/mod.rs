@@ -1 +1,4 @@
+pub mod events;
+pub mod history;
+pub mod pty_stream;
 pub mod utils;
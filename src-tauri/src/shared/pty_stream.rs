@@ -0,0 +1,231 @@
+use std::io::{Read, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const READ_BUFFER_BYTES: usize = 8 * 1024;
+
+/// The single writer portable-pty hands out per master. It has its own lock so a
+/// child that stops reading stdin only blocks writers to that child.
+pub type PtyWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct StreamConfig {
+    /// How long to keep collecting output after the first chunk of a batch.
+    pub window: Duration,
+    /// Flush early once a batch holds this much.
+    pub max_batch_bytes: usize,
+    /// Reads queued between the reader and the emitter. When full the reader stops
+    /// reading, the PTY buffer fills up and the child blocks on write.
+    pub queue_chunks: usize,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_millis(16), // About one frame
+            max_batch_bytes: 64 * 1024,
+            queue_chunks: 64,
+        }
+    }
+}
+
+/// Turns a byte stream into text without breaking characters that straddle two reads.
+#[derive(Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let mut text = String::new();
+        let mut rest: &[u8] = &self.pending;
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    rest = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        // Genuinely invalid bytes, replace them like from_utf8_lossy would
+                        Some(len) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        // A character cut off by the end of the read, wait for the rest
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.pending = rest.to_vec();
+        text
+    }
+
+    /// Whatever is left once the stream has ended.
+    pub fn finish(&mut self) -> String {
+        let text = String::from_utf8_lossy(&self.pending).to_string();
+        self.pending.clear();
+        text
+    }
+}
+
+/// Signals when a stream has delivered its last batch.
+pub struct StreamHandle {
+    done: Receiver<()>,
+}
+
+impl StreamHandle {
+    /// Waits for the reader to hit EOF and flush. Returns false on timeout.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        !matches!(self.done.recv_timeout(timeout), Err(RecvTimeoutError::Timeout))
+    }
+}
+
+/// Reads a PTY on one thread and hands decoded, batched text to `on_output` on another.
+pub fn stream_pty<R, F>(reader: R, config: StreamConfig, on_output: F) -> StreamHandle
+where
+    R: Read + Send + 'static,
+    F: FnMut(&str) + Send + 'static,
+{
+    stream_pty_with_end(reader, config, on_output, || {})
+}
+
+/// Like `stream_pty`, then calls `on_end` after the last batch, before `wait` returns.
+pub fn stream_pty_with_end<R, F, E>(mut reader: R, config: StreamConfig, mut on_output: F, on_end: E) -> StreamHandle
+where
+    R: Read + Send + 'static,
+    F: FnMut(&str) + Send + 'static,
+    E: FnOnce() + Send + 'static,
+{
+    let (chunk_tx, chunk_rx) = mpsc::sync_channel::<Vec<u8>>(config.queue_chunks.max(1));
+    let (done_tx, done_rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; READ_BUFFER_BYTES];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(n) if n > 0 => {
+                    // Blocks while the emitter is behind
+                    if chunk_tx.send(buffer[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => break, // EOF
+                Err(_) => break, // Error, EIO once the child side closes on Linux
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        let mut decoder = Utf8Decoder::default();
+        let mut ended = false;
+        while !ended {
+            let Ok(mut batch) = chunk_rx.recv() else {
+                break;
+            };
+            let deadline = Instant::now() + config.window;
+            while batch.len() < config.max_batch_bytes {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                match chunk_rx.recv_timeout(deadline - now) {
+                    Ok(chunk) => batch.extend_from_slice(&chunk),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        ended = true;
+                        break;
+                    }
+                }
+            }
+            let text = decoder.decode(&batch);
+            if !text.is_empty() {
+                on_output(&text);
+            }
+        }
+        let tail = decoder.finish();
+        if !tail.is_empty() {
+            on_output(&tail);
+        }
+        on_end();
+        let _ = done_tx.send(());
+    });
+
+    StreamHandle { done: done_rx }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    /// Hands out one scripted chunk per read, like a PTY does.
+    struct ChunkedReader(VecDeque<Vec<u8>>);
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some(chunk) = self.0.pop_front() else {
+                return Ok(0);
+            };
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_decoder_joins_split_characters() {
+        let bytes = "ok ✓ 🚀".as_bytes();
+        let mut decoder = Utf8Decoder::default();
+        // Split inside the check mark and inside the rocket
+        let mut text = decoder.decode(&bytes[..4]);
+        text += &decoder.decode(&bytes[4..9]);
+        text += &decoder.decode(&bytes[9..]);
+        assert_eq!(text, "ok ✓ 🚀");
+
+        assert_eq!(decoder.decode(b"a\xffb\xe2\x9c"), "a\u{fffd}b");
+        assert_eq!(decoder.finish(), "\u{fffd}");
+    }
+
+    #[test]
+    fn test_stream_batches_and_decodes() {
+        let bytes = "héllo wörld ✓\n".as_bytes().to_vec();
+        let chunks: VecDeque<Vec<u8>> = bytes.chunks(1).map(|c| c.to_vec()).collect();
+        let batches = Arc::new(Mutex::new(Vec::<String>::new()));
+        let batches_clone = batches.clone();
+
+        let config = StreamConfig { window: Duration::from_millis(200), ..Default::default() };
+        let handle = stream_pty(ChunkedReader(chunks), config, move |text| batches_clone.lock().unwrap().push(text.to_string()));
+        assert!(handle.wait(Duration::from_secs(5)));
+
+        let batches = batches.lock().unwrap();
+        assert_eq!(batches.concat(), "héllo wörld ✓\n");
+        assert!(batches.len() < 3, "expected batching, got {} events", batches.len());
+    }
+
+    #[test]
+    fn test_slow_consumer_loses_nothing() {
+        let chunks: VecDeque<Vec<u8>> = (0..200).map(|i| format!("{};", i).into_bytes()).collect();
+        let expected: String = (0..200).map(|i| format!("{};", i)).collect();
+        let received = Arc::new(Mutex::new(String::new()));
+        let received_clone = received.clone();
+
+        let config = StreamConfig { window: Duration::ZERO, max_batch_bytes: 4, queue_chunks: 1 };
+        let handle = stream_pty(ChunkedReader(chunks), config, move |text| {
+            thread::sleep(Duration::from_millis(1));
+            received_clone.lock().unwrap().push_str(text);
+        });
+        assert!(handle.wait(Duration::from_secs(10)));
+        assert_eq!(*received.lock().unwrap(), expected);
+    }
+}
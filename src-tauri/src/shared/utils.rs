@@ -15,3 +15,11 @@ pub fn expand_path(path: &str) -> String {
     }
     path.to_string()
 }
+
+/// Milliseconds since the Unix epoch, used for timestamps that never touch SQLite.
+pub fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -1,17 +1,20 @@
-import React from 'react';
-import { LayoutGrid, Plus, FolderInput, GitBranch, ChevronUp } from 'lucide-react';
+import React, { useState } from 'react';
+import { LayoutGrid, Plus, FolderInput, GitBranch, ChevronUp, KeyRound } from 'lucide-react';
 import {
   DropdownMenu,
   DropdownMenuContent,
   DropdownMenuItem,
   DropdownMenuTrigger,
 } from "./ui/dropdown-menu"
+import { SshKeysDialog } from './Terminal/SshKeysDialog';
 
 interface SidebarProps {
   onNewProject: (mode: 'create' | 'import' | 'clone') => void;
 }
 
 export const Sidebar: React.FC<SidebarProps> = ({ onNewProject }) => {
+  const [isKeysOpen, setIsKeysOpen] = useState(false);
+
   return (
     <div className="w-64 border-r border-border bg-card flex flex-col h-full">
       <div className="p-6">
@@ -28,8 +31,17 @@ export const Sidebar: React.FC<SidebarProps> = ({ onNewProject }) => {
           <LayoutGrid className="w-4 h-4" />
           Projects
         </div>
+        <button
+          className="w-full flex items-center gap-3 px-3 py-2 text-sm font-medium rounded-md text-muted-foreground hover:bg-accent hover:text-accent-foreground transition-colors"
+          onClick={() => setIsKeysOpen(true)}
+        >
+          <KeyRound className="w-4 h-4" />
+          SSH Keys
+        </button>
       </nav>
 
+      <SshKeysDialog open={isKeysOpen} onOpenChange={setIsKeysOpen} />
+
       <div className="p-4 border-t border-border">
         <DropdownMenu>
             <DropdownMenuTrigger asChild>
@@ -0,0 +1,56 @@
+import React, { useState } from 'react';
+import { Circle, Square } from 'lucide-react';
+import { toast } from 'sonner';
+import { invokeCommand } from '../../lib/tauri';
+
+export interface RecordingInfo {
+    id: string;
+    project_id: string | null;
+    title: string | null;
+    width: number;
+    height: number;
+    timestamp: number;
+    duration: number;
+    size_bytes: number;
+}
+
+interface RecordButtonProps {
+    sessionId: string | null; // Terminal session or managed process id
+}
+
+/** Toggles an asciicast recording of a terminal or process session. */
+export const RecordButton: React.FC<RecordButtonProps> = ({ sessionId }) => {
+    const [recording, setRecording] = useState(false);
+
+    const toggle = async () => {
+        if (!sessionId) return;
+        try {
+            if (recording) {
+                const info = await invokeCommand<RecordingInfo>('stop_recording', { sessionId });
+                setRecording(false);
+                toast.success(`Recording saved (${info.duration.toFixed(1)}s)`);
+            } else {
+                await invokeCommand<RecordingInfo>('start_recording', { sessionId });
+                setRecording(true);
+            }
+        } catch (err) {
+            setRecording(false);
+            toast.error(`Recording failed: ${err}`);
+        }
+    };
+
+    return (
+        <button
+            type="button"
+            onClick={toggle}
+            disabled={!sessionId}
+            title={recording ? 'Stop recording' : 'Record session'}
+            className={`absolute top-2 right-3 z-10 flex items-center gap-1 rounded px-1.5 py-0.5 text-[10px] font-mono transition-colors ${
+                recording ? 'bg-red-500/20 text-red-400' : 'text-zinc-500 hover:text-zinc-300 hover:bg-zinc-800'
+            } disabled:opacity-40`}
+        >
+            {recording ? <Square className="w-3 h-3 fill-current" /> : <Circle className="w-3 h-3" />}
+            {recording ? 'REC' : 'Record'}
+        </button>
+    );
+};
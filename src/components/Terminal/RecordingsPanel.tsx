@@ -0,0 +1,138 @@
+import React, { useEffect, useRef, useState } from 'react';
+import { Terminal } from '@xterm/xterm';
+import { Film, Play, Trash2, RefreshCw } from 'lucide-react';
+import { toast } from 'sonner';
+import { Button } from '../ui/button';
+import { invokeCommand } from '../../lib/tauri';
+import type { RecordingInfo } from './RecordButton';
+import '@xterm/xterm/css/xterm.css';
+
+interface Recording {
+    info: RecordingInfo;
+    events: { time: number; code: string; data: string }[];
+}
+
+interface RecordingsPanelProps {
+    projectId: string;
+}
+
+const formatDuration = (seconds: number) => {
+    const m = Math.floor(seconds / 60);
+    const s = Math.floor(seconds % 60);
+    return `${m}:${s.toString().padStart(2, '0')}`;
+};
+
+export const RecordingsPanel: React.FC<RecordingsPanelProps> = ({ projectId }) => {
+    const [recordings, setRecordings] = useState<RecordingInfo[]>([]);
+    const [playing, setPlaying] = useState<string | null>(null);
+    const containerRef = useRef<HTMLDivElement>(null);
+    const termRef = useRef<Terminal | null>(null);
+    const timersRef = useRef<number[]>([]);
+
+    const load = () => {
+        invokeCommand<RecordingInfo[]>('list_recordings', { projectId })
+            .then(setRecordings)
+            .catch(err => toast.error(`Failed to load recordings: ${err}`));
+    };
+
+    useEffect(load, [projectId]);
+
+    const stopPlayback = () => {
+        timersRef.current.forEach(t => window.clearTimeout(t));
+        timersRef.current = [];
+        termRef.current?.dispose();
+        termRef.current = null;
+    };
+
+    useEffect(() => stopPlayback, []);
+
+    const play = async (id: string) => {
+        stopPlayback();
+        if (!containerRef.current) return;
+        try {
+            const recording = await invokeCommand<Recording>('get_recording', { id });
+            const term = new Terminal({
+                cols: recording.info.width,
+                rows: recording.info.height,
+                fontFamily: '"JetBrains Mono", Menlo, Consolas, monospace',
+                fontSize: 12,
+                disableStdin: true,
+            });
+            term.open(containerRef.current);
+            termRef.current = term;
+            setPlaying(id);
+
+            // Replay in real time, like asciinema does without an idle limit
+            for (const event of recording.events) {
+                const timer = window.setTimeout(() => {
+                    if (event.code === 'o') {
+                        term.write(event.data);
+                    } else if (event.code === 'r') {
+                        const [cols, rows] = event.data.split('x').map(Number);
+                        if (cols && rows) term.resize(cols, rows);
+                    }
+                }, event.time * 1000);
+                timersRef.current.push(timer);
+            }
+        } catch (err) {
+            toast.error(`Failed to play recording: ${err}`);
+        }
+    };
+
+    const remove = async (id: string) => {
+        try {
+            await invokeCommand('delete_recording', { id });
+            if (playing === id) {
+                stopPlayback();
+                setPlaying(null);
+            }
+            load();
+        } catch (err) {
+            toast.error(`Failed to delete recording: ${err}`);
+        }
+    };
+
+    return (
+        <div className="h-full flex">
+            <div className="w-72 border-r border-border flex flex-col">
+                <div className="flex items-center justify-between px-3 py-2 border-b border-border">
+                    <span className="text-xs font-medium text-muted-foreground flex items-center gap-1.5">
+                        <Film className="w-3.5 h-3.5" /> Recordings
+                    </span>
+                    <Button variant="ghost" size="icon" className="h-6 w-6" onClick={load}>
+                        <RefreshCw className="w-3 h-3" />
+                    </Button>
+                </div>
+                <div className="flex-1 overflow-auto">
+                    {recordings.length === 0 && (
+                        <div className="p-4 text-xs text-muted-foreground">
+                            No recordings yet. Use Record on a terminal or process to capture one.
+                        </div>
+                    )}
+                    {recordings.map(r => (
+                        <div
+                            key={r.id}
+                            className={`group flex items-center gap-2 px-3 py-2 text-xs border-b border-border/40 ${playing === r.id ? 'bg-muted/50' : ''}`}
+                        >
+                            <button type="button" className="flex-1 text-left min-w-0" onClick={() => play(r.id)}>
+                                <div className="font-mono truncate">{r.title ?? r.id}</div>
+                                <div className="text-muted-foreground">
+                                    {new Date(r.timestamp * 1000).toLocaleString()} · {formatDuration(r.duration)}
+                                </div>
+                            </button>
+                            <Button variant="ghost" size="icon" className="h-6 w-6" onClick={() => play(r.id)}>
+                                <Play className="w-3 h-3" />
+                            </Button>
+                            <Button variant="ghost" size="icon" className="h-6 w-6 opacity-0 group-hover:opacity-100" onClick={() => remove(r.id)}>
+                                <Trash2 className="w-3 h-3 text-red-400" />
+                            </Button>
+                        </div>
+                    ))}
+                </div>
+            </div>
+            <div className="flex-1 bg-zinc-950 p-2 overflow-auto">
+                <div ref={containerRef} />
+            </div>
+        </div>
+    );
+};
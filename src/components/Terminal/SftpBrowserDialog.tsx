@@ -0,0 +1,273 @@
+import React, { useEffect, useState } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { open, save } from '@tauri-apps/plugin-dialog';
+import { Button } from '../ui/button';
+import { Input } from '../ui/input';
+import { Progress } from '../ui/progress';
+import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogDescription } from '../ui/dialog';
+import { ArrowUp, Bookmark, Download, File, Folder, FolderPlus, Link2, Pencil, RefreshCw, Trash2, Upload } from 'lucide-react';
+import { invokeCommand } from '../../lib/tauri';
+import { toast } from 'sonner';
+import type { SftpEntry, SftpProgress } from '../../types';
+
+interface SftpBrowserDialogProps {
+    open: boolean;
+    onOpenChange: (open: boolean) => void;
+    host: string;
+    initialPath?: string;
+    projectId?: string; // Enables bookmarking the current folder as a link
+    onBookmarked?: () => void;
+}
+
+const joinPath = (dir: string, name: string) => `${dir.replace(/\/+$/, '')}/${name}`;
+const parentPath = (path: string) => path.replace(/\/+$/, '').replace(/\/[^/]*$/, '') || '/';
+
+const formatSize = (size: number | null) => {
+    if (size === null) return '';
+    if (size < 1024) return `${size} B`;
+    const units = ['KB', 'MB', 'GB', 'TB'];
+    let value = size / 1024;
+    let unit = 0;
+    while (value >= 1024 && unit < units.length - 1) {
+        value /= 1024;
+        unit++;
+    }
+    return `${value.toFixed(1)} ${units[unit]}`;
+};
+
+export const SftpBrowserDialog: React.FC<SftpBrowserDialogProps> = ({ open: isOpen, onOpenChange, host, initialPath, projectId, onBookmarked }) => {
+    const [cwd, setCwd] = useState('');
+    const [pathInput, setPathInput] = useState('');
+    const [entries, setEntries] = useState<SftpEntry[]>([]);
+    const [isLoading, setIsLoading] = useState(false);
+    const [password, setPassword] = useState<string | null>(null);
+    const [transfers, setTransfers] = useState<Record<string, SftpProgress>>({});
+
+    // Asks for a password once if key and agent auth are refused
+    const call = async <T,>(command: string, args: Record<string, unknown>): Promise<T> => {
+        try {
+            return await invokeCommand<T>(command, { host, password, ...args });
+        } catch (e) {
+            if (password === null && String(e).startsWith('Authentication failed')) {
+                const entered = window.prompt(`Password for ${host}`);
+                if (entered !== null) {
+                    setPassword(entered);
+                    return await invokeCommand<T>(command, { host, password: entered, ...args });
+                }
+            }
+            throw e;
+        }
+    };
+
+    const load = async (path: string) => {
+        setIsLoading(true);
+        try {
+            // Resolves ~ and tells folders from files, which open their folder instead
+            const target = await call<SftpEntry>('sftp_stat', { path });
+            const dir = target.kind === 'dir' ? target.path : parentPath(target.path);
+            setEntries(await call<SftpEntry[]>('sftp_list_dir', { path: dir }));
+            setCwd(dir);
+            setPathInput(dir);
+        } catch (e) {
+            toast.error(`${e}`);
+            setPathInput(cwd);
+        } finally {
+            setIsLoading(false);
+        }
+    };
+
+    useEffect(() => {
+        if (!isOpen) return;
+        load(initialPath || '~');
+
+        const unlisten = listen<SftpProgress>('sftp_progress', (event) => {
+            if (event.payload.host !== host) return;
+            setTransfers(prev => ({ ...prev, [event.payload.transfer_id]: event.payload }));
+        });
+        return () => {
+            unlisten.then(f => f());
+            invokeCommand('sftp_disconnect', { host }).catch(console.error);
+        };
+    }, [isOpen, host, initialPath]);
+
+    const finishTransfer = (transferId: string) => {
+        setTransfers(prev => {
+            const { [transferId]: _, ...rest } = prev;
+            return rest;
+        });
+    };
+
+    const handleDownload = async (entry: SftpEntry) => {
+        const localPath = await save({ defaultPath: entry.name });
+        if (!localPath) return;
+        const transferId = crypto.randomUUID();
+        try {
+            await call('sftp_download', { transferId, remotePath: entry.path, localPath });
+            toast.success(`Downloaded ${entry.name}`);
+        } catch (e) {
+            toast.error(`Download failed: ${e}`);
+        } finally {
+            finishTransfer(transferId);
+        }
+    };
+
+    const handleUpload = async () => {
+        const selected = await open({ multiple: false, directory: false });
+        if (!selected || typeof selected !== 'string') return;
+        const name = selected.split(/[\\/]/).pop() ?? selected;
+        const remotePath = joinPath(cwd, name);
+        if (entries.some(e => e.name === name) && !window.confirm(`Replace ${remotePath}?`)) return;
+        const transferId = crypto.randomUUID();
+        try {
+            await call('sftp_upload', { transferId, localPath: selected, remotePath });
+            toast.success(`Uploaded ${name}`);
+            load(cwd);
+        } catch (e) {
+            toast.error(`Upload failed: ${e}`);
+        } finally {
+            finishTransfer(transferId);
+        }
+    };
+
+    const handleRename = async (entry: SftpEntry) => {
+        const name = window.prompt('Rename to', entry.name);
+        if (!name || name === entry.name) return;
+        const to = name.startsWith('/') ? name : joinPath(cwd, name);
+        const overwrite = entries.some(e => e.path === to);
+        if (overwrite && !window.confirm(`Replace ${to}?`)) return;
+        try {
+            await call('sftp_rename', { from: entry.path, to, overwrite });
+            load(cwd);
+        } catch (e) {
+            toast.error(`Rename failed: ${e}`);
+        }
+    };
+
+    const handleDelete = async (entry: SftpEntry) => {
+        if (!window.confirm(`Delete ${entry.path} on ${host}?`)) return;
+        try {
+            await call('sftp_delete', { path: entry.path });
+            load(cwd);
+        } catch (e) {
+            toast.error(`Delete failed: ${e}`);
+        }
+    };
+
+    const handleMkdir = async () => {
+        const name = window.prompt('New folder name');
+        if (!name) return;
+        try {
+            await call('sftp_mkdir', { path: joinPath(cwd, name) });
+            load(cwd);
+        } catch (e) {
+            toast.error(`Could not create folder: ${e}`);
+        }
+    };
+
+    const handleBookmark = async () => {
+        if (!projectId) return;
+        try {
+            await invokeCommand('add_project_link', {
+                projectId,
+                title: `${host}:${cwd.split('/').pop() || '/'}`,
+                url: `sftp://${host}${cwd}`,
+                icon: null,
+                kind: 'sftp',
+                working_directory: null,
+            });
+            toast.success("Bookmarked");
+            onBookmarked?.();
+        } catch (e) {
+            toast.error(`Failed to bookmark: ${e}`);
+        }
+    };
+
+    return (
+        <Dialog open={isOpen} onOpenChange={onOpenChange}>
+            <DialogContent className="max-w-3xl">
+                <DialogHeader>
+                    <DialogTitle className="flex items-center gap-2">
+                        <Link2 className="w-5 h-5 text-primary" />
+                        {host}
+                    </DialogTitle>
+                    <DialogDescription>Browse and transfer files over SFTP.</DialogDescription>
+                </DialogHeader>
+
+                <div className="flex items-center gap-1">
+                    <Button variant="ghost" size="icon" className="h-8 w-8" title="Up" onClick={() => load(parentPath(cwd))} disabled={cwd === '/'}>
+                        <ArrowUp className="w-4 h-4" />
+                    </Button>
+                    <Input
+                        value={pathInput}
+                        onChange={e => setPathInput(e.target.value)}
+                        onKeyDown={e => { if (e.key === 'Enter') load(pathInput); }}
+                        className="font-mono text-xs h-8 flex-1"
+                    />
+                    <Button variant="ghost" size="icon" className="h-8 w-8" title="Refresh" onClick={() => load(cwd)}>
+                        <RefreshCw className={`w-4 h-4 ${isLoading ? 'animate-spin' : ''}`} />
+                    </Button>
+                    <Button variant="ghost" size="icon" className="h-8 w-8" title="New folder" onClick={handleMkdir}>
+                        <FolderPlus className="w-4 h-4" />
+                    </Button>
+                    <Button variant="ghost" size="icon" className="h-8 w-8" title="Upload" onClick={handleUpload}>
+                        <Upload className="w-4 h-4" />
+                    </Button>
+                    {projectId && (
+                        <Button variant="ghost" size="icon" className="h-8 w-8" title="Bookmark this folder" onClick={handleBookmark}>
+                            <Bookmark className="w-4 h-4" />
+                        </Button>
+                    )}
+                </div>
+
+                {Object.values(transfers).map(t => (
+                    <div key={t.transfer_id} className="space-y-1">
+                        <div className="text-xs text-muted-foreground flex justify-between">
+                            <span className="truncate">{t.direction === 'download' ? 'Downloading' : 'Uploading'} {t.remote_path}</span>
+                            <span>{formatSize(t.transferred)}{t.total !== null ? ` / ${formatSize(t.total)}` : ''}</span>
+                        </div>
+                        <Progress className="h-1.5" value={t.total ? (t.transferred / t.total) * 100 : 0} />
+                    </div>
+                ))}
+
+                <div className="h-[50vh] overflow-y-auto border rounded-md divide-y divide-border">
+                    {entries.length === 0 && !isLoading && (
+                        <p className="text-center text-xs text-muted-foreground py-8">Empty folder</p>
+                    )}
+                    {entries.map(entry => (
+                        <div
+                            key={entry.path}
+                            className="group flex items-center justify-between gap-4 px-3 py-1.5 text-sm hover:bg-accent/50 cursor-default"
+                            onDoubleClick={() => entry.kind === 'file' ? handleDownload(entry) : load(entry.path)}
+                        >
+                            <div className="flex items-center gap-2 min-w-0">
+                                {entry.kind === 'dir'
+                                    ? <Folder className="w-4 h-4 text-primary shrink-0" />
+                                    : <File className="w-4 h-4 text-muted-foreground shrink-0" />}
+                                <span className={`truncate ${entry.kind === 'symlink' ? 'italic' : ''}`}>{entry.name}</span>
+                            </div>
+                            <div className="flex items-center gap-3 shrink-0">
+                                <span className="text-xs text-muted-foreground w-20 text-right">{entry.kind === 'file' ? formatSize(entry.size) : ''}</span>
+                                <span className="text-xs text-muted-foreground w-36 text-right">
+                                    {entry.modified ? new Date(entry.modified * 1000).toLocaleString() : ''}
+                                </span>
+                                <div className="flex items-center opacity-0 group-hover:opacity-100">
+                                    {entry.kind !== 'dir' && (
+                                        <Button variant="ghost" size="icon" className="h-7 w-7" title="Download" onClick={() => handleDownload(entry)}>
+                                            <Download className="w-3.5 h-3.5" />
+                                        </Button>
+                                    )}
+                                    <Button variant="ghost" size="icon" className="h-7 w-7" title="Rename" onClick={() => handleRename(entry)}>
+                                        <Pencil className="w-3.5 h-3.5" />
+                                    </Button>
+                                    <Button variant="ghost" size="icon" className="h-7 w-7" title="Delete" onClick={() => handleDelete(entry)}>
+                                        <Trash2 className="w-3.5 h-3.5 text-red-400" />
+                                    </Button>
+                                </div>
+                            </div>
+                        </div>
+                    ))}
+                </div>
+            </DialogContent>
+        </Dialog>
+    );
+};
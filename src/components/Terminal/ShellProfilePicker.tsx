@@ -0,0 +1,94 @@
+import React, { useState } from 'react';
+import { ChevronDown, Plus, TerminalSquare } from 'lucide-react';
+import { toast } from 'sonner';
+import { invokeCommand } from '../../lib/tauri';
+import type { ShellProfile, ShellProfileInput } from '../../types';
+import {
+    DropdownMenu,
+    DropdownMenuContent,
+    DropdownMenuItem,
+    DropdownMenuLabel,
+    DropdownMenuSeparator,
+    DropdownMenuTrigger,
+} from '../ui/dropdown-menu';
+
+interface ShellProfilePickerProps {
+    projectId: string;
+    onPick: (profile: ShellProfile) => void;
+}
+
+/** "New terminal" menu listing the project's shell profiles and the shells found on this machine. */
+export const ShellProfilePicker: React.FC<ShellProfilePickerProps> = ({ projectId, onPick }) => {
+    const [profiles, setProfiles] = useState<ShellProfile[]>([]);
+    const [detected, setDetected] = useState<ShellProfileInput[]>([]);
+
+    // Loaded on open, profiles may have changed since the last time
+    const load = async (open: boolean) => {
+        if (!open) return;
+        try {
+            const [saved, shells] = await Promise.all([
+                invokeCommand<ShellProfile[]>('get_shell_profiles', { projectId }),
+                invokeCommand<ShellProfileInput[]>('detect_shells'),
+            ]);
+            setProfiles(saved);
+            setDetected(shells.filter(shell => !saved.some(p => p.program === shell.program)));
+        } catch (err) {
+            toast.error(`Failed to load shell profiles: ${err}`);
+        }
+    };
+
+    // A detected shell becomes a profile first, spawn_shell only takes profile ids
+    const pickDetected = async (shell: ShellProfileInput) => {
+        try {
+            const profile = await invokeCommand<ShellProfile>('create_shell_profile', {
+                projectId,
+                profile: { ...shell, is_default: profiles.length === 0 },
+            });
+            onPick(profile);
+        } catch (err) {
+            toast.error(`Failed to save ${shell.name} as a profile: ${err}`);
+        }
+    };
+
+    return (
+        <DropdownMenu onOpenChange={load}>
+            <DropdownMenuTrigger asChild>
+                <button
+                    type="button"
+                    title="New terminal"
+                    className="absolute top-2 right-20 z-10 flex items-center gap-1 rounded px-1.5 py-0.5 text-[10px] font-mono text-zinc-500 hover:text-zinc-300 hover:bg-zinc-800 transition-colors"
+                >
+                    <Plus className="w-3 h-3" />
+                    New
+                    <ChevronDown className="w-3 h-3 opacity-50" />
+                </button>
+            </DropdownMenuTrigger>
+            <DropdownMenuContent align="end" className="w-56">
+                <DropdownMenuLabel className="text-xs">Profiles</DropdownMenuLabel>
+                {profiles.length === 0 && (
+                    <div className="px-2 py-1.5 text-xs text-muted-foreground">No saved profiles yet</div>
+                )}
+                {profiles.map(profile => (
+                    <DropdownMenuItem key={profile.id} onClick={() => onPick(profile)}>
+                        <TerminalSquare className="w-4 h-4 mr-2" />
+                        <span className="flex-1 truncate">{profile.name}</span>
+                        {profile.is_default && <span className="text-[10px] text-muted-foreground">default</span>}
+                    </DropdownMenuItem>
+                ))}
+                {detected.length > 0 && (
+                    <>
+                        <DropdownMenuSeparator />
+                        <DropdownMenuLabel className="text-xs">Detected shells</DropdownMenuLabel>
+                        {detected.map(shell => (
+                            <DropdownMenuItem key={shell.program} onClick={() => pickDetected(shell)}>
+                                <Plus className="w-4 h-4 mr-2" />
+                                <span className="flex-1 truncate">{shell.name}</span>
+                                <span className="text-[10px] text-muted-foreground font-mono truncate">{shell.program}</span>
+                            </DropdownMenuItem>
+                        ))}
+                    </>
+                )}
+            </DropdownMenuContent>
+        </DropdownMenu>
+    );
+};
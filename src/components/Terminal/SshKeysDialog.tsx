@@ -0,0 +1,191 @@
+import React, { useEffect, useState } from 'react';
+import { Button } from '../ui/button';
+import { Input } from '../ui/input';
+import { Label } from '../ui/label';
+import { Card, CardContent } from '../ui/card';
+import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogDescription, DialogFooter } from '../ui/dialog';
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '../ui/select';
+import { Copy, KeyRound, Lock, Plus, Upload, X } from 'lucide-react';
+import { invokeCommand } from '../../lib/tauri';
+import { toast } from 'sonner';
+import type { SshKeyInfo, SshKeyType } from '../../types';
+
+interface SshKeysDialogProps {
+    open: boolean;
+    onOpenChange: (open: boolean) => void;
+}
+
+export const SshKeysDialog: React.FC<SshKeysDialogProps> = ({ open, onOpenChange }) => {
+    const [keys, setKeys] = useState<SshKeyInfo[]>([]);
+    const [isGenerateOpen, setIsGenerateOpen] = useState(false);
+    const [isGenerating, setIsGenerating] = useState(false);
+
+    // Form State
+    const [name, setName] = useState('id_ed25519');
+    const [keyType, setKeyType] = useState<SshKeyType>('ed25519');
+    const [comment, setComment] = useState('');
+    const [passphrase, setPassphrase] = useState('');
+
+    const fetchKeys = async () => {
+        try {
+            setKeys(await invokeCommand<SshKeyInfo[]>('list_ssh_keys'));
+        } catch (e) {
+            console.error(e);
+            toast.error("Failed to load SSH keys");
+        }
+    };
+
+    useEffect(() => {
+        if (open) fetchKeys();
+    }, [open]);
+
+    const resetForm = () => {
+        setName('id_ed25519');
+        setKeyType('ed25519');
+        setComment('');
+        setPassphrase('');
+    };
+
+    const handleGenerate = async () => {
+        setIsGenerating(true);
+        try {
+            await invokeCommand<SshKeyInfo>('generate_ssh_key', {
+                input: {
+                    name,
+                    key_type: keyType,
+                    bits: null,
+                    comment: comment || null,
+                    passphrase: passphrase || null,
+                },
+            });
+            toast.success(`Generated ${name}`);
+            setIsGenerateOpen(false);
+            resetForm();
+            fetchKeys();
+        } catch (e) {
+            toast.error(`Failed to generate key: ${e}`);
+        } finally {
+            setIsGenerating(false);
+        }
+    };
+
+    const handleCopy = async (key: SshKeyInfo) => {
+        try {
+            const publicKey = await invokeCommand<string>('get_public_key', { path: key.path });
+            await navigator.clipboard.writeText(publicKey);
+            toast.success("Public key copied");
+        } catch (e) {
+            toast.error(`Failed to copy public key: ${e}`);
+        }
+    };
+
+    const handleAgent = async (key: SshKeyInfo) => {
+        try {
+            if (key.in_agent) {
+                await invokeCommand('remove_key_from_agent', { path: key.path });
+            } else {
+                // Without a saved passphrase, ask for it once
+                const passphrase = key.encrypted && !key.passphrase_saved
+                    ? window.prompt(`Passphrase for ${key.name}`)
+                    : null;
+                if (key.encrypted && !key.passphrase_saved && passphrase === null) return;
+                await invokeCommand('add_key_to_agent', { path: key.path, passphrase });
+            }
+            fetchKeys();
+        } catch (e) {
+            toast.error(`ssh-agent: ${e}`);
+        }
+    };
+
+    return (
+        <Dialog open={open} onOpenChange={onOpenChange}>
+            <DialogContent className="max-w-2xl">
+                <DialogHeader>
+                    <DialogTitle className="flex items-center gap-2">
+                        <KeyRound className="w-5 h-5 text-primary" />
+                        SSH Keys
+                    </DialogTitle>
+                    <DialogDescription>
+                        Key pairs in ~/.ssh. Passphrases of generated keys are kept in the system keychain.
+                    </DialogDescription>
+                </DialogHeader>
+
+                {isGenerateOpen ? (
+                    <div className="grid grid-cols-2 gap-4 py-2">
+                        <div className="space-y-2">
+                            <Label>File Name</Label>
+                            <Input value={name} onChange={e => setName(e.target.value)} />
+                        </div>
+                        <div className="space-y-2">
+                            <Label>Type</Label>
+                            <Select value={keyType} onValueChange={(v) => setKeyType(v as SshKeyType)}>
+                                <SelectTrigger><SelectValue /></SelectTrigger>
+                                <SelectContent>
+                                    <SelectItem value="ed25519">Ed25519</SelectItem>
+                                    <SelectItem value="rsa">RSA 4096</SelectItem>
+                                </SelectContent>
+                            </Select>
+                        </div>
+                        <div className="space-y-2">
+                            <Label>Comment</Label>
+                            <Input placeholder="you@machine" value={comment} onChange={e => setComment(e.target.value)} />
+                        </div>
+                        <div className="space-y-2">
+                            <Label>Passphrase</Label>
+                            <Input type="password" placeholder="Optional" value={passphrase} onChange={e => setPassphrase(e.target.value)} />
+                        </div>
+                    </div>
+                ) : (
+                    <div className="max-h-[50vh] overflow-y-auto space-y-2 p-1">
+                        {keys.length === 0 && (
+                            <div className="text-center py-12 text-muted-foreground border-2 border-dashed rounded-lg">
+                                <KeyRound className="w-8 h-8 mx-auto mb-2 opacity-50" />
+                                <p>No key pairs found in ~/.ssh.</p>
+                            </div>
+                        )}
+                        {keys.map(key => (
+                            <Card key={key.path}>
+                                <CardContent className="p-3 flex items-center justify-between gap-4">
+                                    <div className="flex flex-col min-w-0">
+                                        <span className="font-medium text-sm flex items-center gap-2">
+                                            {key.name}
+                                            {key.encrypted && <Lock className="w-3 h-3 text-muted-foreground" />}
+                                            <span className="text-xs text-muted-foreground">{key.key_type} {key.bits}</span>
+                                            {key.in_agent && <span className="text-xs text-green-500">in agent</span>}
+                                        </span>
+                                        <span className="text-xs text-muted-foreground font-mono truncate">{key.fingerprint}</span>
+                                        {key.comment && <span className="text-xs text-muted-foreground truncate">{key.comment}</span>}
+                                    </div>
+                                    <div className="flex items-center gap-1 shrink-0">
+                                        <Button variant="ghost" size="icon" className="h-8 w-8" title="Copy public key" onClick={() => handleCopy(key)}>
+                                            <Copy className="w-4 h-4" />
+                                        </Button>
+                                        <Button variant="ghost" size="icon" className="h-8 w-8" title={key.in_agent ? "Remove from agent" : "Add to agent"} onClick={() => handleAgent(key)}>
+                                            {key.in_agent ? <X className="w-4 h-4" /> : <Upload className="w-4 h-4" />}
+                                        </Button>
+                                    </div>
+                                </CardContent>
+                            </Card>
+                        ))}
+                    </div>
+                )}
+
+                <DialogFooter>
+                    {isGenerateOpen ? (
+                        <>
+                            <Button variant="outline" onClick={() => setIsGenerateOpen(false)}>Cancel</Button>
+                            <Button onClick={handleGenerate} disabled={!name || isGenerating}>
+                                {isGenerating ? 'Generating...' : 'Generate'}
+                            </Button>
+                        </>
+                    ) : (
+                        <Button className="gap-1" onClick={() => setIsGenerateOpen(true)}>
+                            <Plus className="w-4 h-4" />
+                            Generate Key
+                        </Button>
+                    )}
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+};
@@ -0,0 +1,128 @@
+import React, { useEffect, useRef } from 'react';
+import { Terminal } from '@xterm/xterm';
+import { FitAddon } from '@xterm/addon-fit';
+import { listen } from '@tauri-apps/api/event';
+import { invokeCommand } from '../../lib/tauri';
+import type { SshCommandError } from '../../types';
+import '@xterm/xterm/css/xterm.css';
+
+interface SshTerminalPanelProps {
+    host: string; // Alias from ~/.ssh/config
+    onSessionReady?: (sessionId: string) => void;
+    onExit?: () => void;
+}
+
+type SshStatus = 'connected' | 'reconnecting' | 'closed';
+
+export const SshTerminalPanel: React.FC<SshTerminalPanelProps> = ({ host, onSessionReady, onExit }) => {
+    const containerRef = useRef<HTMLDivElement>(null);
+    const sessionIdRef = useRef<string | null>(null);
+
+    useEffect(() => {
+        if (!containerRef.current) return;
+
+        const term = new Terminal({
+            cursorBlink: true,
+            fontFamily: '"JetBrains Mono", Menlo, Consolas, monospace',
+            fontSize: 13,
+            lineHeight: 1.2,
+            theme: { background: '#18181b', foreground: '#fafafa' },
+            allowProposedApi: true,
+        });
+        const fitAddon = new FitAddon();
+        term.loadAddon(fitAddon);
+        term.open(containerRef.current);
+        fitAddon.fit();
+
+        term.onData(data => {
+            if (sessionIdRef.current) {
+                invokeCommand('ssh_write', { sessionId: sessionIdRef.current, data }).catch(console.error);
+            }
+        });
+
+        const resizeObserver = new ResizeObserver(() => {
+            try {
+                fitAddon.fit();
+                if (sessionIdRef.current) {
+                    invokeCommand('ssh_resize', { sessionId: sessionIdRef.current, cols: term.cols, rows: term.rows })
+                        .catch(console.error);
+                }
+            } catch (e) { console.error(e); }
+        });
+        resizeObserver.observe(containerRef.current);
+
+        let disposed = false;
+        const unlisteners: (() => void)[] = [];
+
+        const connect = async (): Promise<string> => {
+            try {
+                return await invokeCommand<string>('ssh_connect', { host, cols: term.cols, rows: term.rows });
+            } catch (err) {
+                const { kind, fingerprint, message } = err as SshCommandError;
+                if (kind !== 'unknown_host_key' || !fingerprint) throw new Error(message);
+                // First connection to this host: show the fingerprint, like ssh does
+                if (!window.confirm(`The authenticity of host '${host}' can't be established.\nKey fingerprint is ${fingerprint}.\n\nTrust this host and continue connecting?`)) {
+                    throw new Error(message);
+                }
+                // Refused if the server presents a different key than the one confirmed here
+                await invokeCommand<string>('trust_ssh_host', { host, fingerprint });
+                return await invokeCommand<string>('ssh_connect', { host, cols: term.cols, rows: term.rows });
+            }
+        };
+
+        const init = async () => {
+            term.writeln(`\x1b[38;5;75m⚡ Connecting to ${host}...\x1b[0m\r\n`);
+            unlisteners.push(await listen<{ session_id: string, data: string }>('ssh_data', (event) => {
+                if (event.payload.session_id === sessionIdRef.current) term.write(event.payload.data);
+            }));
+            unlisteners.push(await listen<{ session_id: string, status: SshStatus, message: string | null }>('ssh_status', (event) => {
+                if (event.payload.session_id !== sessionIdRef.current) return;
+                const { status, message } = event.payload;
+                if (status === 'reconnecting') {
+                    term.writeln(`\r\n\x1b[33m[Connection lost${message ? `: ${message}` : ''}, reconnecting...]\x1b[0m`);
+                } else if (status === 'connected') {
+                    term.writeln('\r\n\x1b[32m[Reconnected]\x1b[0m');
+                }
+            }));
+            unlisteners.push(await listen<{ session_id: string, exit_status: number | null, reason: string | null }>('ssh_exit', (event) => {
+                if (event.payload.session_id !== sessionIdRef.current) return;
+                const { exit_status, reason } = event.payload;
+                term.writeln(`\r\n\x1b[38;5;245m[${reason ? `Disconnected: ${reason}` : `Connection closed${exit_status === null ? '' : ` (exit ${exit_status})`}`}]\x1b[0m`);
+                sessionIdRef.current = null;
+                onExit?.();
+            }));
+
+            try {
+                const sessionId = await connect();
+                if (disposed) {
+                    invokeCommand('ssh_disconnect', { sessionId }).catch(console.error);
+                    return;
+                }
+                sessionIdRef.current = sessionId;
+                onSessionReady?.(sessionId);
+                term.focus();
+            } catch (err) {
+                const message = err instanceof Error ? err.message : (err as SshCommandError).message;
+                term.writeln(`\r\n\x1b[31mConnection failed: ${message}\x1b[0m`);
+            }
+        };
+        init();
+
+        return () => {
+            disposed = true;
+            resizeObserver.disconnect();
+            unlisteners.forEach(u => u());
+            if (sessionIdRef.current) {
+                invokeCommand('ssh_disconnect', { sessionId: sessionIdRef.current }).catch(console.error);
+                sessionIdRef.current = null;
+            }
+            term.dispose();
+        };
+    }, [host]);
+
+    return (
+        <div className="h-full w-full bg-[#18181b] p-1 relative rounded-md border border-zinc-800">
+            <div ref={containerRef} className="absolute inset-2" />
+        </div>
+    );
+};
@@ -0,0 +1,165 @@
+import React, { useEffect, useState } from 'react';
+import { Dialog, DialogContent, DialogDescription, DialogHeader, DialogTitle } from '../ui/dialog';
+import { Button } from '../ui/button';
+import { Input } from '../ui/input';
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '../ui/select';
+import { Bell, Pencil, Trash2, X } from 'lucide-react';
+import { toast } from 'sonner';
+import { invokeCommand } from '../../lib/tauri';
+
+export type AlertSeverity = 'info' | 'success' | 'warning' | 'error';
+
+interface AlertRule {
+    id: string;
+    project_id: string;
+    label: string | null;
+    pattern: string;
+    severity: AlertSeverity;
+}
+
+const SEVERITIES: AlertSeverity[] = ['error', 'warning', 'success', 'info'];
+
+const SEVERITY_COLORS: Record<AlertSeverity, string> = {
+    error: 'text-red-500',
+    warning: 'text-amber-500',
+    success: 'text-green-500',
+    info: 'text-blue-500',
+};
+
+interface AlertRulesDialogProps {
+    projectId: string;
+    onClose: () => void;
+}
+
+/** Regex rules matched against each line of the project's process output. */
+export const AlertRulesDialog: React.FC<AlertRulesDialogProps> = ({ projectId, onClose }) => {
+    const [rules, setRules] = useState<AlertRule[]>([]);
+    const [editingId, setEditingId] = useState<string | null>(null);
+    const [label, setLabel] = useState('');
+    const [pattern, setPattern] = useState('');
+    const [severity, setSeverity] = useState<AlertSeverity>('error');
+
+    const fetchRules = async () => {
+        try {
+            setRules(await invokeCommand<AlertRule[]>('get_alert_rules', { projectId }));
+        } catch (err) {
+            toast.error(`Failed to load alert rules: ${err}`);
+        }
+    };
+
+    useEffect(() => {
+        fetchRules();
+    }, [projectId]);
+
+    const resetForm = () => {
+        setEditingId(null);
+        setLabel('');
+        setPattern('');
+        setSeverity('error');
+    };
+
+    const handleEdit = (rule: AlertRule) => {
+        setEditingId(rule.id);
+        setLabel(rule.label ?? '');
+        setPattern(rule.pattern);
+        setSeverity(rule.severity);
+    };
+
+    const handleSubmit = async (e: React.FormEvent) => {
+        e.preventDefault();
+        if (!pattern.trim()) return;
+        // The backend rejects patterns the regex crate can't compile
+        const fields = { label: label.trim() || null, pattern, severity };
+        try {
+            if (editingId) {
+                await invokeCommand('update_alert_rule', { id: editingId, ...fields });
+            } else {
+                await invokeCommand('create_alert_rule', { projectId, ...fields });
+            }
+            resetForm();
+            fetchRules();
+        } catch (err) {
+            toast.error(`Failed to save alert rule: ${err}`);
+        }
+    };
+
+    const handleDelete = async (id: string) => {
+        try {
+            await invokeCommand('delete_alert_rule', { id });
+            if (editingId === id) resetForm();
+            setRules(prev => prev.filter(r => r.id !== id));
+        } catch (err) {
+            toast.error(`Failed to delete alert rule: ${err}`);
+        }
+    };
+
+    return (
+        <Dialog open={true} onOpenChange={(open) => !open && onClose()}>
+            <DialogContent className="max-w-2xl">
+                <DialogHeader>
+                    <DialogTitle className="flex items-center gap-2 text-base">
+                        <Bell className="w-4 h-4" />
+                        Alert rules
+                    </DialogTitle>
+                    <DialogDescription>
+                        Each line of process output is checked against these patterns, e.g. <code className="font-mono">error TS\d+</code> or <code className="font-mono">panicked at</code>.
+                    </DialogDescription>
+                </DialogHeader>
+
+                <form onSubmit={handleSubmit} className="flex gap-2">
+                    <Input
+                        value={label}
+                        onChange={(e) => setLabel(e.target.value)}
+                        placeholder="Label (optional)"
+                        className="h-8 text-xs w-36"
+                    />
+                    <Input
+                        value={pattern}
+                        onChange={(e) => setPattern(e.target.value)}
+                        placeholder="Regex"
+                        className="h-8 text-xs font-mono flex-1"
+                    />
+                    <Select value={severity} onValueChange={(v) => setSeverity(v as AlertSeverity)}>
+                        <SelectTrigger className="h-8 w-28 text-xs">
+                            <SelectValue />
+                        </SelectTrigger>
+                        <SelectContent>
+                            {SEVERITIES.map(s => (
+                                <SelectItem key={s} value={s}>{s}</SelectItem>
+                            ))}
+                        </SelectContent>
+                    </Select>
+                    <Button type="submit" size="sm" className="h-8" disabled={!pattern.trim()}>
+                        {editingId ? 'Save' : 'Add'}
+                    </Button>
+                    {editingId && (
+                        <Button type="button" size="icon" variant="ghost" className="h-8 w-8" title="Cancel editing" onClick={resetForm}>
+                            <X className="w-4 h-4" />
+                        </Button>
+                    )}
+                </form>
+
+                <div className="border rounded-md divide-y max-h-80 overflow-auto">
+                    {rules.length === 0 && (
+                        <div className="p-6 text-center text-sm text-muted-foreground">No alert rules yet</div>
+                    )}
+                    {rules.map(rule => (
+                        <div key={rule.id} className={`flex items-center gap-3 px-3 py-2 text-sm ${editingId === rule.id ? 'bg-muted/50' : ''}`}>
+                            <span className={`text-[10px] uppercase font-semibold w-14 shrink-0 ${SEVERITY_COLORS[rule.severity]}`}>
+                                {rule.severity}
+                            </span>
+                            <span className="truncate w-32 shrink-0">{rule.label ?? <span className="text-muted-foreground italic">no label</span>}</span>
+                            <code className="font-mono text-xs truncate flex-1" title={rule.pattern}>{rule.pattern}</code>
+                            <Button size="icon" variant="ghost" className="h-7 w-7" title="Edit" onClick={() => handleEdit(rule)}>
+                                <Pencil className="w-3.5 h-3.5" />
+                            </Button>
+                            <Button size="icon" variant="ghost" className="h-7 w-7 text-muted-foreground hover:text-destructive" title="Delete" onClick={() => handleDelete(rule.id)}>
+                                <Trash2 className="w-3.5 h-3.5" />
+                            </Button>
+                        </div>
+                    ))}
+                </div>
+            </DialogContent>
+        </Dialog>
+    );
+};
@@ -1,12 +1,13 @@
 import React, { useEffect, useState } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import { Button } from '../ui/button';
 import { Input } from '../ui/input';
 import { Card, CardContent, CardHeader, CardTitle } from '../ui/card';
 import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogFooter, DialogDescription } from '../ui/dialog';
-import { Plus, Trash2, Database, RefreshCw, Server, CheckCircle, XCircle, Play, ArrowLeft } from 'lucide-react';
+import { Plus, Trash2, Database, RefreshCw, Server, CheckCircle, XCircle, Play, ArrowLeft, Unplug, Square } from 'lucide-react';
 import { invokeCommand } from '../../lib/tauri';
 import { toast } from 'sonner';
-import type { ProjectConnection } from '../../types';
+import type { DbConnectionState, ProjectConnection, QueryPage, QueryStats } from '../../types';
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '../ui/select';
 import { Label } from '../ui/label';
 import { Textarea } from '../ui/textarea';
@@ -15,12 +16,27 @@ interface DatabasePanelProps {
     projectId: string;
 }
 
-interface QueryResult {
+interface ResultSet {
     columns: string[];
+    column_types: string[];
     rows: any[][];
     affected_rows: number;
+    truncated: boolean;
 }
 
+const ROW_CAPS = [1000, 10000, 100000];
+
+// Binary values arrive as {"$binary": "<base64>"}
+const renderCell = (cell: any) => {
+    if (cell === null) return <span className="text-muted-foreground italic">NULL</span>;
+    if (typeof cell === 'object' && typeof cell.$binary === 'string') {
+        const bytes = Math.floor(cell.$binary.length * 3 / 4) - (cell.$binary.match(/=*$/)?.[0].length ?? 0);
+        return <span className="text-muted-foreground italic">[binary, {bytes} bytes]</span>;
+    }
+    if (typeof cell === 'object') return JSON.stringify(cell);
+    return String(cell);
+};
+
 export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
     const [connections, setConnections] = useState<ProjectConnection[]>([]);
     const [isLoading, setIsLoading] = useState(false);
@@ -31,8 +47,13 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
 
     // Query State
     const [sqlQuery, setSqlQuery] = useState('SELECT * FROM sqlite_master LIMIT 10;');
-    const [queryResult, setQueryResult] = useState<QueryResult | null>(null);
-    const [isExecuting, setIsExecuting] = useState(false);
+    const [resultSets, setResultSets] = useState<ResultSet[]>([]); // One per statement
+    const [queryStats, setQueryStats] = useState<QueryStats | null>(null);
+    const [activeSet, setActiveSet] = useState(0);
+    const [runningQueryId, setRunningQueryId] = useState<string | null>(null);
+    const [maxRows, setMaxRows] = useState(10000);
+    const isExecuting = runningQueryId !== null;
+    const [health, setHealth] = useState<DbConnectionState | null>(null);
 
     // Dialog State
     const [isCreateOpen, setIsCreateOpen] = useState(false);
@@ -93,7 +114,7 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
             port: parseInt(port),
             username,
             database,
-            password 
+            password // Moved to the keychain when the connection is saved
         });
     };
     
@@ -154,11 +175,11 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
         setIsLoadingTables(true);
         try {
             const data = await invokeCommand<{name: string, schema: string | null}[]>('get_tables', {
-                kind: conn.kind,
-                details: conn.details,
-                password: null // TODO: Handle password
+                connectionId: conn.id,
+                password: null // Saved in the keychain
             });
             setTables(data);
+            setHealth(await invokeCommand<DbConnectionState>('check_db_connection', { connectionId: conn.id }));
         } catch (e) {
             console.error("Failed to fetch tables", e);
             toast.error("Could not load table schema");
@@ -167,7 +188,14 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
         }
     };
 
-    const handleOpenQuery = (conn: ProjectConnection) => {
+    const handleOpenQuery = async (conn: ProjectConnection) => {
+        // Tunnels linked to this connection have to be up before we can reach it
+        try {
+            await invokeCommand('start_connection_tunnels', { connectionId: conn.id });
+        } catch (e) {
+            toast.error(`${e}`);
+            return;
+        }
         setActiveConnection(conn);
         setView('query');
         // Default query based on type
@@ -178,7 +206,8 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
         } else {
             setSqlQuery('SHOW TABLES;');
         }
-        setQueryResult(null);
+        setResultSets([]);
+        setQueryStats(null);
         fetchTables(conn);
     };
 
@@ -189,24 +218,81 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
 
     const handleRunQuery = async () => {
         if (!activeConnection) return;
-        setIsExecuting(true);
+        const queryId = crypto.randomUUID();
+        setRunningQueryId(queryId);
+        setResultSets([]);
+        setQueryStats(null);
+        setActiveSet(0);
+
+        // Rows arrive in pages while the query runs
+        let sets: ResultSet[] = [];
+        const unlisten = await listen<QueryPage>('query_page', (event) => {
+            const page = event.payload;
+            if (page.query_id !== queryId) return;
+            const set = sets[page.result_set] ?? { columns: [], column_types: [], rows: [], affected_rows: 0, truncated: false };
+            sets = [...sets];
+            sets[page.result_set] = {
+                columns: set.columns.length > 0 ? set.columns : page.columns,
+                column_types: set.column_types.length > 0 ? set.column_types : page.column_types,
+                rows: page.rows.length > 0 ? set.rows.concat(page.rows) : set.rows,
+                affected_rows: set.affected_rows + page.affected_rows,
+                truncated: set.truncated || page.truncated,
+            };
+            setResultSets(sets);
+        });
         try {
-            const res = await invokeCommand<QueryResult>('execute_query', {
-                kind: activeConnection.kind,
-                details: activeConnection.details,
+            const stats = await invokeCommand<QueryStats>('execute_query', {
+                connectionId: activeConnection.id,
+                queryId,
                 query: sqlQuery,
-                password: null // Ideally passed from vault or prompt
+                maxRows,
+                password: null // Saved in the keychain
             });
-            setQueryResult(res);
-            toast.success("Query executed");
+            setQueryStats(stats);
+            setActiveSet(Math.max(0, sets.findIndex(set => set.columns.length > 0)));
+            if (stats.cancelled && stats.completed_statements > 0) {
+                toast.info(`Query cancelled after ${stats.completed_statements} statement(s), the rest didn't run`);
+            } else if (stats.cancelled) {
+                toast.info("Query cancelled");
+            } else if (stats.truncated) {
+                toast.warning(`Results were cut off at ${maxRows.toLocaleString()} rows`);
+            } else {
+                toast.success("Query executed");
+            }
         } catch (e: any) {
             console.error(e);
             toast.error("Query failed: " + e);
         } finally {
-            setIsExecuting(false);
+            unlisten();
+            setRunningQueryId(null);
+        }
+    };
+
+    const handleCancelQuery = async () => {
+        if (!runningQueryId) return;
+        try {
+            const cancelled = await invokeCommand<boolean>('cancel_query', { queryId: runningQueryId });
+            if (!cancelled) {
+                toast.info("The query had already finished");
+            }
+        } catch (e) {
+            toast.error(`Failed to cancel: ${e}`);
+        }
+    };
+
+    const handleDisconnect = async () => {
+        if (!activeConnection) return;
+        try {
+            await invokeCommand('disconnect_db', { connectionId: activeConnection.id });
+            setHealth(null);
+            setView('list');
+        } catch (e) {
+            toast.error(`Failed to disconnect: ${e}`);
         }
     };
 
+    const shownSet: ResultSet | null = resultSets[activeSet] ?? resultSets[0] ?? null;
+
     if (view === 'query' && activeConnection) {
         return (
             <div className="h-full flex flex-col p-4 space-y-4">
@@ -222,9 +308,14 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
                             </h2>
                             <p className="text-xs text-muted-foreground font-mono opacity-80">
                                 {activeConnection.kind}
+                                {health?.connected && health.latency_ms !== null && ` · ${health.latency_ms} ms`}
                             </p>
                         </div>
                     </div>
+                    <Button variant="ghost" size="sm" className="gap-1" onClick={handleDisconnect} title="Close the pooled connection">
+                        <Unplug className="w-4 h-4" />
+                        Disconnect
+                    </Button>
                 </div>
 
                 <div className="flex-1 flex gap-4 overflow-hidden">
@@ -261,7 +352,19 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
                     {/* Query Area */}
                     <div className="flex-1 flex flex-col gap-4 overflow-hidden min-w-0">
                         <div className="flex flex-col gap-2 shrink-0">
-                            <Label>SQL Query</Label>
+                            <div className="flex items-center justify-between">
+                                <Label>SQL Query</Label>
+                                <Select value={String(maxRows)} onValueChange={(v) => setMaxRows(Number(v))}>
+                                    <SelectTrigger className="h-7 w-36 text-xs" title="Stop reading after this many rows">
+                                        <SelectValue />
+                                    </SelectTrigger>
+                                    <SelectContent>
+                                        {ROW_CAPS.map(cap => (
+                                            <SelectItem key={cap} value={String(cap)}>Max {cap.toLocaleString()} rows</SelectItem>
+                                        ))}
+                                    </SelectContent>
+                                </Select>
+                            </div>
                             <div className="relative">
                                 <Textarea 
                                     value={sqlQuery} 
@@ -269,46 +372,77 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
                                     className="font-mono min-h-[120px] resize-y"
                                     placeholder="SELECT * FROM table;"
                                 />
-                                <Button 
-                                    size="sm" 
-                                    className="absolute bottom-3 right-3 gap-2 shadow-lg" 
-                                    onClick={handleRunQuery}
-                                    disabled={isExecuting}
-                                >
-                                    <Play className="w-3.5 h-3.5 fill-current" />
-                                    {isExecuting ? 'Running...' : 'Run'}
-                                </Button>
+                                {isExecuting ? (
+                                    <Button
+                                        size="sm"
+                                        variant="destructive"
+                                        className="absolute bottom-3 right-3 gap-2 shadow-lg"
+                                        onClick={handleCancelQuery}
+                                    >
+                                        <Square className="w-3.5 h-3.5 fill-current" />
+                                        Cancel
+                                    </Button>
+                                ) : (
+                                    <Button 
+                                        size="sm" 
+                                        className="absolute bottom-3 right-3 gap-2 shadow-lg" 
+                                        onClick={handleRunQuery}
+                                    >
+                                        <Play className="w-3.5 h-3.5 fill-current" />
+                                        Run
+                                    </Button>
+                                )}
                             </div>
                         </div>
 
                         <div className="flex-1 border rounded-md overflow-hidden flex flex-col bg-muted/10">
-                            {queryResult ? (
+                            {resultSets.length > 1 && (
+                                <div className="flex gap-1 border-b bg-muted/30 px-2 py-1 shrink-0 overflow-x-auto">
+                                    {resultSets.map((set, i) => (
+                                        <Button
+                                            key={i}
+                                            variant={i === activeSet ? 'secondary' : 'ghost'}
+                                            size="sm"
+                                            className="h-6 text-xs"
+                                            onClick={() => setActiveSet(i)}
+                                        >
+                                            {i + 1}: {set.columns.length > 0 ? `${set.rows.length}${set.truncated ? '+' : ''} rows` : `${set.affected_rows} affected`}
+                                        </Button>
+                                    ))}
+                                </div>
+                            )}
+                            {shownSet ? (
                                 <div className="flex-1 overflow-auto">
                                     <table className="w-full text-sm">
                                         <thead className="bg-muted sticky top-0 z-10">
                                             <tr>
-                                                {queryResult.columns.map((col, i) => (
+                                                {shownSet.columns.map((col, i) => (
                                                     <th key={i} className="px-4 py-2 text-left font-medium border-b text-xs text-muted-foreground whitespace-nowrap bg-muted">
                                                         {col}
+                                                        {shownSet.column_types[i] && (
+                                                            <span className="ml-1 font-mono text-[10px] opacity-60">{shownSet.column_types[i].toLowerCase()}</span>
+                                                        )}
                                                     </th>
                                                 ))}
                                             </tr>
                                         </thead>
                                         <tbody>
-                                            {queryResult.rows.map((row, i) => (
+                                            {shownSet.rows.map((row, i) => (
                                                 <tr key={i} className="border-b hover:bg-muted/50">
                                                     {row.map((cell, j) => (
-                                                        <td key={j} className="px-4 py-2 border-r last:border-r-0 max-w-[300px] truncate">
-                                                            {typeof cell === 'object' ? JSON.stringify(cell) : String(cell)}
+                                                        <td key={j} className={`px-4 py-2 border-r last:border-r-0 max-w-[300px] truncate ${typeof cell === 'number' ? 'text-right font-mono' : ''}`}>
+                                                            {renderCell(cell)}
                                                         </td>
                                                     ))}
                                                 </tr>
                                             ))}
                                         </tbody>
                                     </table>
-                                    {queryResult.rows.length === 0 && (
+                                    {shownSet.rows.length === 0 && (
                                         <div className="p-8 text-center text-muted-foreground text-sm">
-                                            No results found
+                                            {shownSet.columns.length === 0 && shownSet.affected_rows > 0
+                                                ? `${shownSet.affected_rows} rows affected`
+                                                : 'No results found'}
                                         </div>
                                     )}
                                 </div>
@@ -317,10 +451,18 @@ export const DatabasePanel: React.FC<DatabasePanelProps> = ({ projectId }) => {
                                     Run a query to see results
                                 </div>
                             )}
-                            {queryResult && (
+                            {shownSet && (
                                  <div className="border-t bg-muted/30 px-3 py-1 text-[10px] text-muted-foreground flex justify-between shrink-0">
-                                    <span>{queryResult.rows.length} rows</span>
-                                    <span>{queryResult.columns.length} columns</span>
+                                    <span>
+                                        {shownSet.rows.length} rows
+                                        {queryStats && queryStats.affected_rows > 0 && ` · ${queryStats.affected_rows} affected`}
+                                        {shownSet.truncated && ` · cut off at the row cap`}
+                                        {queryStats?.cancelled && ' · cancelled'}
+                                    </span>
+                                    <span>
+                                        {shownSet.columns.length} columns
+                                        {queryStats && ` · ${queryStats.elapsed_ms} ms`}
+                                    </span>
                                 </div>
                             )}
                         </div>
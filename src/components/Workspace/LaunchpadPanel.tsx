@@ -97,6 +97,7 @@ export const LaunchpadPanel: React.FC<LaunchpadPanelProps> = ({ projectId, proje
             if (link.kind === 'command') {
                 const cwd = link.working_directory || projectPath;
                 await invokeCommand('start_process', { 
+                    projectId,
                     command: link.url, 
                     cwd
                 });
@@ -122,7 +123,7 @@ export const LaunchpadPanel: React.FC<LaunchpadPanelProps> = ({ projectId, proje
                 const kind = link.kind || 'url';
                 if (kind === 'command') {
                      const cwd = link.working_directory || projectPath;
-                     await invokeCommand('start_process', { command: link.url, cwd });
+                     await invokeCommand('start_process', { projectId, command: link.url, cwd });
                      successCount++;
                 } else if (kind === 'repository') {
                     await invokeCommand('open_in_editor', { path: link.url });
@@ -3,9 +3,10 @@ import { invokeCommand } from '../../lib/tauri';
 import { Button } from '../ui/button';
 import { Card, CardContent } from '../ui/card';
 import { Input } from '../ui/input';
-import { ExternalLink, Plus, Trash2, Globe, Rocket, Terminal, FileCode } from 'lucide-react';
+import { ExternalLink, Plus, Trash2, Globe, Rocket, Terminal, FileCode, FolderTree } from 'lucide-react';
 import { toast } from 'sonner';
 import { Tabs, TabsList, TabsTrigger } from '../ui/tabs';
+import { SftpBrowserDialog } from '../Terminal/SftpBrowserDialog';
 
 interface ProjectLink {
     id: string;
@@ -13,7 +14,7 @@ interface ProjectLink {
     title: string;
     url: string;
     icon: string | null;
-    kind: 'url' | 'command' | 'repository';
+    kind: 'url' | 'command' | 'repository' | 'sftp';
     working_directory?: string;
 }
 
@@ -28,7 +29,8 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
     const [newValue, setNewValue] = useState('');
     const [newCwd, setNewCwd] = useState('');
     const [isCreating, setIsCreating] = useState(false);
-    const [createType, setCreateType] = useState<'url' | 'command' | 'repository'>('url');
+    const [createType, setCreateType] = useState<'url' | 'command' | 'repository' | 'sftp'>('url');
+    const [browsing, setBrowsing] = useState<{ host: string; path: string } | null>(null);
 
     useEffect(() => {
         loadLinks();
@@ -44,6 +46,15 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
         }
     };
 
+    // sftp://<host>/<path>, "/~/..." being relative to the login directory
+    const parseSftpUrl = (url: string) => {
+        const rest = url.replace(/^sftp:\/\//, '');
+        const slash = rest.indexOf('/');
+        if (slash === -1) return { host: rest, path: '~' };
+        const path = rest.slice(slash);
+        return { host: rest.slice(0, slash), path: path.startsWith('/~') ? path.slice(1) : path };
+    };
+
     const ensureProtocol = (url: string) => {
         if (!url) return '';
         if (url.startsWith('http://') || url.startsWith('https://')) return url;
@@ -56,6 +67,8 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
         let finalValue = newValue;
         if (createType === 'url') {
             finalValue = ensureProtocol(newValue);
+        } else if (createType === 'sftp' && !newValue.startsWith('sftp://')) {
+            finalValue = `sftp://${newValue}`;
         }
 
         try {
@@ -75,7 +88,7 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
             toast.success("Item added");
         } catch (e) {
             console.error(e);
-            toast.error("Failed to add item");
+            toast.error(`Failed to add item: ${e}`);
         }
     };
 
@@ -96,6 +109,7 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
             if (link.kind === 'command') {
                 const cwd = link.working_directory || projectPath;
                 await invokeCommand('start_process', { 
+                    projectId,
                     command: link.url, 
                     cwd
                 });
@@ -103,6 +117,8 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
             } else if (link.kind === 'repository') {
                 await invokeCommand('open_in_editor', { path: link.url });
                 toast.success(`Opening ${link.title} in Editor`);
+            } else if (link.kind === 'sftp') {
+                setBrowsing(parseSftpUrl(link.url));
             } else {
                 const validUrl = ensureProtocol(link.url);
                 await invokeCommand('open_url', { url: validUrl });
@@ -123,6 +139,7 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
                 if (kind === 'command') {
                      const cwd = link.working_directory || projectPath;
                      await invokeCommand('start_process', { 
+                        projectId,
                         command: link.url, 
                         cwd
                     });
@@ -130,6 +147,9 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
                 } else if (kind === 'repository') {
                     await invokeCommand('open_in_editor', { path: link.url });
                     successCount++;
+                } else if (kind === 'sftp') {
+                    // Remote folders are browsed one at a time
+                    continue;
                 } else {
                     const validUrl = ensureProtocol(link.url);
                     await invokeCommand('open_url', { url: validUrl });
@@ -147,6 +167,7 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
         switch (kind) {
             case 'command': return <Terminal className="w-4 h-4" />;
             case 'repository': return <FileCode className="w-4 h-4" />;
+            case 'sftp': return <FolderTree className="w-4 h-4" />;
             default: return <Globe className="w-4 h-4" />;
         }
     };
@@ -155,6 +176,7 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
          switch (kind) {
             case 'command': return 'bg-slate-100 dark:bg-slate-800 text-slate-600 dark:text-slate-400';
             case 'repository': return 'bg-orange-100 dark:bg-orange-950/30 text-orange-600 dark:text-orange-400';
+            case 'sftp': return 'bg-sky-100 dark:bg-sky-950/30 text-sky-600 dark:text-sky-400';
             default: return 'bg-primary/10 text-primary';
         }
     };
@@ -183,10 +205,11 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
             {isCreating && (
                 <div className="p-3 border border-border/50 rounded-lg bg-muted/50 space-y-3">
                     <Tabs value={createType} onValueChange={(v: any) => setCreateType(v)} className="w-full">
-                        <TabsList className="grid w-full grid-cols-3 h-7 p-0.5">
+                        <TabsList className="grid w-full grid-cols-4 h-7 p-0.5">
                             <TabsTrigger value="url" className="text-xs h-6">Link</TabsTrigger>
                             <TabsTrigger value="command" className="text-xs h-6">Command</TabsTrigger>
                             <TabsTrigger value="repository" className="text-xs h-6">Repo</TabsTrigger>
+                            <TabsTrigger value="sftp" className="text-xs h-6">SFTP</TabsTrigger>
                         </TabsList>
                     </Tabs>
                     <Input 
@@ -200,7 +223,8 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
                             <Input 
                                 placeholder={
                                     createType === 'url' ? "https://..." : 
-                                    createType === 'repository' ? "/absolute/path/to/repo" : "npm run dev"
+                                    createType === 'repository' ? "/absolute/path/to/repo" :
+                                    createType === 'sftp' ? "sftp://host/var/log" : "npm run dev"
                                 }
                                 value={newValue} 
                                 onChange={e => setNewValue(e.target.value)}
@@ -223,6 +247,11 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
                                  Path to the repository folder. Launches in your default editor.
                              </p>
                         )}
+                        {createType === 'sftp' && (
+                             <p className="text-[10px] text-muted-foreground px-1">
+                                 A host from ~/.ssh/config and a remote folder. Opens in the file browser.
+                             </p>
+                        )}
                     </div>
                 </div>
             )}
@@ -278,6 +307,17 @@ export const LinksPanel: React.FC<LinksPanelProps> = ({ projectId, projectPath }
                     </Card>
                 ))}
             </div>
+
+            {browsing && (
+                <SftpBrowserDialog
+                    open={!!browsing}
+                    onOpenChange={(open) => { if (!open) setBrowsing(null); }}
+                    host={browsing.host}
+                    initialPath={browsing.path}
+                    projectId={projectId}
+                    onBookmarked={loadLinks}
+                />
+            )}
         </div>
     );
 };
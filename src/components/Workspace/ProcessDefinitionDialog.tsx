@@ -0,0 +1,195 @@
+import React, { useState } from 'react';
+import { Button } from '../ui/button';
+import { Input } from '../ui/input';
+import { Label } from '../ui/label';
+import { Textarea } from '../ui/textarea';
+import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogFooter, DialogDescription } from '../ui/dialog';
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '../ui/select';
+import { toast } from 'sonner';
+import { invokeCommand } from '../../lib/tauri';
+import type { ProcessDefinition, ProcessDefinitionInput, RestartPolicy } from '../../types';
+
+interface ProcessDefinitionDialogProps {
+    projectId: string;
+    definition: ProcessDefinition | null; // null adds a new service
+    otherNames: string[]; // Services this one can depend on
+    onSaved: () => void;
+    onClose: () => void;
+}
+
+const parseJson = <T,>(value: string, fallback: T): T => {
+    try {
+        return JSON.parse(value) as T;
+    } catch {
+        return fallback;
+    }
+};
+
+// KEY=value per line, the same shape as a .env file
+const formatEnv = (env: Record<string, string>) =>
+    Object.entries(env).map(([key, value]) => `${key}=${value}`).join('\n');
+
+const parseEnv = (text: string) => {
+    const env: Record<string, string> = {};
+    for (const line of text.split('\n')) {
+        const eq = line.indexOf('=');
+        if (eq > 0) env[line.slice(0, eq).trim()] = line.slice(eq + 1);
+    }
+    return env;
+};
+
+const optionalNumber = (text: string) => text.trim() === '' ? null : Number(text);
+
+/** Add or edit one service of the project's stack. */
+export const ProcessDefinitionDialog: React.FC<ProcessDefinitionDialogProps> = ({ projectId, definition, otherNames, onSaved, onClose }) => {
+    const [name, setName] = useState(definition?.name ?? '');
+    const [command, setCommand] = useState(definition?.command ?? '');
+    const [cwd, setCwd] = useState(definition?.cwd ?? '');
+    const [env, setEnv] = useState(formatEnv(parseJson(definition?.env ?? '{}', {})));
+    const [dependsOn, setDependsOn] = useState<string[]>(parseJson(definition?.depends_on ?? '[]', []));
+    const [restartPolicy, setRestartPolicy] = useState<RestartPolicy>(definition?.restart_policy ?? 'never');
+    const [maxRestarts, setMaxRestarts] = useState(definition?.max_restarts?.toString() ?? '');
+    const [backoffMs, setBackoffMs] = useState(String(definition?.backoff_ms ?? 1000));
+    const [readyPort, setReadyPort] = useState(definition?.ready_port?.toString() ?? '');
+    const [readyPattern, setReadyPattern] = useState(definition?.ready_pattern ?? '');
+    const [autostart, setAutostart] = useState(definition?.autostart ?? false);
+    const [saving, setSaving] = useState(false);
+
+    const toggleDependency = (other: string) => {
+        setDependsOn(prev => prev.includes(other) ? prev.filter(n => n !== other) : [...prev, other]);
+    };
+
+    const handleSave = async () => {
+        const input: ProcessDefinitionInput = {
+            name: name.trim(),
+            command: command.trim(),
+            cwd: cwd.trim() || null,
+            env: parseEnv(env),
+            autostart,
+            restart_policy: restartPolicy,
+            max_restarts: optionalNumber(maxRestarts),
+            backoff_ms: Number(backoffMs) || 0,
+            depends_on: dependsOn,
+            ready_port: optionalNumber(readyPort),
+            ready_pattern: readyPattern.trim() || null,
+        };
+        setSaving(true);
+        try {
+            if (definition) {
+                await invokeCommand('update_process_definition', { id: definition.id, definition: input });
+            } else {
+                await invokeCommand('create_process_definition', { projectId, definition: input });
+            }
+            onSaved();
+            onClose();
+        } catch (err) {
+            toast.error(`Failed to save ${input.name || 'service'}: ${err}`);
+        } finally {
+            setSaving(false);
+        }
+    };
+
+    return (
+        <Dialog open={true} onOpenChange={(open) => !open && onClose()}>
+            <DialogContent className="max-w-lg">
+                <DialogHeader>
+                    <DialogTitle>{definition ? `Edit ${definition.name}` : 'Add Service'}</DialogTitle>
+                    <DialogDescription>
+                        Services start in dependency order; a ready check holds back the ones that depend on it.
+                    </DialogDescription>
+                </DialogHeader>
+
+                <div className="space-y-4 py-2">
+                    <div className="grid grid-cols-3 gap-4">
+                        <div className="space-y-2">
+                            <Label>Name</Label>
+                            <Input placeholder="web" value={name} onChange={e => setName(e.target.value)} />
+                        </div>
+                        <div className="col-span-2 space-y-2">
+                            <Label>Command</Label>
+                            <Input placeholder="npm run dev" className="font-mono" value={command} onChange={e => setCommand(e.target.value)} />
+                        </div>
+                    </div>
+                    <div className="space-y-2">
+                        <Label>Working Directory</Label>
+                        <Input placeholder="Project root" className="font-mono" value={cwd} onChange={e => setCwd(e.target.value)} />
+                    </div>
+                    <div className="space-y-2">
+                        <Label>Environment</Label>
+                        <Textarea
+                            placeholder="PORT=3000"
+                            className="font-mono text-xs min-h-[60px]"
+                            value={env}
+                            onChange={e => setEnv(e.target.value)}
+                        />
+                    </div>
+
+                    {otherNames.length > 0 && (
+                        <div className="space-y-2">
+                            <Label>Depends On</Label>
+                            <div className="flex flex-wrap gap-3">
+                                {otherNames.map(other => (
+                                    <label key={other} className="flex items-center gap-1.5 text-sm font-mono">
+                                        <input
+                                            type="checkbox"
+                                            checked={dependsOn.includes(other)}
+                                            onChange={() => toggleDependency(other)}
+                                        />
+                                        {other}
+                                    </label>
+                                ))}
+                            </div>
+                        </div>
+                    )}
+
+                    <div className="grid grid-cols-2 gap-4">
+                        <div className="space-y-2">
+                            <Label>Ready When Port Opens</Label>
+                            <Input placeholder="3000" value={readyPort} onChange={e => setReadyPort(e.target.value)} />
+                        </div>
+                        <div className="space-y-2">
+                            <Label>Ready When Output Matches</Label>
+                            <Input placeholder="Listening on" className="font-mono" value={readyPattern} onChange={e => setReadyPattern(e.target.value)} />
+                        </div>
+                    </div>
+
+                    <div className="grid grid-cols-3 gap-4">
+                        <div className="space-y-2">
+                            <Label>Restart</Label>
+                            <Select value={restartPolicy} onValueChange={(v) => setRestartPolicy(v as RestartPolicy)}>
+                                <SelectTrigger>
+                                    <SelectValue />
+                                </SelectTrigger>
+                                <SelectContent>
+                                    <SelectItem value="never">Never</SelectItem>
+                                    <SelectItem value="on-failure">On failure</SelectItem>
+                                    <SelectItem value="always">Always</SelectItem>
+                                </SelectContent>
+                            </Select>
+                        </div>
+                        <div className="space-y-2">
+                            <Label>Max Restarts</Label>
+                            <Input placeholder="Unlimited" disabled={restartPolicy === 'never'} value={maxRestarts} onChange={e => setMaxRestarts(e.target.value)} />
+                        </div>
+                        <div className="space-y-2">
+                            <Label>Backoff (ms)</Label>
+                            <Input disabled={restartPolicy === 'never'} value={backoffMs} onChange={e => setBackoffMs(e.target.value)} />
+                        </div>
+                    </div>
+
+                    <label className="flex items-center gap-2 text-sm">
+                        <input type="checkbox" checked={autostart} onChange={e => setAutostart(e.target.checked)} />
+                        Start when the project opens
+                    </label>
+                </div>
+
+                <DialogFooter>
+                    <Button variant="ghost" onClick={onClose}>Cancel</Button>
+                    <Button onClick={handleSave} disabled={saving || !name.trim() || !command.trim()}>
+                        {definition ? 'Save' : 'Add Service'}
+                    </Button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+};
@@ -1,18 +1,86 @@
-import React, { useState, useEffect } from 'react';
+import React, { useState, useEffect, useCallback, useMemo } from 'react';
 import { Button } from '../ui/button';
 import { Input } from '../ui/input';
 import { ScrollArea } from '../ui/scroll-area';
-import { Square, Plus, Terminal as TerminalIcon, LayoutList, Trash2 } from 'lucide-react';
+import { Square, Plus, Terminal as TerminalIcon, LayoutList, Trash2, Bell } from 'lucide-react';
 import { ProjectTerminal } from './ProjectTerminal';
+import { AlertRulesDialog, type AlertSeverity } from './AlertRulesDialog';
+import { StackSection } from './StackSection';
 import { invokeCommand } from '../../lib/tauri';
+import { listen } from '@tauri-apps/api/event';
 import { toast } from 'sonner';
 
+type ProcessStatus = 'running' | 'exited' | 'killed';
+
 interface Process {
     id: string;
     command: string;
     cwd: string;
     running: boolean;
     pid: number;
+    status: ProcessStatus;
+    exit_code: number | null;
+    started_at: number;
+    ended_at: number | null;
+    definition_id: string | null;
+    stats: ProcessStats | null;
+    ports: number[];
+    project_id: string | null;
+    alert_counts: Partial<Record<AlertSeverity, number>>;
+}
+
+interface ProcessAlertPayload {
+    id: string;
+    label: string | null;
+    severity: AlertSeverity;
+    line: string;
+    count: number | null;
+}
+
+interface PortOwner {
+    port: number;
+    address: string;
+    pid: number | null;
+    name: string | null;
+    command: string | null;
+    process_id: string | null;
+}
+
+interface PortConflictPayload {
+    id: string;
+    port: number;
+    owners: PortOwner[];
+}
+
+interface ProcessStats {
+    cpu_percent: number;
+    rss_bytes: number;
+    threads: number | null;
+    process_count: number;
+    sampled_at: number;
+}
+
+interface ProcessStatsPayload {
+    id: string;
+    stats: ProcessStats;
+}
+
+const formatBytes = (bytes: number) => {
+    if (bytes >= 1024 ** 3) return `${(bytes / 1024 ** 3).toFixed(1)} GB`;
+    if (bytes >= 1024 ** 2) return `${(bytes / 1024 ** 2).toFixed(0)} MB`;
+    return `${(bytes / 1024).toFixed(0)} KB`;
+};
+
+interface ProcessExitPayload {
+    id: string;
+    status: ProcessStatus;
+    exit_code: number | null;
+    ended_at: number;
+}
+
+interface TerminateReport {
+    terminated: number[];
+    killed: number[];
 }
 
 interface ProcessManagerProps {
@@ -25,20 +93,93 @@ export const ProcessManager: React.FC<ProcessManagerProps> = ({ projectId, path
     const [snippets, setSnippets] = useState<any[]>([]); // Using 'any' briefly to avoid import churn, should be Snippet
     const [newCommand, setNewCommand] = useState('');
     const [selectedId, setSelectedId] = useState<string | null>(null);
+    const [showAlertRules, setShowAlertRules] = useState(false);
 
-    useEffect(() => {
-        // Fetch active processes
+    const refreshProcesses = useCallback(() => {
         invokeCommand<Process[]>('get_active_processes')
             .then(setProcesses)
             .catch(err => {
                 console.error("Failed to fetch active processes:", err);
                 toast.error("Failed to restore process list");
             });
+    }, []);
+
+    // Services of the stack that are currently running, for the stack section
+    const runningByDefinition = useMemo(() => {
+        const running: Record<string, string> = {};
+        for (const p of processes) {
+            if (p.running && p.definition_id) running[p.definition_id] = p.id;
+        }
+        return running;
+    }, [processes]);
+
+    useEffect(() => {
+        refreshProcesses();
 
         // Fetch saved snippets
         fetchSnippets();
     }, [projectId]); // Re-fetch if project changes
 
+    useEffect(() => {
+        const unlisten = listen<ProcessExitPayload>('process_exit', (event) => {
+            const { id, status, exit_code, ended_at } = event.payload;
+            setProcesses(prev => prev.map(p =>
+                p.id === id ? { ...p, running: false, status, exit_code, ended_at } : p
+            ));
+        });
+        // Supervised restarts start a new run behind our back
+        const unlistenStarted = listen<Process>('process_started', (event) => {
+            const started = event.payload;
+            setProcesses(prev => [...prev.filter(p => p.id !== started.id), started]);
+        });
+        const unlistenStats = listen<ProcessStatsPayload>('process_stats', (event) => {
+            const { id, stats } = event.payload;
+            setProcesses(prev => prev.map(p => p.id === id ? { ...p, stats } : p));
+        });
+        const unlistenPorts = listen<{ id: string; ports: number[] }>('process_ports', (event) => {
+            const { id, ports } = event.payload;
+            setProcesses(prev => prev.map(p => p.id === id ? { ...p, ports } : p));
+        });
+        const unlistenConflict = listen<PortConflictPayload>('process_port_conflict', (event) => {
+            const { port, owners } = event.payload;
+            const owner = owners.find(o => o.pid !== null);
+            if (!owner || owner.pid === null) {
+                toast.error(`Port ${port} is already in use`);
+                return;
+            }
+            const ownerPid = owner.pid;
+            toast.error(`Port ${port} is held by ${owner.name ?? 'another process'} (PID ${ownerPid})`, {
+                description: owner.command ?? undefined,
+                action: {
+                    label: 'Kill',
+                    onClick: () => {
+                        invokeCommand('kill_port_owner', { port, pid: ownerPid })
+                            .then(() => toast.success(`Freed port ${port}`))
+                            .catch((e) => toast.error(`Failed to kill PID ${ownerPid}: ${e}`));
+                    }
+                }
+            });
+        });
+        const unlistenAlerts = listen<ProcessAlertPayload>('process_alert', (event) => {
+            const { id, severity, count, label, line } = event.payload;
+            setProcesses(prev => prev.map(p => p.id === id
+                ? { ...p, alert_counts: { ...p.alert_counts, [severity]: count ?? (p.alert_counts[severity] ?? 0) + 1 } }
+                : p
+            ));
+            if (severity === 'error') {
+                toast.error(label ?? 'Process error', { description: line });
+            }
+        });
+        return () => {
+            unlisten.then(f => f());
+            unlistenStarted.then(f => f());
+            unlistenStats.then(f => f());
+            unlistenAlerts.then(f => f());
+            unlistenPorts.then(f => f());
+            unlistenConflict.then(f => f());
+        };
+    }, []);
+
     const fetchSnippets = async () => {
         try {
             const data = await invokeCommand<any[]>('get_project_snippets', { projectId });
@@ -86,6 +227,7 @@ export const ProcessManager: React.FC<ProcessManagerProps> = ({ projectId, path
 
         try {
             const process = await invokeCommand<Process>('start_process', { 
+                projectId,
                 command: cmdToRun, 
                 cwd: path 
             });
@@ -102,12 +244,24 @@ export const ProcessManager: React.FC<ProcessManagerProps> = ({ projectId, path
     const handleStop = async (id: string, e: React.MouseEvent) => {
         e.stopPropagation();
         try {
-            await invokeCommand('stop_process', { id });
+            const report = await invokeCommand<TerminateReport>('stop_process', { id });
+            const pids = [...report.terminated, ...report.killed];
+            toast.success(`Process terminated (PID ${pids.join(', ')})`, {
+                description: report.killed.length > 0 ? `Force killed after grace period: ${report.killed.join(', ')}` : undefined
+            });
+        } catch (err) {
+            toast.error("Failed to stop process");
+        }
+    };
+
+    const handleRemove = async (id: string, e: React.MouseEvent) => {
+        e.stopPropagation();
+        try {
+            await invokeCommand('remove_process', { id });
             setProcesses(prev => prev.filter(p => p.id !== id));
             if (selectedId === id) setSelectedId(null);
-            toast.success("Process terminated");
         } catch (err) {
-            toast.error("Failed to stop process");
+            toast.error("Failed to remove process");
         }
     };
 
@@ -119,6 +273,15 @@ export const ProcessManager: React.FC<ProcessManagerProps> = ({ projectId, path
                     <h3 className="text-sm font-semibold flex items-center gap-2 mb-3">
                         <LayoutList className="w-4 h-4" />
                         Active Processes
+                        <Button
+                            size="icon"
+                            variant="ghost"
+                            className="h-6 w-6 ml-auto"
+                            title="Alert rules"
+                            onClick={() => setShowAlertRules(true)}
+                        >
+                            <Bell className="w-3.5 h-3.5" />
+                        </Button>
                     </h3>
                     <form onSubmit={handleStart} className="flex gap-2">
                         <Input 
@@ -156,18 +319,52 @@ export const ProcessManager: React.FC<ProcessManagerProps> = ({ projectId, path
                                 `}
                             >
                                 <div className="flex items-center gap-2 truncate flex-1 min-w-0">
-                                    <div className="w-2 h-2 rounded-full bg-green-500 animate-pulse shrink-0" />
+                                    <div className={`w-2 h-2 rounded-full shrink-0 ${
+                                        proc.running
+                                            ? 'bg-green-500 animate-pulse'
+                                            : proc.status === 'exited' && proc.exit_code !== 0 ? 'bg-red-500' : 'bg-zinc-500'
+                                    }`} />
                                     <span className="truncate font-mono text-xs" title={proc.command}>
                                         {proc.command}
                                     </span>
+                                    {(proc.alert_counts.error ?? 0) > 0 && (
+                                        <span className="text-[10px] px-1 rounded bg-red-500/15 text-red-500 shrink-0 tabular-nums" title="Errors">
+                                            {proc.alert_counts.error}
+                                        </span>
+                                    )}
+                                    {(proc.alert_counts.warning ?? 0) > 0 && (
+                                        <span className="text-[10px] px-1 rounded bg-amber-500/15 text-amber-500 shrink-0 tabular-nums" title="Warnings">
+                                            {proc.alert_counts.warning}
+                                        </span>
+                                    )}
+                                    {proc.running && proc.ports.map(port => (
+                                        <span key={port} className="text-[10px] font-mono px-1 rounded bg-muted text-muted-foreground shrink-0">
+                                            :{port}
+                                        </span>
+                                    ))}
+                                    {proc.running && proc.stats && (
+                                        <span
+                                            className="text-[10px] text-muted-foreground shrink-0 tabular-nums"
+                                            title={`${proc.stats.process_count} processes${proc.stats.threads !== null ? `, ${proc.stats.threads} threads` : ''}`}
+                                        >
+                                            {proc.stats.cpu_percent.toFixed(0)}% · {formatBytes(proc.stats.rss_bytes)}
+                                        </span>
+                                    )}
+                                    {!proc.running && (
+                                        <span className="text-[10px] text-muted-foreground shrink-0">
+                                            {proc.status === 'killed' ? 'killed' : `exit ${proc.exit_code ?? '?'}`}
+                                        </span>
+                                    )}
                                 </div>
                                 <Button 
                                     variant="ghost" 
                                     size="icon" 
                                     className="h-6 w-6 opacity-0 group-hover:opacity-100 hover:text-destructive hover:bg-destructive/10"
-                                    onClick={(e) => handleStop(proc.id, e)}
+                                    onClick={(e) => proc.running ? handleStop(proc.id, e) : handleRemove(proc.id, e)}
                                 >
-                                    <Square className="w-3 h-3 fill-current" />
+                                    {proc.running
+                                        ? <Square className="w-3 h-3 fill-current" />
+                                        : <Trash2 className="w-3 h-3" />}
                                 </Button>
                             </div>
                         ))}
@@ -177,6 +374,13 @@ export const ProcessManager: React.FC<ProcessManagerProps> = ({ projectId, path
                             </div>
                         )}
 
+                        <StackSection
+                            projectId={projectId}
+                            runningByDefinition={runningByDefinition}
+                            onSelectProcess={setSelectedId}
+                            onProcessesChanged={refreshProcesses}
+                        />
+
                         {snippets.length > 0 && (
                             <>
                                 <div className="mt-4 mb-2 px-2 text-xs font-semibold text-muted-foreground uppercase tracking-wider">
@@ -222,6 +426,9 @@ export const ProcessManager: React.FC<ProcessManagerProps> = ({ projectId, path
                     </div>
                 )}
             </div>
+            {showAlertRules && (
+                <AlertRulesDialog projectId={projectId} onClose={() => setShowAlertRules(false)} />
+            )}
         </div>
     );
 };
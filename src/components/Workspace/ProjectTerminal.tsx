@@ -4,6 +4,7 @@ import { FitAddon } from '@xterm/addon-fit';
 import '@xterm/xterm/css/xterm.css'; // Ensure you have this configured or add to global css
 import { invokeCommand } from '../../lib/tauri';
 import { listen } from '@tauri-apps/api/event';
+import { RecordButton } from '../Terminal/RecordButton';
 
 interface ProjectTerminalProps {
     processId: string;
@@ -50,9 +51,9 @@ export const ProjectTerminal: React.FC<ProjectTerminalProps> = ({ processId, onE
         fitAddonRef.current = fitAddon;
 
         // Fetch initial history
-        invokeCommand<string>('get_process_history', { id: processId })
+        invokeCommand<{ data: string, start: number, end: number, total: number }>('get_process_history', { id: processId })
             .then(history => {
-                if (history) term.write(history);
+                if (history.data) term.write(history.data);
             })
             .catch(console.error);
 
@@ -110,7 +111,8 @@ export const ProjectTerminal: React.FC<ProjectTerminalProps> = ({ processId, onE
     }, [mounted]);
 
     return (
-        <div className="h-full w-full bg-zinc-950 p-2 overflow-hidden">
+        <div className="h-full w-full bg-zinc-950 p-2 overflow-hidden relative">
+            <RecordButton sessionId={processId} />
             <div ref={containerRef} className="h-full w-full" />
         </div>
     );
@@ -1,4 +1,4 @@
-import React, { useState } from 'react';
+import React, { useEffect, useState } from 'react';
 import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogDescription } from '../ui/dialog';
 import { Tabs, TabsList, TabsTrigger } from '../ui/tabs';
 import { OverviewPanel } from './OverviewPanel';
@@ -10,9 +10,12 @@ import { ScriptRunner } from './ScriptRunner';
 import { ProcessManager } from './ProcessManager';
 import { LaunchpadPanel } from './LaunchpadPanel';
 import { DatabasePanel } from './DatabasePanel';
+import { TunnelsPanel } from './TunnelsPanel';
+import { RecordingsPanel } from '../Terminal/RecordingsPanel';
 import type { Project } from '../../types';
-import { FolderOpen, ScrollText, Play, LayoutDashboard, Lock, GitBranch, Terminal, Rocket, Database } from 'lucide-react';
+import { FolderOpen, ScrollText, Play, LayoutDashboard, Lock, GitBranch, Terminal, Rocket, Database, Film, Network } from 'lucide-react';
 import { toast } from 'sonner';
+import { invokeCommand } from '../../lib/tauri';
 
 interface ProjectWorkspaceProps {
     project: Project;
@@ -22,6 +25,17 @@ interface ProjectWorkspaceProps {
 export const ProjectWorkspace: React.FC<ProjectWorkspaceProps> = ({ project, onClose }) => {
     const [activeTab, setActiveTab] = useState('overview');
 
+    useEffect(() => {
+        // Bring up the project's autostart processes (no-op for ones already running)
+        invokeCommand<{ failed: { name: string, error: string }[] }>('open_project_processes', { projectId: project.id })
+            .then(({ failed }) => {
+                for (const { name, error } of failed) {
+                    toast.error(`Failed to autostart ${name}`, { description: error });
+                }
+            })
+            .catch(err => console.error("Failed to start project processes:", err));
+    }, [project.id]);
+
     const handleRunSnippet = async (command: string) => {
         try {
             await navigator.clipboard.writeText(command);
@@ -89,6 +103,14 @@ export const ProjectWorkspace: React.FC<ProjectWorkspaceProps> = ({ project, onC
                                     <Database className="w-3.5 h-3.5" />
                                     Databases
                                 </TabsTrigger>
+                                <TabsTrigger value="tunnels" className="gap-2">
+                                    <Network className="w-3.5 h-3.5" />
+                                    Tunnels
+                                </TabsTrigger>
+                                <TabsTrigger value="recordings" className="gap-2">
+                                    <Film className="w-3.5 h-3.5" />
+                                    Recordings
+                                </TabsTrigger>
                             </TabsList>
                         </div>
                         
@@ -105,7 +127,7 @@ export const ProjectWorkspace: React.FC<ProjectWorkspaceProps> = ({ project, onC
 
                             <div className={activeTab === 'scripts' ? 'h-full' : 'hidden h-full'}>
                                 {activeTab === 'scripts' && (
-                                    <ScriptRunner path={project.path} onNavigate={setActiveTab} />
+                                    <ScriptRunner projectId={project.id} path={project.path} onNavigate={setActiveTab} />
                                 )}
                             </div>
 
@@ -151,6 +173,18 @@ export const ProjectWorkspace: React.FC<ProjectWorkspaceProps> = ({ project, onC
                                     <DatabasePanel projectId={project.id} />
                                 )}
                             </div>
+
+                            <div className={activeTab === 'tunnels' ? 'h-full' : 'hidden h-full'}>
+                                {activeTab === 'tunnels' && (
+                                    <TunnelsPanel projectId={project.id} />
+                                )}
+                            </div>
+
+                            <div className={activeTab === 'recordings' ? 'h-full' : 'hidden h-full'}>
+                                {activeTab === 'recordings' && (
+                                    <RecordingsPanel projectId={project.id} />
+                                )}
+                            </div>
                         </div>
                     </Tabs>
                 </div>
@@ -11,11 +11,12 @@ interface ProjectScript {
 }
 
 interface ScriptRunnerProps {
+    projectId: string;
     path: string;
     onNavigate: (tab: string) => void;
 }
 
-export const ScriptRunner: React.FC<ScriptRunnerProps> = ({ path, onNavigate }) => {
+export const ScriptRunner: React.FC<ScriptRunnerProps> = ({ projectId, path, onNavigate }) => {
     const [scripts, setScripts] = useState<ProjectScript[]>([]);
     const [loading, setLoading] = useState(false);
     const [runningScript, setRunningScript] = useState<string | null>(null);
@@ -49,6 +50,7 @@ export const ScriptRunner: React.FC<ScriptRunnerProps> = ({ path, onNavigate })
 
             // Use the internal process manager
             await invokeCommand('start_process', { 
+                projectId,
                 cwd: path, 
                 command: fullCommand 
             });
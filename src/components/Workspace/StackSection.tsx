@@ -0,0 +1,220 @@
+import React, { useEffect, useState } from 'react';
+import { Button } from '../ui/button';
+import { Download, Pencil, Play, Plus, Square, Trash2 } from 'lucide-react';
+import { listen } from '@tauri-apps/api/event';
+import { toast } from 'sonner';
+import { invokeCommand } from '../../lib/tauri';
+import type { ProcessDefinition, StackProgress, StackProgressState } from '../../types';
+import {
+    DropdownMenu,
+    DropdownMenuContent,
+    DropdownMenuItem,
+    DropdownMenuTrigger,
+} from '../ui/dropdown-menu';
+import { ProcessDefinitionDialog } from './ProcessDefinitionDialog';
+
+interface StackSectionProps {
+    projectId: string;
+    runningByDefinition: Record<string, string>; // definition id -> id of its running process
+    onSelectProcess: (processId: string) => void;
+    onProcessesChanged: () => void;
+}
+
+const STATE_COLORS: Record<StackProgressState, string> = {
+    starting: 'text-amber-500',
+    ready: 'text-green-500',
+    failed: 'text-red-500',
+    stopping: 'text-amber-500',
+    stopped: 'text-muted-foreground',
+};
+
+/** The project's saved services, started and stopped together in dependency order. */
+export const StackSection: React.FC<StackSectionProps> = ({ projectId, runningByDefinition, onSelectProcess, onProcessesChanged }) => {
+    const [definitions, setDefinitions] = useState<ProcessDefinition[]>([]);
+    const [progress, setProgress] = useState<Record<string, StackProgress>>({});
+    const [busy, setBusy] = useState(false);
+    const [editing, setEditing] = useState<ProcessDefinition | 'new' | null>(null);
+
+    const fetchDefinitions = async () => {
+        try {
+            setDefinitions(await invokeCommand<ProcessDefinition[]>('get_process_definitions', { projectId }));
+        } catch (err) {
+            console.error('Failed to fetch services:', err);
+        }
+    };
+
+    useEffect(() => {
+        fetchDefinitions();
+        setProgress({});
+    }, [projectId]);
+
+    useEffect(() => {
+        const unlisten = listen<StackProgress>('stack_progress', (event) => {
+            if (event.payload.project_id !== projectId) return;
+            setProgress(prev => ({ ...prev, [event.payload.definition_id]: event.payload }));
+            // Started and stopped runs show up in the process list as the stack moves along
+            onProcessesChanged();
+        });
+        return () => {
+            unlisten.then(f => f());
+        };
+    }, [projectId, onProcessesChanged]);
+
+    const handleImport = async (command: 'import_procfile' | 'import_stack_file', file: string) => {
+        try {
+            const imported = await invokeCommand<ProcessDefinition[]>(command, { projectId, path: null });
+            toast.success(`Imported ${imported.length} service${imported.length === 1 ? '' : 's'} from ${file}`);
+            fetchDefinitions();
+        } catch (err) {
+            toast.error(`Failed to import ${file}: ${err}`);
+        }
+    };
+
+    const handleStartStack = async () => {
+        setBusy(true);
+        setProgress({});
+        try {
+            await invokeCommand('start_stack', { projectId });
+            toast.success('Stack is up');
+        } catch (err) {
+            toast.error(`Stack failed to start: ${err}`);
+        } finally {
+            setBusy(false);
+            onProcessesChanged();
+        }
+    };
+
+    const handleStopStack = async () => {
+        setBusy(true);
+        try {
+            await invokeCommand('stop_stack', { projectId });
+            toast.success('Stack stopped');
+        } catch (err) {
+            toast.error(`Failed to stop the stack: ${err}`);
+        } finally {
+            setBusy(false);
+            onProcessesChanged();
+        }
+    };
+
+    const handleToggle = async (definition: ProcessDefinition, e: React.MouseEvent) => {
+        e.stopPropagation();
+        const running = definition.id in runningByDefinition;
+        try {
+            if (running) {
+                await invokeCommand('stop_process_definition', { id: definition.id });
+            } else {
+                const process = await invokeCommand<{ id: string }>('start_process_definition', { id: definition.id });
+                onSelectProcess(process.id);
+            }
+        } catch (err) {
+            toast.error(`Failed to ${running ? 'stop' : 'start'} ${definition.name}: ${err}`);
+        } finally {
+            onProcessesChanged();
+        }
+    };
+
+    const handleDelete = async (definition: ProcessDefinition, e: React.MouseEvent) => {
+        e.stopPropagation();
+        if (!window.confirm(`Delete ${definition.name}? It is stopped if running.`)) return;
+        try {
+            await invokeCommand('delete_process_definition', { id: definition.id });
+            setDefinitions(prev => prev.filter(d => d.id !== definition.id));
+            onProcessesChanged();
+        } catch (err) {
+            toast.error(`Failed to delete ${definition.name}: ${err}`);
+        }
+    };
+
+    return (
+        <>
+            <div className="mt-4 mb-2 px-2 flex items-center gap-1">
+                <span className="text-xs font-semibold text-muted-foreground uppercase tracking-wider flex-1">
+                    Stack
+                </span>
+                <DropdownMenu>
+                    <DropdownMenuTrigger asChild>
+                        <Button size="icon" variant="ghost" className="h-6 w-6" title="Import services">
+                            <Download className="w-3 h-3" />
+                        </Button>
+                    </DropdownMenuTrigger>
+                    <DropdownMenuContent align="end">
+                        <DropdownMenuItem onClick={() => handleImport('import_procfile', 'Procfile')}>
+                            From Procfile
+                        </DropdownMenuItem>
+                        <DropdownMenuItem onClick={() => handleImport('import_stack_file', 'switchboard.toml')}>
+                            From switchboard.toml
+                        </DropdownMenuItem>
+                    </DropdownMenuContent>
+                </DropdownMenu>
+                <Button size="icon" variant="ghost" className="h-6 w-6" title="Add service" onClick={() => setEditing('new')}>
+                    <Plus className="w-3 h-3" />
+                </Button>
+                {definitions.length > 0 && (
+                    <>
+                        <Button size="icon" variant="ghost" className="h-6 w-6" title="Start stack" disabled={busy} onClick={handleStartStack}>
+                            <Play className="w-3 h-3" />
+                        </Button>
+                        <Button size="icon" variant="ghost" className="h-6 w-6" title="Stop stack" disabled={busy} onClick={handleStopStack}>
+                            <Square className="w-3 h-3" />
+                        </Button>
+                    </>
+                )}
+            </div>
+
+            {definitions.map(definition => {
+                const processId = runningByDefinition[definition.id];
+                const step = progress[definition.id];
+                return (
+                    <div
+                        key={definition.id}
+                        onClick={() => processId && onSelectProcess(processId)}
+                        className="group/item flex items-center justify-between p-2 rounded-md hover:bg-muted cursor-pointer text-sm"
+                    >
+                        <div className="flex items-center gap-2 truncate flex-1 min-w-0">
+                            <div className={`w-2 h-2 rounded-full shrink-0 ${processId ? 'bg-green-500' : 'bg-zinc-500'}`} />
+                            <span className="truncate font-mono text-xs" title={definition.command}>{definition.name}</span>
+                            {step && (
+                                <span className={`text-[10px] shrink-0 ${STATE_COLORS[step.state]}`} title={step.error ?? undefined}>
+                                    {step.state}
+                                </span>
+                            )}
+                        </div>
+                        <div className="flex items-center opacity-0 group-hover/item:opacity-100">
+                            <Button variant="ghost" size="icon" className="h-6 w-6" title={processId ? 'Stop' : 'Start'} onClick={(e) => handleToggle(definition, e)}>
+                                {processId ? <Square className="w-3 h-3 fill-current" /> : <Play className="w-3 h-3" />}
+                            </Button>
+                            <Button variant="ghost" size="icon" className="h-6 w-6" title="Edit" onClick={(e) => { e.stopPropagation(); setEditing(definition); }}>
+                                <Pencil className="w-3 h-3" />
+                            </Button>
+                            <Button
+                                variant="ghost"
+                                size="icon"
+                                className="h-6 w-6 hover:text-destructive hover:bg-destructive/10"
+                                title="Delete"
+                                onClick={(e) => handleDelete(definition, e)}
+                            >
+                                <Trash2 className="w-3 h-3" />
+                            </Button>
+                        </div>
+                    </div>
+                );
+            })}
+            {definitions.length === 0 && (
+                <div className="px-2 pb-2 text-xs text-muted-foreground">
+                    Add services or import a Procfile to start them together
+                </div>
+            )}
+
+            {editing && (
+                <ProcessDefinitionDialog
+                    projectId={projectId}
+                    definition={editing === 'new' ? null : editing}
+                    otherNames={definitions.filter(d => editing === 'new' || d.id !== editing.id).map(d => d.name)}
+                    onSaved={fetchDefinitions}
+                    onClose={() => setEditing(null)}
+                />
+            )}
+        </>
+    );
+};
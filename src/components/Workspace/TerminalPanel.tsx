@@ -1,21 +1,59 @@
-import React, { useRef, useEffect } from 'react';
+import React, { useRef, useEffect, useState } from 'react';
 import { Terminal } from '@xterm/xterm';
 import { FitAddon } from '@xterm/addon-fit';
 import { listen } from '@tauri-apps/api/event';
 import { invokeCommand } from '../../lib/tauri';
+import { RecordButton } from '../Terminal/RecordButton';
+import { ShellProfilePicker } from '../Terminal/ShellProfilePicker';
+import type { ShellProfile } from '../../types';
 import '@xterm/xterm/css/xterm.css';
 
+interface TerminalInfo {
+  session_id: string;
+  project_id: string;
+  program: string;
+  pid: number | null;
+  cols: number;
+  rows: number;
+  created_at: number;
+}
+
+interface TerminalAttachment {
+  info: TerminalInfo;
+  scrollback: string;
+  end: number; // Stream offset the scrollback ends at
+}
+
+interface TerminalData {
+  session_id: string;
+  data: string;
+  end: number;
+}
+
+interface TerminalExit {
+  session_id: string;
+  exit_code: number | null;
+}
+
 interface TerminalPanelProps {
   projectId: string;
   initialCommand?: string;
+  profileId?: string; // Shell profile to spawn, defaults to the project's default profile
   onSessionReady?: (sessionId: string) => void;
+  onExit?: (exitCode: number | null) => void;
 }
 
-export const TerminalPanel: React.FC<TerminalPanelProps> = ({ projectId, initialCommand, onSessionReady }) => {
+// Survives a webview reload (but not an app restart), so we can find our shell again
+const storageKey = (projectId: string, initialCommand?: string) =>
+  `switchboard.terminal.${projectId}.${initialCommand ?? ''}`;
+
+// One shell session in the panel; remounted to start another
+const ShellView: React.FC<TerminalPanelProps> = ({ projectId, initialCommand, profileId, onSessionReady, onExit }) => {
   const terminalRef = useRef<HTMLDivElement>(null);
   const xtermRef = useRef<Terminal | null>(null);
   const fitAddonRef = useRef<FitAddon | null>(null);
   const sessionIdRef = useRef<string | null>(null);
+  const [sessionId, setSessionId] = useState<string | null>(null);
 
   useEffect(() => {
     if (!terminalRef.current) return;
@@ -82,28 +120,83 @@ export const TerminalPanel: React.FC<TerminalPanelProps> = ({ projectId, initial
     });
     resizeObserver.observe(terminalRef.current);
 
+    // Output already in the attached scrollback, so replayed chunks can be skipped
+    let scrollbackEnd = 0;
+
+    // Reattach to the shell this panel had before a reload, if it is still running
+    const reattach = async (): Promise<string | null> => {
+        const previous = sessionStorage.getItem(storageKey(projectId, initialCommand));
+        if (!previous) return null;
+        const shells = await invokeCommand<TerminalInfo[]>('list_shells', { projectId });
+        if (!shells.some(s => s.session_id === previous)) {
+            sessionStorage.removeItem(storageKey(projectId, initialCommand));
+            return null;
+        }
+        const attachment = await invokeCommand<TerminalAttachment>('attach_shell', { sessionId: previous });
+        term.write(attachment.scrollback);
+        scrollbackEnd = attachment.end;
+        return previous;
+    };
+
     // Connect to Backend
     const initTerminal = async () => {
+        // Subscribe before spawning or attaching so no output falls in between;
+        // events are held until we know which session is ours
+        let sessionId: string | null = null;
+        const pending: Array<{ data?: TerminalData, exit?: TerminalExit }> = [];
+
+        const handleData = (payload: TerminalData) => {
+            if (payload.session_id === sessionId && payload.end > scrollbackEnd) {
+                term.write(payload.data);
+            }
+        };
+        const handleExit = (payload: TerminalExit) => {
+            if (payload.session_id !== sessionId) return;
+            const code = payload.exit_code;
+            term.writeln(`\r\n\x1b[38;5;245m[Process exited${code === null ? '' : ` with code ${code}`}]\x1b[0m`);
+            sessionIdRef.current = null;
+            setSessionId(null);
+            sessionStorage.removeItem(storageKey(projectId, initialCommand));
+            onExit?.(code);
+        };
+
+        const unlistenData = await listen<TerminalData>('terminal_data', (event) => {
+            if (sessionId === null) pending.push({ data: event.payload });
+            else handleData(event.payload);
+        });
+        const unlistenExit = await listen<TerminalExit>('terminal_exit', (event) => {
+            if (sessionId === null) pending.push({ exit: event.payload });
+            else handleExit(event.payload);
+        });
+        const unlisten = () => {
+            unlistenData();
+            unlistenExit();
+        };
+
         try {
             term.writeln('\x1b[38;5;75m⚡ Switchboard Terminal connecting...\x1b[0m\r\n');
-            
-            const sessionId = await invokeCommand<string>('spawn_shell', { 
+
+            sessionId = await reattach() ?? await invokeCommand<string>('spawn_shell', {
                 projectId,
+                profileId,
                 initialCommand
             });
             sessionIdRef.current = sessionId;
+            setSessionId(sessionId);
+            sessionStorage.setItem(storageKey(projectId, initialCommand), sessionId);
             onSessionReady?.(sessionId);
 
-            const unlisten = await listen<{ session_id: string, data: string }>('terminal_data', (event) => {
-                if (event.payload.session_id === sessionId) {
-                    term.write(event.payload.data);
-                }
-            });
-            return unlisten;
+            for (const event of pending.splice(0)) {
+                if (event.data) handleData(event.data);
+                if (event.exit) handleExit(event.exit);
+            }
+
+            const { cols, rows } = term;
+            invokeCommand('resize_shell', { sessionId, cols, rows }).catch(console.error);
         } catch (err) {
             term.writeln(`\r\n\x1b[31mConnection failed: ${err}\x1b[0m`);
-            return () => {};
         }
+        return unlisten;
     };
 
     let cleanupListen: (() => void) | undefined;
@@ -118,8 +211,48 @@ export const TerminalPanel: React.FC<TerminalPanelProps> = ({ projectId, initial
   }, [projectId]); // logic depends on projectId, initialCommand is generally stable but we can add it
 
   return (
-    <div className="h-full w-full bg-[#18181b] p-1 relative rounded-md border border-zinc-800">
+    <>
+        <RecordButton sessionId={sessionId} />
         <div ref={terminalRef} className="absolute inset-2" />
+    </>
+  );
+};
+
+export const TerminalPanel: React.FC<TerminalPanelProps> = ({ projectId, initialCommand, profileId, onSessionReady, onExit }) => {
+  const [activeProfileId, setActiveProfileId] = useState(profileId);
+  const [generation, setGeneration] = useState(0);
+  const sessionIdRef = useRef<string | null>(null);
+
+  // The panel holds one shell, so a new terminal replaces the running one
+  const handleNewTerminal = async (profile: ShellProfile) => {
+    const running = sessionIdRef.current;
+    if (running) {
+      if (!window.confirm(`Close the running shell and start ${profile.name}?`)) return;
+      await invokeCommand('close_shell', { sessionId: running }).catch(console.error);
+      sessionIdRef.current = null;
+    }
+    sessionStorage.removeItem(storageKey(projectId, initialCommand));
+    setActiveProfileId(profile.id);
+    setGeneration(g => g + 1);
+  };
+
+  return (
+    <div className="h-full w-full bg-[#18181b] p-1 relative rounded-md border border-zinc-800">
+        <ShellProfilePicker projectId={projectId} onPick={handleNewTerminal} />
+        <ShellView
+          key={generation}
+          projectId={projectId}
+          initialCommand={initialCommand}
+          profileId={activeProfileId}
+          onSessionReady={(id) => {
+            sessionIdRef.current = id;
+            onSessionReady?.(id);
+          }}
+          onExit={(code) => {
+            sessionIdRef.current = null;
+            onExit?.(code);
+          }}
+        />
     </div>
   );
 };
@@ -0,0 +1,281 @@
+import React, { useEffect, useState } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { Button } from '../ui/button';
+import { Input } from '../ui/input';
+import { Label } from '../ui/label';
+import { Card, CardContent } from '../ui/card';
+import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogDescription, DialogFooter } from '../ui/dialog';
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '../ui/select';
+import { Network, Plus, Play, Square, Trash2 } from 'lucide-react';
+import { invokeCommand } from '../../lib/tauri';
+import { toast } from 'sonner';
+import { useAppStore } from '../../stores/useAppStore';
+import type { ProjectConnection, SshTunnel, TunnelKind, TunnelState, TunnelStatus } from '../../types';
+
+interface TunnelsPanelProps {
+    projectId: string;
+}
+
+const STATUS_STYLES: Record<TunnelStatus, string> = {
+    stopped: 'bg-zinc-500',
+    starting: 'bg-yellow-500 animate-pulse',
+    up: 'bg-green-500',
+    reconnecting: 'bg-orange-500 animate-pulse',
+    failed: 'bg-red-500',
+};
+
+const describe = (tunnel: SshTunnel) => {
+    const bind = `${tunnel.bind_address ? `${tunnel.bind_address}:` : ''}${tunnel.bind_port}`;
+    if (tunnel.kind === 'dynamic') return `SOCKS ${bind} via ${tunnel.host}`;
+    const target = `${tunnel.target_host}:${tunnel.target_port}`;
+    return tunnel.kind === 'local'
+        ? `${bind} → ${target} via ${tunnel.host}`
+        : `${tunnel.host}:${bind} → ${target}`;
+};
+
+export const TunnelsPanel: React.FC<TunnelsPanelProps> = ({ projectId }) => {
+    const { hosts, fetchHosts } = useAppStore();
+    const [tunnels, setTunnels] = useState<SshTunnel[]>([]);
+    const [states, setStates] = useState<Record<string, TunnelState>>({});
+    const [connections, setConnections] = useState<ProjectConnection[]>([]);
+    const [isAddOpen, setIsAddOpen] = useState(false);
+
+    // Form State
+    const [name, setName] = useState('');
+    const [host, setHost] = useState('');
+    const [kind, setKind] = useState<TunnelKind>('local');
+    const [bindPort, setBindPort] = useState('');
+    const [targetHost, setTargetHost] = useState('localhost');
+    const [targetPort, setTargetPort] = useState('');
+    const [connectionId, setConnectionId] = useState('none');
+
+    const fetchTunnels = async () => {
+        try {
+            const [data, tunnelStates] = await Promise.all([
+                invokeCommand<SshTunnel[]>('get_tunnels', { projectId }),
+                invokeCommand<TunnelState[]>('get_tunnel_states', { projectId }),
+            ]);
+            setTunnels(data);
+            setStates(Object.fromEntries(tunnelStates.map(s => [s.tunnel_id, s])));
+        } catch (e) {
+            console.error(e);
+            toast.error("Failed to load tunnels");
+        }
+    };
+
+    useEffect(() => {
+        fetchTunnels();
+        fetchHosts();
+        invokeCommand<ProjectConnection[]>('get_connections', { projectId })
+            .then(setConnections)
+            .catch(console.error);
+
+        const unlisten = listen<TunnelState & { project_id: string }>('tunnel_status', (event) => {
+            if (event.payload.project_id !== projectId) return;
+            setStates(prev => ({ ...prev, [event.payload.tunnel_id]: event.payload }));
+        });
+        return () => { unlisten.then(f => f()); };
+    }, [projectId]);
+
+    const resetForm = () => {
+        setName('');
+        setHost('');
+        setKind('local');
+        setBindPort('');
+        setTargetHost('localhost');
+        setTargetPort('');
+        setConnectionId('none');
+    };
+
+    const handleCreate = async () => {
+        try {
+            await invokeCommand('create_tunnel', {
+                projectId,
+                input: {
+                    name,
+                    host,
+                    kind,
+                    bind_address: null,
+                    bind_port: Number(bindPort),
+                    target_host: kind === 'dynamic' ? null : targetHost,
+                    target_port: kind === 'dynamic' ? null : Number(targetPort),
+                    connection_id: connectionId === 'none' ? null : connectionId,
+                    auto_reconnect: true,
+                },
+            });
+            toast.success("Tunnel saved");
+            setIsAddOpen(false);
+            resetForm();
+            fetchTunnels();
+        } catch (e) {
+            toast.error(`Failed to save tunnel: ${e}`);
+        }
+    };
+
+    const handleStart = async (id: string) => {
+        try {
+            const state = await invokeCommand<TunnelState>('start_tunnel', { id });
+            setStates(prev => ({ ...prev, [id]: state }));
+        } catch (e) {
+            toast.error(`Failed to start tunnel: ${e}`);
+        }
+    };
+
+    const handleStop = async (id: string) => {
+        try {
+            await invokeCommand('stop_tunnel', { id });
+        } catch (e) {
+            toast.error(`Failed to stop tunnel: ${e}`);
+        }
+    };
+
+    const handleDelete = async (id: string) => {
+        try {
+            await invokeCommand('delete_tunnel', { id });
+            fetchTunnels();
+        } catch (e) {
+            toast.error(`Failed to delete tunnel: ${e}`);
+        }
+    };
+
+    const canSave = name && host && Number(bindPort) > 0 && (kind === 'dynamic' || (targetHost && Number(targetPort) > 0));
+
+    return (
+        <div className="h-full flex flex-col p-4 space-y-4 overflow-hidden">
+            <div className="flex items-center justify-between shrink-0">
+                <div>
+                    <h2 className="text-lg font-semibold flex items-center gap-2">
+                        <Network className="w-5 h-5 text-primary" />
+                        SSH Tunnels
+                    </h2>
+                    <p className="text-sm text-muted-foreground">
+                        Port forwards through hosts from your ~/.ssh/config, reconnected automatically.
+                    </p>
+                </div>
+                <Button size="sm" className="gap-1" onClick={() => setIsAddOpen(true)}>
+                    <Plus className="w-4 h-4" />
+                    Add Tunnel
+                </Button>
+            </div>
+
+            <Dialog open={isAddOpen} onOpenChange={setIsAddOpen}>
+                <DialogContent>
+                    <DialogHeader>
+                        <DialogTitle>New Tunnel</DialogTitle>
+                        <DialogDescription>
+                            Runs <code>ssh -N</code> with the forward below. The host must log in without prompting (key or agent).
+                        </DialogDescription>
+                    </DialogHeader>
+                    <div className="grid grid-cols-2 gap-4 py-2">
+                        <div className="space-y-2 col-span-2">
+                            <Label>Name</Label>
+                            <Input placeholder="e.g. Staging Postgres" value={name} onChange={e => setName(e.target.value)} />
+                        </div>
+                        <div className="space-y-2">
+                            <Label>Host</Label>
+                            <Select value={host} onValueChange={setHost}>
+                                <SelectTrigger><SelectValue placeholder="Pick a host" /></SelectTrigger>
+                                <SelectContent>
+                                    {hosts.map(h => <SelectItem key={h.host} value={h.host}>{h.host}</SelectItem>)}
+                                </SelectContent>
+                            </Select>
+                        </div>
+                        <div className="space-y-2">
+                            <Label>Type</Label>
+                            <Select value={kind} onValueChange={(v) => setKind(v as TunnelKind)}>
+                                <SelectTrigger><SelectValue /></SelectTrigger>
+                                <SelectContent>
+                                    <SelectItem value="local">Local (-L)</SelectItem>
+                                    <SelectItem value="remote">Remote (-R)</SelectItem>
+                                    <SelectItem value="dynamic">Dynamic / SOCKS (-D)</SelectItem>
+                                </SelectContent>
+                            </Select>
+                        </div>
+                        <div className="space-y-2">
+                            <Label>{kind === 'remote' ? 'Remote Port' : 'Local Port'}</Label>
+                            <Input placeholder="15432" value={bindPort} onChange={e => setBindPort(e.target.value)} />
+                        </div>
+                        {kind !== 'dynamic' && (
+                            <>
+                                <div className="space-y-2">
+                                    <Label>Target Host</Label>
+                                    <Input value={targetHost} onChange={e => setTargetHost(e.target.value)} />
+                                </div>
+                                <div className="space-y-2">
+                                    <Label>Target Port</Label>
+                                    <Input placeholder="5432" value={targetPort} onChange={e => setTargetPort(e.target.value)} />
+                                </div>
+                            </>
+                        )}
+                        <div className="space-y-2 col-span-2">
+                            <Label>Database Connection</Label>
+                            <Select value={connectionId} onValueChange={setConnectionId}>
+                                <SelectTrigger><SelectValue /></SelectTrigger>
+                                <SelectContent>
+                                    <SelectItem value="none">None</SelectItem>
+                                    {connections.map(c => <SelectItem key={c.id} value={c.id}>{c.name}</SelectItem>)}
+                                </SelectContent>
+                            </Select>
+                            <p className="text-xs text-muted-foreground">The database panel brings this tunnel up before connecting.</p>
+                        </div>
+                    </div>
+                    <DialogFooter>
+                        <Button variant="outline" onClick={() => setIsAddOpen(false)}>Cancel</Button>
+                        <Button onClick={handleCreate} disabled={!canSave}>Save</Button>
+                    </DialogFooter>
+                </DialogContent>
+            </Dialog>
+
+            <div className="flex-1 overflow-y-auto space-y-3 p-1">
+                {tunnels.length === 0 && (
+                    <div className="text-center py-12 text-muted-foreground border-2 border-dashed rounded-lg">
+                        <Network className="w-8 h-8 mx-auto mb-2 opacity-50" />
+                        <p>No tunnels yet.</p>
+                    </div>
+                )}
+
+                {tunnels.map(tunnel => {
+                    const state = states[tunnel.id];
+                    const status = state?.status ?? 'stopped';
+                    const active = status !== 'stopped' && status !== 'failed';
+                    const connection = connections.find(c => c.id === tunnel.connection_id);
+                    return (
+                        <Card key={tunnel.id} className="group hover:border-primary/50 transition-colors">
+                            <CardContent className="p-4 flex items-center justify-between gap-4">
+                                <div className="flex items-center gap-3 min-w-0">
+                                    <span className={`w-2.5 h-2.5 rounded-full shrink-0 ${STATUS_STYLES[status]}`} title={status} />
+                                    <div className="flex flex-col min-w-0">
+                                        <span className="font-medium text-sm">
+                                            {tunnel.name}
+                                            {connection && <span className="ml-2 text-xs text-muted-foreground">for {connection.name}</span>}
+                                        </span>
+                                        <span className="text-xs text-muted-foreground font-mono truncate">{describe(tunnel)}</span>
+                                        {state?.message && status !== 'up' && (
+                                            <span className="text-xs text-red-400 truncate">
+                                                {state.message}{status === 'reconnecting' ? ` (attempt ${state.attempts})` : ''}
+                                            </span>
+                                        )}
+                                    </div>
+                                </div>
+                                <div className="flex items-center gap-1 shrink-0">
+                                    {active ? (
+                                        <Button variant="ghost" size="icon" className="h-8 w-8" onClick={() => handleStop(tunnel.id)}>
+                                            <Square className="w-4 h-4" />
+                                        </Button>
+                                    ) : (
+                                        <Button variant="ghost" size="icon" className="h-8 w-8" onClick={() => handleStart(tunnel.id)}>
+                                            <Play className="w-4 h-4" />
+                                        </Button>
+                                    )}
+                                    <Button variant="ghost" size="icon" className="h-8 w-8 opacity-0 group-hover:opacity-100" onClick={() => handleDelete(tunnel.id)}>
+                                        <Trash2 className="w-4 h-4 text-red-400" />
+                                    </Button>
+                                </div>
+                            </CardContent>
+                        </Card>
+                    );
+                })}
+            </div>
+        </div>
+    );
+};
@@ -10,6 +10,8 @@ interface AppState {
 
     fetchProjects: () => Promise<void>;
     fetchHosts: () => Promise<void>;
+    updateHost: (alias: string, host: SshHostModel) => Promise<void>;
+    deleteHost: (alias: string) => Promise<void>;
     createProject: (name: string, path: string, sshKeyPath?: string) => Promise<void>;
     updateProject: (id: string, name: string, path: string, sshKeyPath?: string) => Promise<void>;
     deleteProject: (id: string) => Promise<void>;
@@ -45,6 +47,28 @@ export const useAppStore = create<AppState>((set) => ({
         }
     },
 
+    updateHost: async (alias, host) => {
+        try {
+            await invokeCommand('update_ssh_host', { alias, host });
+            const hosts = await invokeCommand<SshHostModel[]>('get_ssh_hosts');
+            set({ hosts });
+        } catch (e) {
+            console.error('Failed to update host', e);
+            throw e;
+        }
+    },
+
+    deleteHost: async (alias) => {
+        try {
+            await invokeCommand('delete_ssh_host', { alias });
+            const hosts = await invokeCommand<SshHostModel[]>('get_ssh_hosts');
+            set({ hosts });
+        } catch (e) {
+            console.error('Failed to delete host', e);
+            throw e;
+        }
+    },
+
     createProject: async (name, path, sshKeyPath) => {
         set({ isLoading: true });
         try {
@@ -15,6 +15,8 @@ export interface SshHostModel {
     user?: string;
     port?: number;
     identity_file?: string;
+    proxy_jump?: string;
+    local_forwards?: string[]; // e.g. "8080 localhost:80"
 }
 
 export interface Snippet {
@@ -70,7 +72,181 @@ export interface ProjectConnection {
     project_id: string;
     name: string;
     kind: 'postgres' | 'mysql' | 'sqlite';
-    details: string; // JSON string
+    details: string; // JSON string, without the password
+    password_ref: string | null; // Keychain entry holding the password
     created_at: string;
     updated_at: string;
 }
+
+export interface DbConnectionState {
+    connection_id: string;
+    connected: boolean;
+    connected_at: number | null;
+    idle_secs: number;
+    latency_ms: number | null;
+    error: string | null;
+}
+
+// Rows of a running query, streamed as `query_page` events
+export interface QueryPage {
+    query_id: string;
+    result_set: number; // Index of the statement the rows belong to
+    columns: string[];
+    column_types: string[];
+    rows: any[][];
+    affected_rows: number;
+    done: boolean;
+    truncated: boolean; // Set on the last page when this result set hit the row cap
+}
+
+export interface QueryStats {
+    query_id: string;
+    rows_fetched: number;
+    affected_rows: number;
+    result_sets: number;
+    elapsed_ms: number;
+    truncated: boolean; // Some result set hit the row cap with more rows left
+    cancelled: boolean;
+    completed_statements: number; // When cancelled, the statements after these never ran or were cut short
+}
+
+export type TunnelKind = 'local' | 'remote' | 'dynamic';
+
+export interface SshTunnel {
+    id: string;
+    project_id: string;
+    name: string;
+    host: string; // Alias from ~/.ssh/config
+    kind: TunnelKind;
+    bind_address: string | null;
+    bind_port: number;
+    target_host: string | null;
+    target_port: number | null;
+    connection_id: string | null;
+    auto_reconnect: boolean;
+    created_at: string;
+    updated_at: string;
+}
+
+export type TunnelStatus = 'stopped' | 'starting' | 'up' | 'reconnecting' | 'failed';
+
+export interface TunnelState {
+    tunnel_id: string;
+    status: TunnelStatus;
+    message: string | null;
+    pid: number | null;
+    attempts: number;
+}
+
+export type SshKeyType = 'ed25519' | 'rsa';
+
+export interface SshKeyInfo {
+    name: string;
+    path: string;
+    key_type: string;
+    bits: number;
+    fingerprint: string;
+    comment: string | null;
+    encrypted: boolean;
+    passphrase_saved: boolean;
+    in_agent: boolean;
+}
+
+export type SftpEntryKind = 'file' | 'dir' | 'symlink' | 'other';
+
+export interface SftpEntry {
+    name: string;
+    path: string;
+    kind: SftpEntryKind;
+    size: number | null;
+    modified: number | null;
+    permissions: number | null;
+}
+
+export interface SftpProgress {
+    transfer_id: string;
+    host: string;
+    direction: 'download' | 'upload';
+    remote_path: string;
+    transferred: number;
+    total: number | null;
+    done: boolean;
+}
+
+// A saved way of starting a project shell
+export interface ShellProfile {
+    id: string;
+    project_id: string;
+    name: string;
+    program: string;
+    args: string; // JSON array
+    env: string; // JSON object
+    startup_script: string | null;
+    working_subdir: string | null;
+    is_default: boolean;
+}
+
+// Editable profile fields, also what `detect_shells` suggests
+export interface ShellProfileInput {
+    name: string;
+    program: string;
+    args: string[];
+    env: Record<string, string>;
+    startup_script: string | null;
+    working_subdir: string | null;
+    is_default: boolean;
+}
+
+export type RestartPolicy = 'never' | 'on-failure' | 'always';
+
+// A saved service of the project's stack
+export interface ProcessDefinition {
+    id: string;
+    project_id: string;
+    name: string;
+    command: string;
+    cwd: string | null;
+    env: string; // JSON object
+    autostart: boolean;
+    restart_policy: RestartPolicy;
+    max_restarts: number | null;
+    backoff_ms: number;
+    depends_on: string; // JSON array of definition names
+    ready_port: number | null;
+    ready_pattern: string | null;
+}
+
+// Editable definition fields
+export interface ProcessDefinitionInput {
+    name: string;
+    command: string;
+    cwd: string | null;
+    env: Record<string, string>;
+    autostart: boolean;
+    restart_policy: RestartPolicy;
+    max_restarts: number | null;
+    backoff_ms: number;
+    depends_on: string[];
+    ready_port: number | null;
+    ready_pattern: string | null;
+}
+
+export type StackProgressState = 'starting' | 'ready' | 'failed' | 'stopping' | 'stopped';
+
+export interface StackProgress {
+    project_id: string;
+    definition_id: string;
+    name: string;
+    state: StackProgressState;
+    error: string | null;
+}
+
+export type SshErrorKind = 'unknown_host_key' | 'changed_host_key' | 'other';
+
+// What ssh_connect and trust_ssh_host reject with
+export interface SshCommandError {
+    kind: SshErrorKind;
+    alias: string | null;
+    fingerprint: string | null;
+    message: string;
+}